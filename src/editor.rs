@@ -16,42 +16,154 @@ mod commandbar;
 use commandbar::CommandBar;
 mod line;
 use line::Line;
+mod annotation;
+use annotation::{Annotation, AnnotationType};
+mod annotatedstring;
+use annotatedstring::AnnotatedString;
+mod pattern;
+use pattern::Pattern;
+mod config;
+mod keymap;
+use keymap::{keys_to_string, KeyResult, Keymap, Profile};
+use config::Config;
+mod vim;
+use vim::{Action as VimAction, Mode as VimMode, Motion, Register, Vim};
+mod helpscreen;
+use helpscreen::HelpScreen;
+mod indentation;
+use indentation::Indentation;
+mod clipboard;
+use clipboard::{Clipboard, ClipboardBackend};
+mod killring;
+use killring::KillRing;
+mod goto;
+use goto::{FileArgument, GotoTarget};
+pub use goto::parse_arguments;
 mod terminal;
 mod view;
 mod statusbar;
 use statusbar::StatusBar;
-use view::View;
-use terminal::Terminal;
-use crossterm::event::{read, Event, KeyEvent, KeyEventKind::{self},};
+mod layout;
+use layout::{Direction, Layout, Pane, PaneId, SplitDirection};
+use view::{flush_swap_files, Buffer, ExternalChange, SharedBuffer, View};
+use terminal::{set_theme, Terminal};
+use crossterm::event::{poll, read, Event, KeyEvent, KeyEventKind::{self},};
 use std::{
-    env,
+    cell::RefCell,
+    env, fs,
     io::Error,
+    mem,
     panic::{set_hook, take_hook},
+    rc::Rc,
+    thread,
+    time::Duration,
 };
 use self::command::{
-    Command::{self, Edit, Move, System},
+    ClipboardCommand::{Copy, Cut, KillLine, Paste, Yank, YankPop},
+    Command::{self, Clipboard as ClipboardAction, Edit, Move, Select, System, Window},
     Edit::InsertNewline,
-    System::{Dismiss, Quit, Resize, Save},
-
-
-
+    Move::{Down, Left, Right, Up},
+    System::{
+        CycleLineNumbers, Dismiss, NextBuffer, PickBuffer, PreviousBuffer, Quit, Replace, Resize,
+        GotoLine, Help, Save, Search, SetMark, ToggleAutoIndent, ToggleLineEnding,
+        ToggleSoftWrap,
+    },
+    WindowCommand,
 };
 
-const QUIT_TIMES: u8 = 3;
+/// inactivité après laquelle le fichier d'échange est écrit
+const SWAP_IDLE_DELAY: Duration = Duration::from_secs(2);
+/// commandes rappelées par l'aide affichée au démarrage, dans l'ordre, avec les raccourcis
+/// du keymap ; celles qui n'ont pas de raccourci sont omises
+const HELP_COMMANDS: [(Command, &str); 22] = [
+    (System(Help), "key bindings"),
+    (System(Search), "find"),
+    (System(Replace), "replace"),
+    (System(Save), "save"),
+    (System(Quit), "quit"),
+    (Edit(command::Edit::Undo), "undo"),
+    (Edit(command::Edit::Redo), "redo"),
+    (ClipboardAction(Copy), "copy"),
+    (ClipboardAction(Cut), "cut"),
+    (ClipboardAction(Paste), "paste"),
+    (ClipboardAction(KillLine), "kill line"),
+    (ClipboardAction(Yank), "yank"),
+    (System(SetMark), "mark"),
+    (System(ToggleLineEnding), "LF/CRLF"),
+    (System(PickBuffer), "pick buffer"),
+    (System(GotoLine), "go to line"),
+    (System(CycleLineNumbers), "line numbers"),
+    (System(ToggleSoftWrap), "soft wrap"),
+    (System(ToggleAutoIndent), "auto-indent"),
+    (Window(WindowCommand::Split(SplitDirection::Horizontal)), "split"),
+    (Window(WindowCommand::FocusNext), "switch window"),
+    (Window(WindowCommand::Close), "close window"),
+];
+/// commandes du mode normal de Vim, qui ne passent pas par le keymap
+const VIM_HELP_MESSAGE: &str = "i/a/o = insert | Esc = normal mode | v = visual | :w = save | :q = quit | :wq = save and quit | u/Ctrl-R = undo/redo";
 
 pub const NAME: &str = env!("CARGO_PKG_NAME");
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// type de prompt affiché dans la barre de commande
+#[derive(Eq, PartialEq, Default, Clone, Copy)]
+enum PromptType {
+    Search,
+    Save,
+    ReplaceFind,
+    ReplaceWith,
+    ReplaceConfirm,
+    RecoverSwap,
+    ExternalChange,
+    PickBuffer,
+    /// commande `:` du mode Vim
+    VimCommand,
+    GotoLine,
+    /// le dossier du fichier à sauvegarder n'existe pas
+    CreateDirectory,
+    #[default]
+    None,
+}
+
+impl PromptType {
+    fn is_none(self) -> bool {
+        self == Self::None
+    }
+}
+
 #[derive(Default)]
 pub struct Editor {
     should_quit:bool,
+    /// vue de la fenêtre active
     view:View,
+    /// ligne de status de la fenêtre active
     status_bar: StatusBar,
+    /// découpage de l'écran en fenêtres, garde les fenêtres inactives
+    layout: Layout,
+    /// buffers ouverts, dans l'ordre d'ouverture
+    buffers: Vec<SharedBuffer>,
     title:String,
     message_bar: MessageBar,
     terminal_size: Size,
     quit_times: u8,
-    command_bar: Option<CommandBar>,
+    command_bar: CommandBar,
+    prompt_type: PromptType,
+    replace_pattern: Option<Pattern>,
+    /// nom sous lequel sauvegarder une fois son dossier créé, `None` pour le fichier du buffer
+    save_target: Option<String>,
+    clipboard: Clipboard,
+    /// textes coupés et copiés, pour `yank` et `yank_pop`
+    kill_ring: KillRing,
+    /// commande précédente : les `kill_line` successifs s'ajoutent, `yank_pop` suit un `yank`
+    previous_command: Option<Command>,
+    config: Config,
+    keymap: Keymap,
+    /// liste des raccourcis, affichée à la place des fenêtres quand elle est ouverte
+    help_screen: Option<HelpScreen>,
+    /// édition modale à la Vim, quand la configuration la choisit
+    vim: Option<Vim>,
+    /// mode dont le curseur a pris la forme, pour ne la changer qu'avec le mode
+    caret_mode: Option<VimMode>,
 }
 
 impl Editor {
@@ -59,52 +171,349 @@ impl Editor {
     pub fn new() -> Result<Self,Error> {
         let current_hook = take_hook();
         set_hook(Box::new(move | panic_info | { // closure
+            flush_swap_files();
             let _ = Terminal::terminate();
             current_hook(panic_info);
         }));
         Terminal::initialize()?;
+        let (mut config, config_errors) = Config::load();
+        set_theme(mem::take(&mut config.theme));
         let mut editor = Self::default();
+        editor.keymap = mem::take(&mut config.keymap);
+        editor.buffers.push(editor.view.buffer());
+        editor.clipboard = Clipboard::new(config.clipboard);
+        editor.message_bar.set_timeout(config.message_timeout);
+        editor.view.set_backup_on_save(config.backup_on_save);
+        editor.view.set_indentation(config.indentation);
+        editor.view.set_line_numbers(config.line_numbers);
+        editor.view.set_soft_wrap(config.soft_wrap);
+        if config.profile == Profile::Vim {
+            editor.vim = Some(Vim::default());
+        }
+        editor.config = config;
         let size = Terminal::size().unwrap_or_default();
         editor.resize(size);
-        editor
-        .message_bar
-        .update_message("HELP: Ctrl-S = save | Ctrl-Q = quit");
-        let args: Vec<String> = env::args().collect();
-        if let Some(file_name) = args.get(1) {
-            editor.view.load(file_name);
+        if config_errors.is_empty() {
+            let hint = editor.startup_hint();
+            editor.message_bar.update_message(&hint);
+        } else {
+            editor
+                .message_bar
+                .update_message(&format!("ERR: {}", config_errors.join(" | ")));
+        }
+        for argument in parse_arguments(env::args().skip(1)) {
+            if let Err(err) = editor.open_file(&argument) {
+                editor
+                    .message_bar
+                    .update_message(&format!("ERR: Could not open file {}: {err}", argument.path));
+            }
+        }
+        if editor.view.has_leftover_swap() {
+            editor.set_prompt(PromptType::RecoverSwap);
         }
         editor.refresh_status();
         Ok(editor)
     }
 
+    /// aide affichée au démarrage : les raccourcis des commandes principales, lus dans le
+    /// keymap pour suivre le profil et la configuration. En mode Vim, les commandes du mode
+    /// normal sont rappelées à la place, le keymap ne donne que la touche de l'aide
+    /// (certains de ses raccourcis, comme Ctrl-R, sont repris par Vim).
+    fn startup_hint(&self) -> String {
+        let vim = self.config.profile == Profile::Vim;
+        let shortcuts = HELP_COMMANDS
+            .iter()
+            .filter(|(command, _)| !vim || *command == System(Help))
+            .filter_map(|(command, description)| {
+                let keys = self.keymap.keys_for(*command)?;
+                Some(format!("{} = {description}", keys_to_string(keys)))
+            });
+        let entries: Vec<String> = if vim {
+            std::iter::once(VIM_HELP_MESSAGE.to_string()).chain(shortcuts).collect()
+        } else {
+            shortcuts.collect()
+        };
+        format!("HELP: {}", entries.join(" | "))
+    }
+
+    /// Ouvre un fichier dans un nouveau buffer, placé après les autres, à la position demandée.
+    /// Le premier fichier prend la place du buffer vide du démarrage.
+    fn open_file(&mut self, argument: &FileArgument) -> Result<(), Error> {
+        if !self.view.is_file_loaded() && self.buffers.len() == 1 {
+            self.view.load(&argument.path)?;
+            self.apply_indentation(&mut self.view.buffer().borrow_mut());
+            if let Some(target) = argument.target {
+                self.view.go_to(target);
+            }
+            return Ok(());
+        }
+        let mut buffer = Buffer::open(&argument.path)?;
+        buffer.backup_on_save = self.config.backup_on_save;
+        self.apply_indentation(&mut buffer);
+        if let Some(target) = argument.target {
+            buffer.last_location = buffer.goto_location(target, buffer.last_location);
+        }
+        self.buffers.push(Rc::new(RefCell::new(buffer)));
+        Ok(())
+    }
+
+    /// donne au buffer les réglages d'indentation de son type de fichier
+    fn apply_indentation(&self, buffer: &mut Buffer) {
+        buffer.set_indentation(Indentation {
+            auto_indent: self.config.auto_indent_for(buffer.syntax_name()),
+            ..self.config.indentation
+        });
+    }
+
+    /// active ou désactive l'indentation automatique pour le type de fichier du buffer actif
+    fn toggle_auto_indent(&mut self) {
+        let buffer = self.view.buffer();
+        let file_type = buffer.borrow().syntax_name();
+        let auto_indent = !buffer.borrow().indentation().auto_indent;
+        self.config
+            .auto_indent_by_file_type
+            .insert(file_type.to_lowercase(), auto_indent);
+        for buffer in &self.buffers {
+            if buffer.borrow().syntax_name() == file_type {
+                self.apply_indentation(&mut buffer.borrow_mut());
+            }
+        }
+        let state = if auto_indent { "on" } else { "off" };
+        self.message_bar
+            .update_message(&format!("Auto-indent {state} for {file_type} files."));
+    }
+
+    /// position du buffer affiché par une vue dans la liste des buffers
+    fn buffer_index_of(buffers: &[SharedBuffer], view: &View) -> usize {
+        buffers
+            .iter()
+            .position(|buffer| view.shows(buffer))
+            .unwrap_or_default()
+    }
+
+    /// Affiche dans la fenêtre active le buffer d'index donné (dans l'ordre d'ouverture)
+    fn switch_to_buffer(&mut self, index: usize) {
+        let Some(buffer) = self.buffers.get(index).map(Rc::clone) else {
+            return;
+        };
+        if self.view.shows(&buffer) {
+            return;
+        }
+        self.view.show_buffer(buffer);
+        self.refresh_status();
+        let status = self.current_status();
+        self.message_bar.update_message(&format!(
+            "Buffer {}: {}",
+            status.buffer_indicator_to_string(),
+            status.file_name
+        ));
+        if self.view.has_leftover_swap() {
+            self.set_prompt(PromptType::RecoverSwap);
+        } else {
+            self.handle_focus_gained();
+        }
+    }
+
+    /// Affiche le buffer suivant, ou le premier après le dernier
+    fn next_buffer(&mut self) {
+        let next = Self::buffer_index_of(&self.buffers, &self.view).saturating_add(1);
+        self.switch_to_buffer(if next < self.buffers.len() { next } else { 0 });
+    }
+
+    /// Affiche le buffer précédent, ou le dernier avant le premier
+    fn previous_buffer(&mut self) {
+        let previous = Self::buffer_index_of(&self.buffers, &self.view)
+            .checked_sub(1)
+            .unwrap_or_else(|| self.buffers.len().saturating_sub(1));
+        self.switch_to_buffer(previous);
+    }
+
     /// Redimensionne l'éditeur
     fn resize(&mut self, size: Size) {
         self.terminal_size = size;
-        self.view.resize(Size {
-            height: size.height.saturating_sub(2),
+        self.layout.set_size(Size {
+            height: size.height.saturating_sub(1),
             width: size.width,
         });
+        self.resize_panes();
+        if let Some(help_screen) = &mut self.help_screen {
+            help_screen.resize(Size {
+                height: size.height.saturating_sub(1),
+                width: size.width,
+            });
+        }
         self.message_bar.resize(Size {
             height: 1,
             width: size.width,
         });
-        self.status_bar.resize(Size {
+        self.command_bar.resize(Size {
             height: 1,
             width: size.width,
         });
-        if let Some(command_bar) = &mut self.command_bar {
-            command_bar.resize(Size {
-                height: 1,
-                width: size.width,
-            });
+    }
+
+    // region: windows
+
+    /// vue et ligne de status d'une fenêtre, active ou non
+    fn pane_parts(&mut self, id: PaneId) -> Option<(&mut View, &mut StatusBar)> {
+        if id == self.layout.focused() {
+            Some((&mut self.view, &mut self.status_bar))
+        } else {
+            self.layout
+                .pane_mut(id)
+                .map(|pane| (&mut pane.view, &mut pane.status_bar))
+        }
+    }
+
+    /// donne à chaque fenêtre la taille de sa zone : la vue, puis la ligne de status en bas
+    fn resize_panes(&mut self) {
+        for (id, rect) in self.layout.rects() {
+            if let Some((view, status_bar)) = self.pane_parts(id) {
+                view.resize(Size {
+                    height: rect.size.height.saturating_sub(1),
+                    width: rect.size.width,
+                });
+                status_bar.resize(Size {
+                    height: 1,
+                    width: rect.size.width,
+                });
+            }
+        }
+    }
+
+    /// dessine chaque fenêtre dans sa zone
+    fn render_panes(&mut self) {
+        for (id, rect) in self.layout.rects() {
+            let status_origin = Position {
+                x: rect.origin.x,
+                y: rect
+                    .origin
+                    .y
+                    .saturating_add(rect.size.height.saturating_sub(1)),
+            };
+            if let Some((view, status_bar)) = self.pane_parts(id) {
+                if rect.size.height > 1 {
+                    view.render(rect.origin);
+                }
+                status_bar.render(status_origin);
+            }
+        }
+        self.layout.render_separators();
+    }
+
+    /// retire la fenêtre active de l'éditeur, pour la confier au découpage
+    fn take_focused_pane(&mut self, replacement: View) -> Pane {
+        Pane {
+            view: mem::replace(&mut self.view, replacement),
+            status_bar: mem::take(&mut self.status_bar),
+        }
+    }
+
+    fn set_focused_pane(&mut self, pane: Pane) {
+        self.view = pane.view;
+        self.status_bar = pane.status_bar;
+        self.refresh_status();
+        // le buffer de cette fenêtre a pu changer sur le disque pendant qu'elle était inactive
+        self.handle_focus_gained();
+    }
+
+    /// position du curseur de la fenêtre active à l'écran
+    fn caret_screen_position(&self) -> Position {
+        let origin = self.layout.focused_rect().origin;
+        let caret = self.view.caret_position();
+        Position {
+            x: origin.x.saturating_add(caret.x),
+            y: origin.y.saturating_add(caret.y),
+        }
+    }
+
+    fn handle_window_command(&mut self, command: WindowCommand) {
+        match command {
+            WindowCommand::Split(direction) => self.split_pane(direction),
+            WindowCommand::Focus(direction) => {
+                if let Some(id) = self.layout.neighbor(direction, self.caret_screen_position()) {
+                    self.focus_pane(id);
+                }
+            }
+            WindowCommand::FocusNext => self.focus_pane(self.layout.next_pane()),
+            WindowCommand::Grow | WindowCommand::Shrink => {
+                if self
+                    .layout
+                    .resize_focused(matches!(command, WindowCommand::Grow))
+                {
+                    self.resize_panes();
+                }
+            }
+            WindowCommand::Close => self.close_pane(),
+        }
+    }
+
+    /// découpe la fenêtre active : la nouvelle fenêtre affiche le même buffer et devient active
+    fn split_pane(&mut self, direction: SplitDirection) {
+        if !self.layout.can_split(direction) {
+            self.message_bar
+                .update_message("Not enough room to split the window.");
+            return;
+        }
+        let new_view = self.view.split();
+        let current = self.take_focused_pane(new_view);
+        self.layout.split(direction, current);
+        self.resize_panes();
+        self.refresh_status();
+    }
+
+    fn focus_pane(&mut self, id: PaneId) {
+        if id == self.layout.focused() {
+            return;
         }
+        let current = self.take_focused_pane(View::default());
+        let pane = self.layout.focus(id, current);
+        self.set_focused_pane(pane);
     }
 
+    /// ferme la fenêtre active, son buffer reste ouvert
+    fn close_pane(&mut self) {
+        if let Some(pane) = self.layout.close() {
+            self.set_focused_pane(pane);
+            self.resize_panes();
+        } else {
+            self.message_bar
+                .update_message("Cannot close the last window.");
+        }
+    }
+
+    /// passe au mode de numéros de ligne suivant, dans toutes les fenêtres
+    fn cycle_line_numbers(&mut self) {
+        let line_numbers = self.view.line_numbers().next();
+        self.view.set_line_numbers(line_numbers);
+        for pane in self.layout.panes_mut() {
+            pane.view.set_line_numbers(line_numbers);
+        }
+        self.message_bar
+            .update_message(&format!("Line numbers: {line_numbers}."));
+    }
+
+    /// active ou désactive le retour à la ligne automatique, dans toutes les fenêtres
+    fn toggle_soft_wrap(&mut self) {
+        let soft_wrap = !self.view.soft_wrap();
+        self.view.set_soft_wrap(soft_wrap);
+        for pane in self.layout.panes_mut() {
+            pane.view.set_soft_wrap(soft_wrap);
+        }
+        self.message_bar.update_message(if soft_wrap {
+            "Soft wrap on."
+        } else {
+            "Soft wrap off."
+        });
+    }
+
+    // endregion
+
     /// Rafraîchit le status
     pub fn refresh_status(&mut self) {
-        let status = self.view.get_status();
+        let status = self.current_status();
         let title = format!("{} - {NAME}", status.file_name);
-        self.status_bar.update_status(status);
+        self.update_panes();
 
         if title != self.title && matches!(Terminal::set_title(&title), Ok(())) {
             self.title = title;
@@ -118,8 +527,13 @@ impl Editor {
             if self.should_quit {
                 break;
             }
-            match read() {
-                Ok(event) => self.evaluate_event(event),
+            // sans activité, on en profite pour écrire le fichier d'échange
+            match poll(SWAP_IDLE_DELAY).and_then(|has_event| has_event.then(read).transpose()) {
+                Ok(Some(event)) => {
+                    self.evaluate_event(event);
+                    self.update_swaps(false);
+                }
+                Ok(None) => self.update_swaps(true),
                 #[cfg(debug_assertions)]
                 Err(err) => {
                     panic!("Could not read event: {err:?}");
@@ -130,8 +544,44 @@ impl Editor {
                 }
             }
 
-            let status = self.view.get_status();
-            self.status_bar.update_status(status);
+            self.update_panes();
+        }
+    }
+
+    /// tient à jour les fichiers d'échange de tous les buffers
+    fn update_swaps(&mut self, force: bool) {
+        for buffer in &self.buffers {
+            buffer.borrow_mut().update_swap(force);
+        }
+    }
+
+    /// status du buffer affiché par une vue, avec sa position dans la liste des buffers
+    fn status_of(buffers: &[SharedBuffer], view: &View) -> DocumentStatus {
+        DocumentStatus {
+            buffer_index: Self::buffer_index_of(buffers, view),
+            buffer_count: buffers.len(),
+            ..view.get_status()
+        }
+    }
+
+    /// status du buffer de la fenêtre active, avec le mode Vim
+    fn current_status(&self) -> DocumentStatus {
+        DocumentStatus {
+            mode: self.vim_mode().map(VimMode::name),
+            ..Self::status_of(&self.buffers, &self.view)
+        }
+    }
+
+    /// met les fenêtres à jour après une modification, qui a pu toucher un buffer
+    /// affiché dans plusieurs fenêtres
+    fn update_panes(&mut self) {
+        self.view.sync_with_buffer(true);
+        let status = self.current_status();
+        self.status_bar.update_status(status);
+        for pane in self.layout.panes_mut() {
+            pane.view.sync_with_buffer(false);
+            pane.status_bar
+                .update_status(Self::status_of(&self.buffers, &pane.view));
         }
     }
 
@@ -141,17 +591,206 @@ impl Editor {
         let should_process = match &event {
             Event::Key(KeyEvent { kind,..}) => kind == &KeyEventKind::Press,
             Event::Resize(_,_) => true,
+            Event::FocusGained => {
+                self.handle_focus_gained();
+                false
+            }
+            Event::Paste(text) => {
+                self.handle_paste(text);
+                false
+            }
             _ => false,
 
         };
 
         if should_process {
-            if let Ok(command) = Command::try_from(event) {
+            let command = match &event {
+                // les prompts et l'aide reçoivent les touches sans passer par le mode Vim
+                Event::Key(key_event)
+                    if self.vim.is_some()
+                        && self.prompt_type.is_none()
+                        && self.help_screen.is_none() =>
+                {
+                    self.handle_vim_key(key_event);
+                    None
+                }
+                Event::Key(key_event) => self.resolve_key(key_event),
+                _ => Command::try_from(event).ok(),
+            };
+            if let Some(command) = command {
                 self.process_command(command);
             }
         }
     }
     
+    /// commande associée à une touche pressée. Le début d'une suite de touches est affiché
+    /// en attendant la suite, et une touche sans raccourci le signale.
+    fn resolve_key(&mut self, key_event: &KeyEvent) -> Option<Command> {
+        match self.keymap.resolve(key_event) {
+            KeyResult::Command(command) => Some(command),
+            KeyResult::Pending(keys) => {
+                self.message_bar
+                    .update_message(&format!("{}-", keys_to_string(&keys)));
+                None
+            }
+            KeyResult::Unbound(keys) => {
+                self.message_bar
+                    .update_message(&format!("{} is not bound.", keys_to_string(&keys)));
+                None
+            }
+        }
+    }
+
+    // region: vim
+
+    /// mode Vim affiché ; la barre de commande `:` a son propre mode
+    fn vim_mode(&self) -> Option<VimMode> {
+        let vim = self.vim.as_ref()?;
+        Some(if self.prompt_type == PromptType::VimCommand {
+            VimMode::CommandLine
+        } else {
+            vim.mode()
+        })
+    }
+
+    /// traite une touche en mode Vim, en exécutant dans l'ordre les actions produites
+    fn handle_vim_key(&mut self, key_event: &KeyEvent) {
+        let Some(vim) = &mut self.vim else {
+            return;
+        };
+        for action in vim.handle_key(key_event) {
+            self.apply_vim_action(action);
+        }
+        if self.prompt_type.is_none()
+            && matches!(self.vim_mode(), Some(VimMode::Normal | VimMode::Visual))
+        {
+            self.view.snap_caret_to_character();
+        }
+    }
+
+    fn apply_vim_action(&mut self, action: VimAction) {
+        match action {
+            VimAction::Key(key_event) => {
+                if let Some(command) = self.resolve_key(&key_event) {
+                    self.process_command(command);
+                }
+            }
+            VimAction::Shortcut(key_event) => {
+                if let Some(command @ (Command::System(_) | Command::Window(_))) =
+                    self.resolve_key(&key_event)
+                {
+                    self.process_command(command);
+                }
+            }
+            VimAction::Command(command) => self.process_command(command),
+            VimAction::Undo(count) => {
+                for _ in 0..count {
+                    self.process_command(Command::Edit(command::Edit::Undo));
+                }
+            }
+            VimAction::Redo(count) => {
+                for _ in 0..count {
+                    self.process_command(Command::Edit(command::Edit::Redo));
+                }
+            }
+            VimAction::Move(motion, count) => self.view.move_by_motion(motion, count),
+            VimAction::Operate(operator, motion, count) => {
+                let register = self.view.apply_operator(operator, motion, count);
+                self.set_vim_register(register);
+            }
+            VimAction::OperateLines(operator, count) => {
+                let register = self.view.apply_operator_to_lines(operator, count);
+                self.set_vim_register(register);
+            }
+            VimAction::OperateSelection(operator) => {
+                let register = self.view.apply_operator_to_selection(operator);
+                self.set_vim_register(register);
+            }
+            VimAction::Insert(position) => self.view.start_insert(position),
+            VimAction::LeaveInsert => self.view.leave_insert(),
+            VimAction::Put { before, count } => {
+                let Some(vim) = &self.vim else {
+                    return;
+                };
+                // tant que rien n'a été copié, `p` colle le presse-papier
+                if !vim.register.text.is_empty() {
+                    self.view.put(&vim.register, before, count);
+                } else if let Some(text) = self.clipboard.get() {
+                    let register = Register {
+                        text,
+                        linewise: false,
+                    };
+                    self.view.put(&register, before, count);
+                }
+            }
+            VimAction::StartVisual => self.view.start_visual(),
+            VimAction::EndVisual => self.view.end_visual(),
+            VimAction::CommandLine => self.set_prompt(PromptType::VimCommand),
+        }
+    }
+
+    /// garde le texte supprimé ou copié par un opérateur, pour `p`
+    fn set_vim_register(&mut self, register: Option<Register>) {
+        if let (Some(vim), Some(register)) = (&mut self.vim, register) {
+            vim.register = register;
+        }
+    }
+
+    /// Traite une édition pendant la saisie d'une commande `:`
+    fn process_command_during_vim_command(&mut self, command: command::Edit) {
+        if matches!(command, InsertNewline) {
+            let value = self.command_bar.value();
+            self.set_prompt(PromptType::None);
+            self.execute_vim_command(value.trim());
+        } else if matches!(command, command::Edit::DeleteBackward)
+            && self.command_bar.value().is_empty()
+        {
+            // effacer le `:` ferme la barre de commande
+            self.set_prompt(PromptType::None);
+        } else {
+            self.command_bar.handle_edit_command(command);
+        }
+    }
+
+    /// exécute `:w [fichier]`, `:q`, `:q!`, `:wq`, `:x`, ou `:42` pour aller à une ligne
+    fn execute_vim_command(&mut self, command: &str) {
+        let (name, argument) = command
+            .split_once(' ')
+            .map_or((command, ""), |(name, argument)| (name, argument.trim()));
+        match name {
+            "" => {}
+            "w" if argument.is_empty() => self.handle_save(),
+            "w" => self.save(Some(argument)),
+            "q" => self.quit_if_saved(),
+            "q!" | "qa!" => self.should_quit = true,
+            "wq" | "x" => {
+                self.handle_save();
+                // sans nom de fichier, le prompt de sauvegarde s'ouvre ; en cas d'erreur, on reste
+                if self.prompt_type.is_none() && !self.view.get_status().is_modified {
+                    self.quit_if_saved();
+                }
+            }
+            _ => match name.parse::<usize>() {
+                Ok(line) => self.view.move_by_motion(Motion::GotoLine(line), 1),
+                Err(_) => self
+                    .message_bar
+                    .update_message(&format!("Not an editor command: {command}")),
+            },
+        }
+    }
+
+    /// `:q` ne quitte pas tant qu'un buffer a des modifications
+    fn quit_if_saved(&mut self) {
+        if self.modified_buffer_count() == 0 {
+            self.should_quit = true;
+        } else {
+            self.message_bar
+                .update_message("No write since last change (add ! to override).");
+        }
+    }
+
+    // endregion
+
     /// Rafraîchit l'écran
     fn refresh_screen(&mut self) {
         if self.terminal_size.height == 0 || self.terminal_size.width == 0 {
@@ -159,28 +798,39 @@ impl Editor {
         }
         let bottom_bar_row = self.terminal_size.height.saturating_sub(1);
         let _ = Terminal::hide_cursor();
-        if let Some(command_bar) = &mut self.command_bar {
-            command_bar.render(bottom_bar_row);
+        if self.prompt_type.is_none() {
+            self.message_bar.render(Position {
+                x: 0,
+                y: bottom_bar_row,
+            });
         } else {
-            self.message_bar.render(bottom_bar_row);
+            self.command_bar.render(Position {
+                x: 0,
+                y: bottom_bar_row,
+            });
         }
         if self.terminal_size.height > 1 {
-            self.status_bar
-                .render(self.terminal_size.height.saturating_sub(2));
-        }
-        if self.terminal_size.height > 2 {
-            self.view.render(0);
+            if let Some(help_screen) = &mut self.help_screen {
+                help_screen.render(Position::default());
+            } else {
+                self.render_panes();
+            }
         }
 
-        let new_caret_pos = if let Some(command_bar) = &self.command_bar {
+        let new_caret_pos = if self.prompt_type.is_none() {
+            self.caret_screen_position()
+        } else {
             Position {
                 y: bottom_bar_row,
-                x: command_bar.caret_position_col(),
+                x: self.command_bar.caret_position_col(),
             }
-        } else {
-            self.view.caret_position()
         };
 
+        if let Some(mode) = self.vim_mode().filter(|mode| Some(*mode) != self.caret_mode) {
+            if Terminal::set_caret_style(mode.caret_style()).is_ok() {
+                self.caret_mode = Some(mode);
+            }
+        }
         let _ = Terminal::move_cursor_to(new_caret_pos);
         let _ = Terminal::show_cursor();
         let _ = Terminal::execute();
@@ -188,9 +838,13 @@ impl Editor {
 
     /// Traite une commande
     fn process_command(&mut self, command: Command) {
+        if self.help_screen.is_some() && !matches!(command, System(Resize(_))) {
+            self.process_command_during_help(command);
+            return;
+        }
         match command {
             System(Quit) => {
-                if self.command_bar.is_none() {
+                if self.prompt_type.is_none() {
                     self.handle_quit();
                 }
             }
@@ -200,68 +854,615 @@ impl Editor {
 
         match command {
             System(Quit | Resize(_)) => {} // already handled above 1Has a conversation.
-            System(Save) => {
-                if self.command_bar.is_none() {
-                    self.handle_save();
+            System(Dismiss) => self.handle_dismiss(),
+            System(system_command) => {
+                if self.prompt_type.is_none() {
+                    self.handle_system_command(system_command);
                 }
             }
-            System(Dismiss) => {
-                if self.command_bar.is_some() {
-                    self.dismiss_prompt();
-                    self.message_bar.update_message("Save aborted.");
+            Edit(edit_command) => match self.prompt_type {
+                PromptType::None => self.view.handle_edit_command(edit_command),
+                PromptType::Save => self.process_command_during_save(edit_command),
+                PromptType::Search => self.process_command_during_search(edit_command),
+                PromptType::ReplaceFind | PromptType::ReplaceWith => {
+                    self.process_command_during_replace_prompt(edit_command);
+                }
+                PromptType::ReplaceConfirm => self.process_command_during_replace_confirm(edit_command),
+                PromptType::RecoverSwap => self.process_command_during_recover(edit_command),
+                PromptType::ExternalChange => {
+                    self.process_command_during_external_change(edit_command);
+                }
+                PromptType::PickBuffer => self.process_command_during_pick_buffer(edit_command),
+                PromptType::VimCommand => self.process_command_during_vim_command(edit_command),
+                PromptType::GotoLine => self.process_command_during_goto_line(edit_command),
+                PromptType::CreateDirectory => {
+                    self.process_command_during_create_directory(edit_command);
+                }
+            },
+            Move(move_command) => match self.prompt_type {
+                PromptType::None => self.view.handle_move_command(move_command),
+                PromptType::Search => match move_command {
+                    Right | Down => self.view.search_next(),
+                    Left | Up => self.view.search_prev(),
+                    _ => {}
+                },
+                PromptType::Save
+                | PromptType::ReplaceFind
+                | PromptType::ReplaceWith
+                | PromptType::ReplaceConfirm
+                | PromptType::RecoverSwap
+                | PromptType::ExternalChange
+                | PromptType::PickBuffer
+                | PromptType::VimCommand
+                | PromptType::GotoLine
+                | PromptType::CreateDirectory => {}
+            },
+            Select(move_command) => {
+                if self.prompt_type.is_none() {
+                    self.view.handle_select_command(move_command);
                 }
             }
-            Edit(edit_command) => {
-                if let Some(command_bar) = &mut self.command_bar {
-                    if matches!(edit_command, InsertNewline) {
-                        let file_name = command_bar.value();
-                        self.dismiss_prompt();
-                        self.save(Some(&file_name));
-                    } else {
-                        command_bar.handle_edit_command(edit_command);
-                    }
+            ClipboardAction(clipboard_command) => {
+                if self.prompt_type.is_none() {
+                    self.handle_clipboard_command(clipboard_command);
+                }
+            }
+            Window(window_command) => {
+                if self.prompt_type.is_none() {
+                    self.handle_window_command(window_command);
+                }
+            }
+        }
+        if !matches!(command, System(Resize(_))) {
+            self.previous_command = Some(command);
+        }
+    }
+
+    /// Traite une commande système, quand aucun prompt n'est affiché
+    fn handle_system_command(&mut self, command: command::System) {
+        match command {
+            Save => self.handle_save(),
+            Search => self.set_prompt(PromptType::Search),
+            Replace => self.set_prompt(PromptType::ReplaceFind),
+            ToggleLineEnding => {
+                let line_ending = self.view.toggle_line_ending();
+                self.message_bar
+                    .update_message(&format!("Line endings converted to {line_ending}."));
+            }
+            NextBuffer => self.next_buffer(),
+            PreviousBuffer => self.previous_buffer(),
+            PickBuffer => self.set_prompt(PromptType::PickBuffer),
+            GotoLine => self.set_prompt(PromptType::GotoLine),
+            CycleLineNumbers => self.cycle_line_numbers(),
+            ToggleSoftWrap => self.toggle_soft_wrap(),
+            ToggleAutoIndent => self.toggle_auto_indent(),
+            Help => self.show_help(),
+            SetMark => {
+                let message = if self.view.set_mark() {
+                    "Mark set."
                 } else {
-                    self.view.handle_edit_command(edit_command);
+                    "Mark deactivated."
+                };
+                self.message_bar.update_message(message);
+            }
+            Quit | Resize(_) | Dismiss => {}
+        }
+    }
+
+    /// affiche la liste des raccourcis à la place des fenêtres
+    fn show_help(&mut self) {
+        let mut help_screen = HelpScreen::new(&self.keymap);
+        help_screen.resize(Size {
+            height: self.terminal_size.height.saturating_sub(1),
+            width: self.terminal_size.width,
+        });
+        self.help_screen = Some(help_screen);
+    }
+
+    /// les déplacements font défiler l'aide, toute autre commande la ferme
+    fn process_command_during_help(&mut self, command: Command) {
+        if let Move(move_command) = command {
+            if let Some(help_screen) = &mut self.help_screen {
+                help_screen.scroll(move_command);
+            }
+        } else {
+            self.help_screen = None;
+            // les fenêtres sont redessinées par-dessus l'aide
+            self.resize(self.terminal_size);
+        }
+    }
+
+    /// Copie, coupe ou colle via le presse-papier ; le texte copié ou coupé va aussi
+    /// dans le kill ring
+    fn handle_clipboard_command(&mut self, command: command::ClipboardCommand) {
+        match command {
+            Copy => {
+                if let Some(text) = self.view.copy_selection() {
+                    self.view.deactivate_mark();
+                    self.kill_ring.push(text.clone(), false);
+                    self.copy_to_clipboard(text);
+                }
+            }
+            Cut => {
+                if let Some(text) = self.view.cut_selection() {
+                    self.kill_ring.push(text.clone(), false);
+                    self.copy_to_clipboard(text);
+                }
+            }
+            Paste => {
+                if let Some(text) = self.clipboard.get() {
+                    self.view.paste(&text);
+                }
+            }
+            KillLine => {
+                if let Some(text) = self.view.kill_line() {
+                    let append = self.previous_command == Some(ClipboardAction(KillLine));
+                    self.kill_ring.push(text, append);
+                }
+            }
+            Yank => match self.kill_ring.yank().map(str::to_string) {
+                Some(text) => self.view.paste(&text),
+                // rien n'a encore été coupé : on colle le presse-papier
+                None => self.handle_clipboard_command(Paste),
+            },
+            YankPop => {
+                if !matches!(
+                    self.previous_command,
+                    Some(ClipboardAction(Yank | YankPop))
+                ) {
+                    self.message_bar
+                        .update_message("Previous command was not a yank.");
+                    return;
+                }
+                if let Some(text) = self.kill_ring.yank_pop().map(str::to_string) {
+                    // le collage précédent est annulé puis remplacé
+                    self.view.handle_edit_command(command::Edit::Undo);
+                    self.view.paste(&text);
+                }
+            }
+        }
+    }
+
+    /// Gère un collage du terminal (bracketed paste) : le texte arrive d'un seul bloc.
+    /// Ignoré pendant l'affichage de l'aide, qui cache le buffer.
+    fn handle_paste(&mut self, text: &str) {
+        if self.help_screen.is_some() {
+            return;
+        }
+        self.reset_quit_times();
+        match self.prompt_type {
+            PromptType::None => {
+                // en mode visuel, le texte collé remplace la sélection et Vim revient au mode normal
+                if let Some(vim) = &mut self.vim {
+                    vim.leave_visual();
+                }
+                self.view.paste(text);
+                if self.vim_mode() == Some(VimMode::Normal) {
+                    self.view.snap_caret_to_character();
+                }
+            }
+            PromptType::Search => {
+                self.command_bar.insert_text(text);
+                let query = self.command_bar.value();
+                self.view.search(&query);
+            }
+            PromptType::Save
+            | PromptType::ReplaceFind
+            | PromptType::ReplaceWith
+            | PromptType::PickBuffer
+            | PromptType::VimCommand
+            | PromptType::GotoLine => {
+                self.command_bar.insert_text(text);
+            }
+            PromptType::ReplaceConfirm
+            | PromptType::RecoverSwap
+            | PromptType::ExternalChange
+            | PromptType::CreateDirectory => {}
+        }
+    }
+
+    /// Copie du texte dans le presse-papier, en signalant si le presse-papier système a échoué
+    fn copy_to_clipboard(&mut self, text: String) {
+        if let Err(error) = self.clipboard.set(text) {
+            self.message_bar
+                .update_message(&format!("ERR: Could not reach system clipboard: {error}"));
+        }
+    }
+
+    /// Gère la touche Échap selon le prompt affiché
+    fn handle_dismiss(&mut self) {
+        match self.prompt_type {
+            PromptType::Save => {
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Save aborted.");
+            }
+            PromptType::Search => {
+                self.view.dismiss_search();
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Search aborted.");
+            }
+            PromptType::ReplaceFind | PromptType::ReplaceWith => {
+                self.replace_pattern = None;
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Replace aborted.");
+            }
+            PromptType::ReplaceConfirm => self.finish_replace(),
+            PromptType::RecoverSwap => {
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Swap file ignored.");
+            }
+            PromptType::ExternalChange => {
+                self.set_prompt(PromptType::None);
+                self.message_bar
+                    .update_message("File changed on disk, you are editing an older version.");
+            }
+            PromptType::CreateDirectory => {
+                self.save_target = None;
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Save aborted.");
+            }
+            PromptType::PickBuffer | PromptType::VimCommand | PromptType::GotoLine => {
+                self.set_prompt(PromptType::None);
+            }
+            PromptType::None => self.view.cancel_selection(),
+        }
+    }
+
+    /// Traite une édition pendant le prompt de sauvegarde
+    fn process_command_during_save(&mut self, command: command::Edit) {
+        if matches!(command, InsertNewline) {
+            let file_name = self.command_bar.value();
+            self.set_prompt(PromptType::None);
+            self.save(Some(&file_name));
+        } else {
+            self.command_bar.handle_edit_command(command);
+        }
+    }
+
+    /// Traite une édition pendant la saisie du motif ou du texte de remplacement
+    fn process_command_during_replace_prompt(&mut self, command: command::Edit) {
+        if !matches!(command, InsertNewline) {
+            self.command_bar.handle_edit_command(command);
+            return;
+        }
+        let value = self.command_bar.value();
+        if self.prompt_type == PromptType::ReplaceFind {
+            match Pattern::parse(&value) {
+                Ok(pattern) if !pattern.is_empty() => {
+                    self.replace_pattern = Some(pattern);
+                    self.set_prompt(PromptType::ReplaceWith);
                 }
+                Ok(_) => self.set_prompt(PromptType::None),
+                Err(err) => {
+                    self.set_prompt(PromptType::None);
+                    let reason = err.to_string();
+                    let reason = reason.lines().last().unwrap_or_default().trim();
+                    self.message_bar
+                        .update_message(&format!("Invalid regular expression: {reason}"));
+                }
+            }
+        } else if let Some(pattern) = self.replace_pattern.take() {
+            if self.view.start_replace(pattern, &value) {
+                self.set_prompt(PromptType::ReplaceConfirm);
+            } else {
+                self.view.finish_replace();
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("No match found.");
+            }
+        }
+    }
+
+    /// Traite la réponse à la question "remplacer cette correspondance ?"
+    fn process_command_during_replace_confirm(&mut self, command: command::Edit) {
+        let has_next = match command {
+            command::Edit::Insert('y' | 'Y') => self.view.replace_current_match(),
+            command::Edit::Insert('n' | 'N') => self.view.skip_current_match(),
+            command::Edit::Insert('a' | 'A') => {
+                self.view.replace_all_matches();
+                false
+            }
+            command::Edit::Insert('q' | 'Q') => false,
+            _ => true,
+        };
+        if !has_next {
+            self.finish_replace();
+        }
+    }
+
+    /// Traite une touche pendant le prompt de récupération du fichier d'échange
+    fn process_command_during_recover(&mut self, command: command::Edit) {
+        match command {
+            command::Edit::Insert('r' | 'R') => {
+                self.set_prompt(PromptType::None);
+                match self.view.recover_swap() {
+                    Ok(()) => self
+                        .message_bar
+                        .update_message("Recovered unsaved changes from swap file."),
+                    Err(err) => self
+                        .message_bar
+                        .update_message(&format!("Could not recover swap file: {err}")),
+                }
+            }
+            command::Edit::Insert('d' | 'D') => {
+                let summary = self
+                    .view
+                    .diff_swap()
+                    .unwrap_or_else(|err| format!("Could not read swap file: {err}"));
+                self.command_bar
+                    .set_prompt(&format!("{summary} (r)ecover (x) discard: "));
+                self.command_bar.set_needs_redraw(true);
+            }
+            command::Edit::Insert('x' | 'X') => {
+                self.view.discard_swap();
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Swap file discarded.");
+            }
+            _ => {}
+        }
+    }
+
+    /// Traite une touche pendant le prompt de modification externe du fichier
+    fn process_command_during_external_change(&mut self, command: command::Edit) {
+        match command {
+            command::Edit::Insert('r' | 'R') => {
+                self.set_prompt(PromptType::None);
+                self.reload();
             }
-            Move(move_command) => {
-                if self.command_bar.is_none() {
-                    self.view.handle_move_command(move_command);
+            command::Edit::Insert('o' | 'O') => {
+                self.set_prompt(PromptType::None);
+                self.save(None);
+            }
+            command::Edit::Insert('k' | 'K') => {
+                self.view.ignore_external_change();
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Kept the buffer version.");
+            }
+            _ => {}
+        }
+    }
+
+    /// Traite une édition pendant le choix du buffer : un numéro ou une partie du nom
+    fn process_command_during_pick_buffer(&mut self, command: command::Edit) {
+        if !matches!(command, InsertNewline) {
+            self.command_bar.handle_edit_command(command);
+            return;
+        }
+        let query = self.command_bar.value();
+        self.set_prompt(PromptType::None);
+        let index = query
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| number.checked_sub(1))
+            .or_else(|| {
+                self.buffers.iter().position(|buffer| {
+                    format!("{}", buffer.borrow().file_info).contains(query.trim())
+                })
+            });
+        match index {
+            Some(index) if index < self.buffers.len() => self.switch_to_buffer(index),
+            _ => self
+                .message_bar
+                .update_message(&format!("No buffer matches \"{query}\".")),
+        }
+    }
+
+    /// Traite une édition pendant le prompt de saut : `42`, `42:7`, `+3` ou `-3`
+    fn process_command_during_goto_line(&mut self, command: command::Edit) {
+        if !matches!(command, InsertNewline) {
+            self.command_bar.handle_edit_command(command);
+            return;
+        }
+        let value = self.command_bar.value();
+        self.set_prompt(PromptType::None);
+        match GotoTarget::parse(&value) {
+            Some(target) => self.view.go_to(target),
+            None => self
+                .message_bar
+                .update_message(&format!("Not a line: \"{value}\".")),
+        }
+    }
+
+    /// Traite une édition pendant la demande de création du dossier du fichier à sauvegarder
+    fn process_command_during_create_directory(&mut self, command: command::Edit) {
+        match command {
+            command::Edit::Insert('y' | 'Y') => {
+                self.set_prompt(PromptType::None);
+                let file_name = self.save_target.take();
+                if let Some(directory) = self.view.missing_directory(file_name.as_deref()) {
+                    if let Err(err) = fs::create_dir_all(&directory) {
+                        self.message_bar.update_message(&format!(
+                            "Could not create directory {}: {err}",
+                            directory.display()
+                        ));
+                        return;
+                    }
                 }
+                self.save(file_name.as_deref());
+            }
+            command::Edit::Insert('n' | 'N') => {
+                self.save_target = None;
+                self.set_prompt(PromptType::None);
+                self.message_bar.update_message("Save aborted.");
             }
+            _ => {}
         }
+    }
 
-        
+    /// liste des buffers pour le prompt de choix : `1:a.rs 2:b.rs*`
+    fn buffer_list_to_string(&self) -> String {
+        self.buffers
+            .iter()
+            .enumerate()
+            .map(|(index, buffer)| {
+                let buffer = buffer.borrow();
+                let modified = if buffer.is_dirty() { "*" } else { "" };
+                format!("{}:{}{modified}", index.saturating_add(1), buffer.file_info)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
     }
+
+    /// Recharge le fichier depuis le disque
+    fn reload(&mut self) {
+        match self.view.reload() {
+            Ok(()) => self.message_bar.update_message("File reloaded from disk."),
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("Could not reload file: {err}")),
+        }
+    }
+
+    /// Vérifie si le fichier a été modifié par un autre programme, quand le terminal reprend
+    /// le focus. Un buffer sans modifications est rechargé directement si `auto_reload`.
+    /// Les buffers qui ne sont pas affichés dans la fenêtre active et ne peuvent pas être
+    /// rechargés sont vérifiés à nouveau quand ils y sont affichés.
+    fn handle_focus_gained(&mut self) {
+        if !self.prompt_type.is_none() {
+            return;
+        }
+        if self.config.auto_reload {
+            self.reload_hidden_buffers();
+        }
+        match self.view.check_external_change() {
+            ExternalChange::Modified => {
+                if self.config.auto_reload && !self.view.get_status().is_modified {
+                    self.reload();
+                } else {
+                    self.set_prompt(PromptType::ExternalChange);
+                }
+            }
+            ExternalChange::Deleted => self
+                .message_bar
+                .update_message("WARNING! File was deleted on disk."),
+            ExternalChange::None => {}
+        }
+    }
+
+    /// recharge les buffers sans modifications, autres que celui de la fenêtre active,
+    /// dont le fichier a été modifié sur le disque
+    fn reload_hidden_buffers(&mut self) {
+        let mut reloaded = 0_usize;
+        for buffer in &self.buffers {
+            if self.view.shows(buffer) || buffer.borrow().is_dirty() {
+                continue;
+            }
+            let mut buffer = buffer.borrow_mut();
+            if buffer.check_external_change() == ExternalChange::Modified && buffer.reload().is_ok() {
+                reloaded = reloaded.saturating_add(1);
+            }
+        }
+        if reloaded > 0 {
+            let plural = if reloaded == 1 { "" } else { "s" };
+            self.message_bar.update_message(&format!(
+                "{reloaded} other buffer{plural} reloaded from disk."
+            ));
+        }
+    }
+
+    /// Termine le remplacement et affiche le nombre de remplacements
+    fn finish_replace(&mut self) {
+        let count = self.view.finish_replace();
+        self.set_prompt(PromptType::None);
+        let plural = if count == 1 { "" } else { "s" };
+        self.message_bar
+            .update_message(&format!("Replaced {count} occurrence{plural}."));
+    }
+
+    /// Traite une édition pendant la recherche : chaque frappe relance la recherche
+    fn process_command_during_search(&mut self, command: command::Edit) {
+        if matches!(command, InsertNewline) {
+            self.view.exit_search();
+            self.set_prompt(PromptType::None);
+        } else {
+            self.command_bar.handle_edit_command(command);
+            let query = self.command_bar.value();
+            self.view.search(&query);
+        }
+    }
+
     /// Gère la commande de quitter
-    // clippy::arithmetic_side_effects: quit_times is guaranteed to be between 0 and QUIT_TIMES
+    // clippy::arithmetic_side_effects: quit_times is guaranteed to be between 0 and config.quit_times
     #[allow(clippy::arithmetic_side_effects)]
     fn handle_quit(&mut self) {
-        if !self.view.get_status().is_modified || self.quit_times + 1 == QUIT_TIMES {
+        let modified_count = self.modified_buffer_count();
+        if modified_count == 0 || self.quit_times + 1 >= self.config.quit_times {
             self.should_quit = true;
-        } else if self.view.get_status().is_modified {
+        } else {
+            let what = if self.buffers.len() == 1 {
+                String::from("File has")
+            } else if modified_count == 1 {
+                String::from("1 buffer has")
+            } else {
+                format!("{modified_count} buffers have")
+            };
+            let keys = self
+                .keymap
+                .keys_for(System(Quit))
+                .map_or_else(|| String::from("quit"), keys_to_string);
             self.message_bar.update_message(&format!(
-                "WARNING! File has unsaved changes. Press Ctrl-Q {} more times to quit.",
-                QUIT_TIMES - self.quit_times - 1
+                "WARNING! {what} unsaved changes. Press {keys} {} more times to quit.",
+                self.config.quit_times - self.quit_times - 1
             ));
 
             self.quit_times += 1;
         }
     }
 
-    fn dismiss_prompt(&mut self) {
-        self.command_bar = None;
-        self.message_bar.set_needs_redraw(true);
+    /// nombre de buffers qui ont des modifications non sauvegardées
+    fn modified_buffer_count(&self) -> usize {
+        self.buffers
+            .iter()
+            .filter(|buffer| buffer.borrow().is_dirty())
+            .count()
     }
-    fn show_prompt(&mut self) {
-        let mut command_bar = CommandBar::default();
-        command_bar.set_prompt("Save as: ");
-        command_bar.resize(Size {
-            height: 1,
-            width: self.terminal_size.width,
-        });
-        command_bar.set_needs_redraw(true);
-        self.command_bar = Some(command_bar);
+
+    /// Affiche (ou cache) la barre de commande avec le prompt correspondant
+    fn set_prompt(&mut self, prompt_type: PromptType) {
+        match prompt_type {
+            PromptType::None => self.message_bar.set_needs_redraw(true),
+            PromptType::Save => self.command_bar.set_prompt("Save as: "),
+            PromptType::ReplaceFind => self
+                .command_bar
+                .set_prompt("Replace (/regex/ or text): "),
+            PromptType::ReplaceWith => self.command_bar.set_prompt("Replace with: "),
+            PromptType::ReplaceConfirm => self
+                .command_bar
+                .set_prompt("Replace this match? (y)es (n)o (a)ll (q)uit"),
+            PromptType::RecoverSwap => self
+                .command_bar
+                .set_prompt("Unsaved changes found! (r)ecover (d)iff (x) discard: "),
+            PromptType::ExternalChange => self
+                .command_bar
+                .set_prompt("File changed on disk! (r)eload (o)verwrite (k)eep: "),
+            PromptType::VimCommand => self.command_bar.set_prompt(":"),
+            PromptType::GotoLine => self
+                .command_bar
+                .set_prompt("Go to line (line, line:col, :col, +N/-N): "),
+            PromptType::CreateDirectory => {
+                let directory = self
+                    .view
+                    .missing_directory(self.save_target.as_deref())
+                    .unwrap_or_default();
+                let prompt = format!(
+                    "Directory {} does not exist. Create it? (y/n): ",
+                    directory.display()
+                );
+                self.command_bar.set_prompt(&prompt);
+            }
+            PromptType::PickBuffer => {
+                let prompt = format!("{} | Buffer (number or name): ", self.buffer_list_to_string());
+                self.command_bar.set_prompt(&prompt);
+            }
+            PromptType::Search => {
+                self.view.enter_search();
+                self.command_bar
+                    .set_prompt("Search (Esc to cancel, Arrows to navigate): ");
+            }
+        }
+        self.command_bar.clear_value();
+        self.command_bar.set_needs_redraw(true);
+        self.prompt_type = prompt_type;
     }
 
     /// Réinitialise le nombre de tentatives de quitter
@@ -275,22 +1476,33 @@ impl Editor {
     /// affiche le message de sauvegarde
     fn handle_save(&mut self) {
         if self.view.is_file_loaded() {
-            self.save(None);
+            if self.view.check_external_change() == ExternalChange::Modified {
+                self.set_prompt(PromptType::ExternalChange);
+            } else {
+                self.save(None);
+            }
         } else {
-            self.show_prompt();
+            self.set_prompt(PromptType::Save);
         }
     }
 
+    /// sauvegarde, après avoir demandé à créer le dossier du fichier s'il n'existe pas
     fn save(&mut self, file_name: Option<&str>) {
+        if self.view.missing_directory(file_name).is_some() {
+            self.save_target = file_name.map(str::to_string);
+            self.set_prompt(PromptType::CreateDirectory);
+            return;
+        }
         let result = if let Some(name) = file_name {
             self.view.save_as(name)
         } else {
             self.view.save()
         };
-        if result.is_ok() {
-            self.message_bar.update_message("File saved successfully.");
-        } else {
-            self.message_bar.update_message("Error writing file!");
+        match result {
+            Ok(()) => self.message_bar.update_message("File saved successfully."),
+            Err(err) => self
+                .message_bar
+                .update_message(&format!("Error writing file: {err}")),
         }
     }
 
@@ -299,6 +1511,12 @@ impl Editor {
 
 impl Drop for Editor {
     fn drop(&mut self) {
+        // en cas de panique, le fichier d'échange doit survivre
+        if !thread::panicking() {
+            for buffer in &self.buffers {
+                buffer.borrow_mut().remove_swap();
+            }
+        }
         let _ = Terminal::terminate();
         if self.should_quit {
             let _ = Terminal::print("Goodbye.\r\n");
@@ -0,0 +1,154 @@
+use std::{
+    cmp::min,
+    fmt::{self, Display},
+};
+
+use super::{Annotation, AnnotationType};
+
+/// string accompagnée d'annotations (correspondances de recherche, etc.) sur des plages d'octets
+#[derive(Default, Debug)]
+pub struct AnnotatedString {
+    string: String,
+    annotations: Vec<Annotation>,
+}
+
+/// morceau d'une `AnnotatedString` ayant une seule annotation (ou aucune)
+#[derive(Debug)]
+pub struct AnnotatedStringPart<'a> {
+    pub string: &'a str,
+    pub annotation_type: Option<AnnotationType>,
+}
+
+impl AnnotatedString {
+    pub fn from(string: &str) -> Self {
+        Self {
+            string: String::from(string),
+            annotations: Vec::new(),
+        }
+    }
+
+    /// ajoute une annotation, les dernières ajoutées sont prioritaires
+    pub fn add_annotation(
+        &mut self,
+        annotation_type: AnnotationType,
+        start_byte_idx: usize,
+        end_byte_idx: usize,
+    ) {
+        debug_assert!(start_byte_idx <= end_byte_idx);
+        self.annotations.push(Annotation {
+            kind: annotation_type,
+            start_byte_idx,
+            end_byte_idx,
+        });
+    }
+
+    /// ajoute du texte à la fin, avec une annotation éventuelle
+    pub fn push_str(&mut self, string: &str, annotation_type: Option<AnnotationType>) {
+        let start_byte_idx = self.string.len();
+        self.string.push_str(string);
+        if let Some(annotation_type) = annotation_type {
+            self.add_annotation(annotation_type, start_byte_idx, self.string.len());
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.string.is_empty()
+    }
+
+    /// supprime tout ce qui précède l'octet donné
+    pub fn truncate_left_until(&mut self, until_byte_idx: usize) {
+        self.replace(0, until_byte_idx, "");
+    }
+
+    /// supprime tout à partir de l'octet donné
+    pub fn truncate_right_from(&mut self, from_byte_idx: usize) {
+        self.replace(from_byte_idx, self.string.len(), "");
+    }
+
+    /// remplace une plage d'octets par une nouvelle string, en décalant les annotations
+    pub fn replace(&mut self, start_byte_idx: usize, end_byte_idx: usize, new_string: &str) {
+        let end_byte_idx = min(end_byte_idx, self.string.len());
+        if start_byte_idx > end_byte_idx {
+            return;
+        }
+        self.string
+            .replace_range(start_byte_idx..end_byte_idx, new_string);
+
+        let inserted_end = start_byte_idx.saturating_add(new_string.len());
+        // une annotation qui chevauche la plage remplacée couvre le texte inséré
+        let shift = |byte_idx: usize, is_end: bool| {
+            if byte_idx <= start_byte_idx {
+                byte_idx
+            } else if byte_idx >= end_byte_idx {
+                byte_idx
+                    .saturating_sub(end_byte_idx)
+                    .saturating_add(inserted_end)
+            } else if is_end {
+                inserted_end
+            } else {
+                start_byte_idx
+            }
+        };
+        self.annotations.retain_mut(|annotation| {
+            annotation.start_byte_idx = shift(annotation.start_byte_idx, false);
+            annotation.end_byte_idx = shift(annotation.end_byte_idx, true);
+            annotation.start_byte_idx < annotation.end_byte_idx
+        });
+    }
+}
+
+impl Display for AnnotatedString {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self.string)
+    }
+}
+
+/// parcourt une `AnnotatedString` morceau par morceau
+pub struct AnnotatedStringIterator<'a> {
+    annotated_string: &'a AnnotatedString,
+    current_idx: usize,
+}
+
+impl<'a> Iterator for AnnotatedStringIterator<'a> {
+    type Item = AnnotatedStringPart<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let string = &self.annotated_string.string;
+        if self.current_idx >= string.len() {
+            return None;
+        }
+        let annotations = &self.annotated_string.annotations;
+        let annotation_type = annotations
+            .iter()
+            .rev()
+            .find(|annotation| {
+                annotation.start_byte_idx <= self.current_idx
+                    && annotation.end_byte_idx > self.current_idx
+            })
+            .map(|annotation| annotation.kind);
+        // le morceau s'arrête à la prochaine frontière d'annotation
+        let end_idx = annotations
+            .iter()
+            .flat_map(|annotation| [annotation.start_byte_idx, annotation.end_byte_idx])
+            .filter(|byte_idx| *byte_idx > self.current_idx)
+            .fold(string.len(), min);
+        let part = AnnotatedStringPart {
+            string: string.get(self.current_idx..end_idx).unwrap_or_default(),
+            annotation_type,
+        };
+        self.current_idx = end_idx;
+        Some(part)
+    }
+}
+
+impl<'a> IntoIterator for &'a AnnotatedString {
+    type Item = AnnotatedStringPart<'a>;
+    type IntoIter = AnnotatedStringIterator<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        AnnotatedStringIterator {
+            annotated_string: self,
+            current_idx: 0,
+        }
+    }
+}
@@ -0,0 +1,41 @@
+/// type d'annotation, détermine la couleur utilisée à l'affichage
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AnnotationType {
+    Match,
+    SelectedMatch,
+    Keyword,
+    Type,
+    String,
+    Comment,
+    Number,
+    Selection,
+    LineNumber,
+    CurrentLineNumber,
+}
+
+impl AnnotationType {
+    /// retrouve un type d'annotation à partir de son nom dans la configuration
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "match" => Some(Self::Match),
+            "selected_match" => Some(Self::SelectedMatch),
+            "keyword" => Some(Self::Keyword),
+            "type" => Some(Self::Type),
+            "string" => Some(Self::String),
+            "comment" => Some(Self::Comment),
+            "number" => Some(Self::Number),
+            "selection" => Some(Self::Selection),
+            "line_number" => Some(Self::LineNumber),
+            "current_line_number" => Some(Self::CurrentLineNumber),
+            _ => None,
+        }
+    }
+}
+
+/// annotation d'une plage d'octets d'une string
+#[derive(Copy, Clone, Debug)]
+pub struct Annotation {
+    pub kind: AnnotationType,
+    pub start_byte_idx: usize,
+    pub end_byte_idx: usize,
+}
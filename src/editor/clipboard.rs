@@ -0,0 +1,170 @@
+use std::{
+    env,
+    io::{Error, Write},
+    process::{Command, Stdio},
+};
+
+use super::Terminal;
+
+/// outil externe donnant accès au presse-papier système
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ClipboardTool {
+    Xclip,
+    WlCopy,
+    Pbcopy,
+}
+
+impl ClipboardTool {
+    /// commande qui lit le texte à copier sur son entrée standard
+    const fn copy_command(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Self::Xclip => ("xclip", &["-selection", "clipboard", "-in"]),
+            Self::WlCopy => ("wl-copy", &[]),
+            Self::Pbcopy => ("pbcopy", &[]),
+        }
+    }
+
+    /// commande qui écrit le contenu du presse-papier sur sa sortie standard
+    const fn paste_command(self) -> (&'static str, &'static [&'static str]) {
+        match self {
+            Self::Xclip => ("xclip", &["-selection", "clipboard", "-out"]),
+            Self::WlCopy => ("wl-paste", &["--no-newline"]),
+            Self::Pbcopy => ("pbpaste", &[]),
+        }
+    }
+
+    /// vrai si les deux commandes de l'outil se trouvent dans le PATH
+    fn is_available(self) -> bool {
+        is_on_path(self.copy_command().0) && is_on_path(self.paste_command().0)
+    }
+
+    fn copy(self, text: &str) -> Result<(), Error> {
+        let (program, args) = self.copy_command();
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        let status = child.wait()?;
+        if status.success() {
+            Ok(())
+        } else {
+            Err(Error::other(format!("{program} failed ({status})")))
+        }
+    }
+
+    fn paste(self) -> Result<String, Error> {
+        let (program, args) = self.paste_command();
+        let output = Command::new(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()?;
+        if !output.status.success() {
+            return Err(Error::other(format!("{program} failed ({})", output.status)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).replace("\r\n", "\n"))
+    }
+}
+
+/// destination des copies faites dans l'éditeur
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum ClipboardBackend {
+    /// choisit le meilleur backend selon l'environnement
+    #[default]
+    Auto,
+    /// presse-papier interne uniquement
+    Internal,
+    /// séquence OSC 52 interprétée par le terminal, fonctionne aussi à travers SSH
+    Osc52,
+    Tool(ClipboardTool),
+}
+
+impl ClipboardBackend {
+    /// retrouve un backend à partir de son nom (`auto`, `internal`, `osc52`, `xclip`...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "internal" | "none" => Some(Self::Internal),
+            "osc52" => Some(Self::Osc52),
+            "xclip" => Some(Self::Tool(ClipboardTool::Xclip)),
+            "wl-copy" | "wayland" => Some(Self::Tool(ClipboardTool::WlCopy)),
+            "pbcopy" => Some(Self::Tool(ClipboardTool::Pbcopy)),
+            _ => None,
+        }
+    }
+
+    /// remplace `Auto` par un backend concret : OSC 52 dans une session SSH, sinon
+    /// l'outil du système graphique s'il est installé, et OSC 52 en dernier recours
+    fn resolve(self) -> Self {
+        if self != Self::Auto {
+            return self;
+        }
+        if env::var_os("SSH_TTY").is_some() || env::var_os("SSH_CONNECTION").is_some() {
+            return Self::Osc52;
+        }
+        let candidates = [
+            (env::var_os("WAYLAND_DISPLAY").is_some(), ClipboardTool::WlCopy),
+            (env::var_os("DISPLAY").is_some(), ClipboardTool::Xclip),
+            (cfg!(target_os = "macos"), ClipboardTool::Pbcopy),
+        ];
+        candidates
+            .into_iter()
+            .find(|(usable, tool)| *usable && tool.is_available())
+            .map_or(Self::Osc52, |(_, tool)| Self::Tool(tool))
+    }
+}
+
+/// presse-papier de l'éditeur, peut contenir du texte sur plusieurs lignes.
+/// Le contenu est toujours gardé en interne et transmis au presse-papier système
+/// selon le backend choisi.
+#[derive(Default)]
+pub struct Clipboard {
+    content: Option<String>,
+    backend: ClipboardBackend,
+}
+
+impl Clipboard {
+    pub fn new(backend: ClipboardBackend) -> Self {
+        Self {
+            content: None,
+            backend: backend.resolve(),
+        }
+    }
+
+    /// remplace le contenu du presse-papier. Le contenu interne est mis à jour même
+    /// si la copie vers le presse-papier système échoue.
+    pub fn set(&mut self, text: String) -> Result<(), Error> {
+        let result = match self.backend {
+            ClipboardBackend::Auto | ClipboardBackend::Internal => Ok(()),
+            ClipboardBackend::Osc52 => Terminal::copy_to_clipboard(&text),
+            ClipboardBackend::Tool(tool) => tool.copy(&text),
+        };
+        self.content = Some(text);
+        result
+    }
+
+    /// retourne le contenu du presse-papier système quand le backend sait le lire,
+    /// sinon le contenu interne
+    pub fn get(&self) -> Option<String> {
+        if let ClipboardBackend::Tool(tool) = self.backend {
+            if let Ok(text) = tool.paste() {
+                if !text.is_empty() {
+                    return Some(text);
+                }
+            }
+        }
+        self.content.clone()
+    }
+}
+
+/// vrai si un exécutable de ce nom se trouve dans un des dossiers du PATH
+fn is_on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|paths| {
+        env::split_paths(&paths).any(|dir| dir.join(program).is_file())
+    })
+}
@@ -1,16 +1,26 @@
-use crossterm::event::{
-    Event,
-    KeyCode::{
-        self,Backspace, Char, Delete, Down, End, Enter, Home, Left, PageDown, PageUp, Right, Tab, Up,
-    },
-    KeyEvent, KeyModifiers,
-};
+use crossterm::event::Event;
 use std::convert::TryFrom;
-use super::Size;
+use super::{Direction, Size, SplitDirection};
+
+/// retrouve une valeur à partir de son nom dans une table de noms
+fn from_table<T: Copy>(table: &[(&str, T)], name: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(entry_name, _)| *entry_name == name)
+        .map(|(_, value)| *value)
+}
+
+/// retrouve le nom d'une valeur dans une table de noms
+fn name_in_table<T: PartialEq>(table: &[(&'static str, T)], value: &T) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(_, entry_value)| entry_value == value)
+        .map(|(name, _)| *name)
+}
 
 /// déplacements possible pour le curseur
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Move {
     PageUp,
     PageDown,
@@ -20,121 +30,216 @@ pub enum Move {
     Left,
     Right,
     Down,
+    /// début du mot précédent, en remontant à la ligne précédente au début d'une ligne
+    WordLeft,
+    /// fin du mot suivant, en passant à la ligne suivante à la fin d'une ligne
+    WordRight,
+    StartOfBuffer,
+    EndOfBuffer,
 }
-impl TryFrom<KeyEvent> for Move {
-    type Error = String;
-    fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
-        let KeyEvent {
-            code, modifiers, ..
-        } = event;
-
-        if modifiers == KeyModifiers::NONE {
-            match code {
-                Up => Ok(Self::Up),
-                Down => Ok(Self::Down),
-                Left => Ok(Self::Left),
-                Right => Ok(Self::Right),
-                PageDown => Ok(Self::PageDown),
-                PageUp => Ok(Self::PageUp),
-                Home => Ok(Self::StartOfLine),
-                End => Ok(Self::EndOfLine),
-                _ => Err(format!("Unsupported code: {code:?}")),
-            }
-        } else {
-            Err(format!(
-                "Unsupported key code {code:?} or modifier {modifiers:?}"
-            ))
-        }
-    }
+
+impl Move {
+    /// noms des déplacements dans la configuration
+    const NAMES: [(&'static str, Self); 12] = [
+        ("page_up", Self::PageUp),
+        ("page_down", Self::PageDown),
+        ("line_start", Self::StartOfLine),
+        ("line_end", Self::EndOfLine),
+        ("up", Self::Up),
+        ("left", Self::Left),
+        ("right", Self::Right),
+        ("down", Self::Down),
+        ("word_left", Self::WordLeft),
+        ("word_right", Self::WordRight),
+        ("buffer_start", Self::StartOfBuffer),
+        ("buffer_end", Self::EndOfBuffer),
+    ];
 }
 
 /// edition possible dans le terminal
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Edit {
     Insert(char),
     InsertNewline,
     Delete,
     DeleteBackward,
+    /// supprime jusqu'à la fin du mot suivant
+    DeleteWord,
+    /// supprime jusqu'au début du mot précédent
+    DeleteWordBackward,
+    Undo,
+    Redo,
 }
-impl TryFrom<KeyEvent> for Edit {
-    type Error = String;
 
-    fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
-        match (event.code, event.modifiers) {
-            (Char(character), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
-                Ok(Self::Insert(character))
-            }
-            (Tab, KeyModifiers::NONE) => Ok(Self::Insert('\t')),
-            (Enter, KeyModifiers::NONE) => Ok(Self::InsertNewline),
-            (Backspace, KeyModifiers::NONE) => Ok(Self::DeleteBackward),
-            (Delete, KeyModifiers::NONE) => Ok(Self::Delete),
-            _ => Err(format!(
-                "Unsupported key code {:?} with modifiers {:?}",
-                event.code, event.modifiers
-            )),
-        }
-    }
+impl Edit {
+    /// noms des éditions dans la configuration ; seule la tabulation a un nom parmi les insertions
+    const NAMES: [(&'static str, Self); 8] = [
+        ("tab", Self::Insert('\t')),
+        ("newline", Self::InsertNewline),
+        ("delete", Self::Delete),
+        ("delete_backward", Self::DeleteBackward),
+        ("delete_word", Self::DeleteWord),
+        ("delete_word_backward", Self::DeleteWordBackward),
+        ("undo", Self::Undo),
+        ("redo", Self::Redo),
+    ];
 }
 
 /// actions systèmes
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum System {
     Save,
     Resize(Size),
     Quit,
     Dismiss,
+    Search,
+    Replace,
+    ToggleLineEnding,
+    NextBuffer,
+    PreviousBuffer,
+    PickBuffer,
+    CycleLineNumbers,
+    ToggleSoftWrap,
+    ToggleAutoIndent,
+    Help,
+    /// pose la marque : les déplacements étendent la sélection (région) depuis elle
+    SetMark,
+    GotoLine,
 }
 
-impl TryFrom<KeyEvent> for System {
-    type Error = String;
-    fn try_from(event: KeyEvent) -> Result<Self, Self::Error> {
-        let KeyEvent {
-            code, modifiers, ..
-        } = event;
-
-        if modifiers == KeyModifiers::CONTROL {
-            match code {
-                Char('q') => Ok(Self::Quit),
-                Char('s') => Ok(Self::Save),
-                _ => Err(format!("Unsupported CONTROL+{code:?} combination")),
-            }
-        } else if modifiers == KeyModifiers::NONE && matches!(code, KeyCode::Esc) {
-            Ok(Self::Dismiss)
-        } else {
-            Err(format!(
-                "Unsupported key code {code:?} or modifier {modifiers:?}"
-            ))
-        }
-    }
+impl System {
+    /// noms des actions système dans la configuration
+    const NAMES: [(&'static str, Self); 15] = [
+        ("save", Self::Save),
+        ("quit", Self::Quit),
+        ("dismiss", Self::Dismiss),
+        ("search", Self::Search),
+        ("replace", Self::Replace),
+        ("toggle_line_ending", Self::ToggleLineEnding),
+        ("next_buffer", Self::NextBuffer),
+        ("previous_buffer", Self::PreviousBuffer),
+        ("pick_buffer", Self::PickBuffer),
+        ("cycle_line_numbers", Self::CycleLineNumbers),
+        ("toggle_soft_wrap", Self::ToggleSoftWrap),
+        ("toggle_auto_indent", Self::ToggleAutoIndent),
+        ("help", Self::Help),
+        ("set_mark", Self::SetMark),
+        ("goto_line", Self::GotoLine),
+    ];
+}
+
+/// actions sur le presse-papier
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardCommand {
+    Copy,
+    Cut,
+    Paste,
+    /// coupe jusqu'à la fin de la ligne
+    KillLine,
+    /// colle le dernier texte coupé ou copié
+    Yank,
+    /// remplace le texte qui vient d'être collé par le précédent
+    YankPop,
+}
+
+impl ClipboardCommand {
+    /// noms des actions du presse-papier dans la configuration
+    const NAMES: [(&'static str, Self); 6] = [
+        ("copy", Self::Copy),
+        ("cut", Self::Cut),
+        ("paste", Self::Paste),
+        ("kill_line", Self::KillLine),
+        ("yank", Self::Yank),
+        ("yank_pop", Self::YankPop),
+    ];
+}
+
+/// actions sur les fenêtres
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum WindowCommand {
+    Split(SplitDirection),
+    Focus(Direction),
+    FocusNext,
+    Grow,
+    Shrink,
+    Close,
+}
+
+impl WindowCommand {
+    /// noms des actions sur les fenêtres dans la configuration
+    const NAMES: [(&'static str, Self); 10] = [
+        ("split_horizontal", Self::Split(SplitDirection::Horizontal)),
+        ("split_vertical", Self::Split(SplitDirection::Vertical)),
+        ("focus_up", Self::Focus(Direction::Up)),
+        ("focus_down", Self::Focus(Direction::Down)),
+        ("focus_left", Self::Focus(Direction::Left)),
+        ("focus_right", Self::Focus(Direction::Right)),
+        ("focus_next", Self::FocusNext),
+        ("grow_window", Self::Grow),
+        ("shrink_window", Self::Shrink),
+        ("close_window", Self::Close),
+    ];
 }
 
 /// commandes pour l'utilisateur
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Command {
     Move(Move),
+    Select(Move),
     Edit(Edit),
     System(System),
+    Clipboard(ClipboardCommand),
+    Window(WindowCommand),
 }
 
-// clippy::as_conversions: Will run into problems for rare edge case systems where usize < u16
-#[allow(clippy::as_conversions)]
+impl Command {
+    /// retrouve une commande à partir de son nom dans la configuration (`save`, `select_up`...)
+    pub fn from_name(name: &str) -> Option<Self> {
+        if let Some(movement) = name.strip_prefix("select_") {
+            return from_table(&Move::NAMES, movement).map(Self::Select);
+        }
+        from_table(&Move::NAMES, name)
+            .map(Self::Move)
+            .or_else(|| from_table(&Edit::NAMES, name).map(Self::Edit))
+            .or_else(|| from_table(&System::NAMES, name).map(Self::System))
+            .or_else(|| from_table(&ClipboardCommand::NAMES, name).map(Self::Clipboard))
+            .or_else(|| from_table(&WindowCommand::NAMES, name).map(Self::Window))
+    }
+
+    /// nom de la commande dans la configuration, `select_` suivi du déplacement pour une sélection
+    pub fn name(self) -> String {
+        let name = match self {
+            Self::Move(movement) => name_in_table(&Move::NAMES, &movement),
+            Self::Select(movement) => {
+                return format!(
+                    "select_{}",
+                    name_in_table(&Move::NAMES, &movement).unwrap_or_default()
+                )
+            }
+            Self::Edit(edit) => name_in_table(&Edit::NAMES, &edit),
+            Self::System(system) => name_in_table(&System::NAMES, &system),
+            Self::Clipboard(clipboard) => name_in_table(&ClipboardCommand::NAMES, &clipboard),
+            Self::Window(window) => name_in_table(&WindowCommand::NAMES, &window),
+        };
+        name.unwrap_or("insert").to_string()
+    }
+}
+
+/// Les touches passent par le `Keymap` ; seul le redimensionnement est converti directement.
 impl TryFrom<Event> for Command {
     type Error = String;
     fn try_from(event: Event) -> Result<Self, Self::Error> {
         match event {
-            Event::Key(key_event) => Edit::try_from(key_event)
-                .map(Command::Edit)
-                .or_else(|_| Move::try_from(key_event).map(Command::Move))
-                .or_else(|_| System::try_from(key_event).map(Command::System))
-                .map_err(|_err| format!("Event not supported: {key_event:?}")),
             Event::Resize(width_u16, height_u16) => Ok(Self::System(System::Resize(Size {
-                height: height_u16 as usize,
-                width: width_u16 as usize,
+                height: usize::from(height_u16),
+                width: usize::from(width_u16),
             }))),
             _ => Err(format!("Event not supported: {event:?}")),
         }
     }
-}
\ No newline at end of file
+}
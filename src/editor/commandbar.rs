@@ -1,6 +1,6 @@
 use std::{cmp::min, io::Error};
 
-use super::{command::Edit, Line, Size, Terminal, UIComponent};
+use super::{command::Edit, Line, Position, Size, Terminal, UIComponent};
 
 #[derive(Default)]
 pub struct CommandBar {
@@ -14,11 +14,30 @@ impl CommandBar {
     pub fn handle_edit_command(&mut self, command: Edit) {
         match command {
             Edit::Insert(character) => self.value.append_char(character),
-            Edit::Delete | Edit::InsertNewline=> {}
+            Edit::Delete
+            | Edit::DeleteWord
+            | Edit::InsertNewline
+            | Edit::Undo
+            | Edit::Redo => {}
             Edit::DeleteBackward => self.value.delete_last(),
+            Edit::DeleteWordBackward => {
+                let start = self
+                    .value
+                    .previous_word_start(self.value.grapheme_count())
+                    .unwrap_or_default();
+                while self.value.grapheme_count() > start {
+                    self.value.delete_last();
+                }
+            }
         }
         self.set_needs_redraw(true);
     }
+    /// ajoute du texte collé à la valeur, seule sa première ligne est gardée
+    pub fn insert_text(&mut self, text: &str) {
+        let first_line = text.lines().next().unwrap_or_default();
+        self.value.append(&Line::from(first_line));
+        self.set_needs_redraw(true);
+    }
     pub fn caret_position_col(&self) -> usize {
         let max_width = self
             .prompt
@@ -32,6 +51,9 @@ impl CommandBar {
     pub fn set_prompt(&mut self, prompt: &str) {
         self.prompt = prompt.to_string();
     }
+    pub fn clear_value(&mut self) {
+        self.value = Line::default();
+    }
 }
 
 impl UIComponent for CommandBar {
@@ -44,7 +66,7 @@ impl UIComponent for CommandBar {
     fn set_size(&mut self, size: Size) {
         self.size = size;
     }
-    fn draw(&mut self, origin: usize) -> Result<(), Error> {
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
         let area_for_value = self.size.width.saturating_sub(self.prompt.len()); //this is how much space there is between the right side of the prompt and the edge of the bar 1Has a conversation.
         let value_end = self.value.width(); // we always want to show the left part of the value, therefore the end of the visible range we try to access will be equal to the full width
         let value_start = value_end.saturating_sub(area_for_value); //This should give us the start for the grapheme subrange we want to print out.
@@ -58,6 +80,6 @@ impl UIComponent for CommandBar {
         } else {
             String::new()
         };
-        Terminal::print_row(origin, &to_print)
+        Terminal::print_row(origin.y, &to_print)
     }
 }
\ No newline at end of file
@@ -0,0 +1,352 @@
+use std::{
+    collections::HashMap,
+    env, fs, mem,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crossterm::{event::KeyEvent, style::Color};
+use toml::{Table, Value};
+
+use super::{
+    command::Command,
+    keymap::{parse_keys, Keymap, Profile},
+    messagebar::DEFAULT_DURATION,
+    terminal::TextAttribute,
+    view::LineNumbers,
+    AnnotationType, ClipboardBackend, Indentation,
+};
+
+/// fichier de configuration de l'utilisateur, dans `$XDG_CONFIG_HOME/hecto/`
+const USER_CONFIG_FILE: &str = "config.toml";
+/// fichier de configuration d'un projet, cherché dans le dossier courant et ses parents
+const PROJECT_CONFIG_FILE: &str = ".hecto.toml";
+/// variable d'environnement activant la copie `fichier~` à chaque sauvegarde
+const BACKUP_ENV_VAR: &str = "HECTO_BACKUP";
+/// variable d'environnement activant le rechargement automatique des fichiers modifiés ailleurs
+const AUTORELOAD_ENV_VAR: &str = "HECTO_AUTORELOAD";
+/// variable d'environnement permettant de choisir le presse-papier système
+const CLIPBOARD_ENV_VAR: &str = "HECTO_CLIPBOARD";
+/// variable d'environnement donnant la distance entre deux taquets de tabulation
+const TAB_WIDTH_ENV_VAR: &str = "HECTO_TAB_WIDTH";
+/// largeurs de tabulation acceptées, dans les fichiers comme dans l'environnement
+const TAB_WIDTHS: RangeInclusive<usize> = 1..=32;
+const TAB_WIDTH_ERROR: &str = "expected a number between 1 and 32";
+/// variable d'environnement faisant insérer des espaces à la touche Tab
+const EXPAND_TABS_ENV_VAR: &str = "HECTO_EXPAND_TABS";
+/// variable d'environnement choisissant le jeu de raccourcis (`default`, `vim` ou `emacs`)
+const KEYMAP_ENV_VAR: &str = "HECTO_KEYMAP";
+const DEFAULT_QUIT_TIMES: u8 = 3;
+
+/// réglages de l'éditeur, lus dans les fichiers de configuration puis dans l'environnement
+pub struct Config {
+    pub indentation: Indentation,
+    /// indentation automatique propre à un type de fichier (nom en minuscules)
+    pub auto_indent_by_file_type: HashMap<String, bool>,
+    pub line_numbers: LineNumbers,
+    pub soft_wrap: bool,
+    /// durée d'affichage des messages
+    pub message_timeout: Duration,
+    /// nombre de Ctrl-Q pour quitter sans sauvegarder
+    pub quit_times: u8,
+    pub backup_on_save: bool,
+    /// recharge sans demander un fichier modifié ailleurs si le buffer n'a pas de modifications
+    pub auto_reload: bool,
+    pub clipboard: ClipboardBackend,
+    /// couleurs qui remplacent celles du thème par défaut
+    pub theme: Vec<(AnnotationType, TextAttribute)>,
+    /// raccourcis du profil, modifiés par les sections `[keys]`
+    pub keymap: Keymap,
+    pub profile: Profile,
+    /// raccourcis des sections `[keys]`, appliqués au profil une fois tous les fichiers lus
+    key_bindings: Vec<(Vec<KeyEvent>, Option<Command>)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            indentation: Indentation::default(),
+            auto_indent_by_file_type: HashMap::new(),
+            line_numbers: LineNumbers::default(),
+            soft_wrap: false,
+            message_timeout: DEFAULT_DURATION,
+            quit_times: DEFAULT_QUIT_TIMES,
+            backup_on_save: false,
+            auto_reload: false,
+            clipboard: ClipboardBackend::default(),
+            theme: Vec::new(),
+            keymap: Keymap::default(),
+            profile: Profile::default(),
+            key_bindings: Vec::new(),
+        }
+    }
+}
+
+impl Config {
+    /// lit la configuration de l'utilisateur, puis celle du projet, puis l'environnement.
+    /// Les erreurs sont retournées avec la configuration, sans empêcher le démarrage :
+    /// un réglage invalide garde sa valeur précédente.
+    pub fn load() -> (Self, Vec<String>) {
+        let mut config = Self::default();
+        let mut errors = Vec::new();
+        for path in Self::paths() {
+            match fs::read_to_string(&path) {
+                Ok(text) => config.apply_file(&path, &text, &mut errors),
+                Err(err) => errors.push(format!("{}: {err}", path.display())),
+            }
+        }
+        config.apply_env(&mut errors);
+        config.keymap = Keymap::for_profile(config.profile);
+        for (keys, command) in mem::take(&mut config.key_bindings) {
+            config.keymap.bind(keys, command);
+        }
+        (config, errors)
+    }
+
+    /// fichiers de configuration existants, du moins au plus prioritaire
+    fn paths() -> Vec<PathBuf> {
+        let config_home = env::var_os("XDG_CONFIG_HOME")
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")));
+        let user_config =
+            config_home.map(|dir| dir.join(env!("CARGO_PKG_NAME")).join(USER_CONFIG_FILE));
+        let project_config = env::current_dir().ok().and_then(|dir| {
+            dir.ancestors()
+                .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+                .find(|path| path.is_file())
+        });
+        let mut paths: Vec<PathBuf> = user_config
+            .into_iter()
+            .filter(|path| path.is_file())
+            .collect();
+        if let Some(project_config) = project_config {
+            if !paths.contains(&project_config) {
+                paths.push(project_config);
+            }
+        }
+        paths
+    }
+
+    fn apply_file(&mut self, path: &Path, text: &str, errors: &mut Vec<String>) {
+        let file_name = path.display();
+        let table = match text.parse::<Table>() {
+            Ok(table) => table,
+            Err(err) => {
+                let line = err.span().map_or(1, |span| {
+                    text.get(..span.start)
+                        .map_or(0, |before| before.matches('\n').count())
+                        .saturating_add(1)
+                });
+                let message = err.message().lines().collect::<Vec<_>>().join(", ");
+                errors.push(format!("{file_name}:{line}: {message}"));
+                return;
+            }
+        };
+        for (key, value) in &table {
+            if let Err(err) = self.apply_setting(key, value) {
+                errors.push(format!("{file_name}: {key}: {err}"));
+            }
+        }
+    }
+
+    fn apply_setting(&mut self, key: &str, value: &Value) -> Result<(), String> {
+        match key {
+            "tab_width" => {
+                self.indentation.tab_width = value
+                    .as_integer()
+                    .and_then(|tab_width| usize::try_from(tab_width).ok())
+                    .filter(|tab_width| TAB_WIDTHS.contains(tab_width))
+                    .ok_or(TAB_WIDTH_ERROR)?;
+            }
+            "expand_tabs" => self.indentation.expand_tabs = as_bool(value)?,
+            "auto_indent" => self.indentation.auto_indent = as_bool(value)?,
+            "soft_wrap" => self.soft_wrap = as_bool(value)?,
+            "backup" => self.backup_on_save = as_bool(value)?,
+            "auto_reload" => self.auto_reload = as_bool(value)?,
+            "line_numbers" => {
+                self.line_numbers = value
+                    .as_str()
+                    .and_then(LineNumbers::from_name)
+                    .ok_or("expected \"off\", \"absolute\", \"relative\" or \"hybrid\"")?;
+            }
+            "clipboard" => {
+                self.clipboard = value
+                    .as_str()
+                    .and_then(ClipboardBackend::from_name)
+                    .ok_or("unknown clipboard")?;
+            }
+            "message_timeout" => {
+                self.message_timeout = match value {
+                    Value::Integer(seconds) => {
+                        u64::try_from(*seconds).ok().map(Duration::from_secs)
+                    }
+                    Value::Float(seconds) => Duration::try_from_secs_f64(*seconds).ok(),
+                    _ => None,
+                }
+                .ok_or("expected a number of seconds")?;
+            }
+            "quit_times" => {
+                self.quit_times = value
+                    .as_integer()
+                    .and_then(|quit_times| u8::try_from(quit_times).ok())
+                    .filter(|quit_times| *quit_times > 0)
+                    .ok_or("expected a number between 1 and 255")?;
+            }
+            "keymap" => {
+                self.profile = value
+                    .as_str()
+                    .and_then(Profile::from_name)
+                    .ok_or("expected \"default\", \"vim\" or \"emacs\"")?;
+            }
+            "theme" => self.apply_theme(as_table(value)?)?,
+            "keys" => self.apply_key_bindings(as_table(value)?)?,
+            "file_type" => self.apply_file_types(as_table(value)?)?,
+            _ => return Err(String::from("unknown setting")),
+        }
+        Ok(())
+    }
+
+    /// `[theme]` : `keyword = "#c678dd"` ou `selection = { foreground = "black", background = "yellow" }`
+    fn apply_theme(&mut self, table: &Table) -> Result<(), String> {
+        for (name, value) in table {
+            let annotation_type = AnnotationType::from_name(name)
+                .ok_or_else(|| format!("unknown element \"{name}\""))?;
+            let attribute = match value {
+                Value::Table(colors) => TextAttribute {
+                    foreground: colors.get("foreground").map(parse_color).transpose()?,
+                    background: colors.get("background").map(parse_color).transpose()?,
+                    reverse: colors
+                        .get("reverse")
+                        .map(as_bool)
+                        .transpose()?
+                        .unwrap_or(false),
+                },
+                color => TextAttribute::colors(Some(parse_color(color)?), None),
+            };
+            self.theme
+                .retain(|(themed_type, _)| *themed_type != annotation_type);
+            self.theme.push((annotation_type, attribute));
+        }
+        Ok(())
+    }
+
+    /// `[keys]` : `"ctrl-k ctrl-d" = "delete"`, ou `"none"` pour libérer une touche
+    fn apply_key_bindings(&mut self, table: &Table) -> Result<(), String> {
+        for (sequence, value) in table {
+            let keys = parse_keys(sequence).ok_or_else(|| format!("unknown key \"{sequence}\""))?;
+            let name = value.as_str().ok_or("expected the name of an action")?;
+            let command = match name {
+                "none" => None,
+                _ => Some(
+                    Command::from_name(name).ok_or_else(|| format!("unknown action \"{name}\""))?,
+                ),
+            };
+            self.key_bindings.push((keys, command));
+        }
+        Ok(())
+    }
+
+    /// `[file_type.markdown]` : `auto_indent = false`
+    fn apply_file_types(&mut self, table: &Table) -> Result<(), String> {
+        for (file_type, settings) in table {
+            for (key, value) in as_table(settings)? {
+                match key.as_str() {
+                    "auto_indent" => {
+                        self.auto_indent_by_file_type
+                            .insert(file_type.to_lowercase(), as_bool(value)?);
+                    }
+                    _ => return Err(format!("{file_type}: unknown setting \"{key}\"")),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// les variables d'environnement `HECTO_*` l'emportent sur les fichiers ;
+    /// une largeur de tabulation invalide est signalée comme dans un fichier
+    fn apply_env(&mut self, errors: &mut Vec<String>) {
+        if let Some(backup_on_save) = env_flag(BACKUP_ENV_VAR) {
+            self.backup_on_save = backup_on_save;
+        }
+        if let Some(auto_reload) = env_flag(AUTORELOAD_ENV_VAR) {
+            self.auto_reload = auto_reload;
+        }
+        if let Some(expand_tabs) = env_flag(EXPAND_TABS_ENV_VAR) {
+            self.indentation.expand_tabs = expand_tabs;
+        }
+        if let Some(clipboard) = env::var(CLIPBOARD_ENV_VAR)
+            .ok()
+            .and_then(|name| ClipboardBackend::from_name(&name))
+        {
+            self.clipboard = clipboard;
+        }
+        if let Some(profile) = env::var(KEYMAP_ENV_VAR)
+            .ok()
+            .and_then(|name| Profile::from_name(&name))
+        {
+            self.profile = profile;
+        }
+        if let Ok(value) = env::var(TAB_WIDTH_ENV_VAR) {
+            match value.parse().ok().filter(|tab_width| TAB_WIDTHS.contains(tab_width)) {
+                Some(tab_width) => self.indentation.tab_width = tab_width,
+                None => errors.push(format!("{TAB_WIDTH_ENV_VAR}: {TAB_WIDTH_ERROR}")),
+            }
+        }
+    }
+
+    /// indentation automatique pour un type de fichier
+    pub fn auto_indent_for(&self, file_type: &str) -> bool {
+        self.auto_indent_by_file_type
+            .get(&file_type.to_lowercase())
+            .copied()
+            .unwrap_or(self.indentation.auto_indent)
+    }
+}
+
+/// `Some(true)` si la variable d'environnement vaut `1` ou `true`, `None` si elle n'existe pas
+fn env_flag(name: &str) -> Option<bool> {
+    env::var(name)
+        .ok()
+        .map(|value| value == "1" || value == "true")
+}
+
+fn as_bool(value: &Value) -> Result<bool, String> {
+    value
+        .as_bool()
+        .ok_or_else(|| String::from("expected true or false"))
+}
+
+fn as_table(value: &Value) -> Result<&Table, String> {
+    value
+        .as_table()
+        .ok_or_else(|| String::from("expected a table"))
+}
+
+/// couleur écrite `#rrggbb`, avec son nom (`dark_grey`) ou son numéro dans la palette 256 couleurs
+fn parse_color(value: &Value) -> Result<Color, String> {
+    let invalid = || format!("invalid color {value}");
+    if let Some(index) = value.as_integer() {
+        return u8::try_from(index)
+            .map(Color::AnsiValue)
+            .map_err(|_| invalid());
+    }
+    let name = value.as_str().ok_or_else(invalid)?;
+    if let Some(hex) = name.strip_prefix('#') {
+        let channel = |range| {
+            hex.get(range)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .ok_or_else(invalid)
+        };
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+        return Ok(Color::Rgb {
+            r: channel(0..2)?,
+            g: channel(2..4)?,
+            b: channel(4..6)?,
+        });
+    }
+    Color::try_from(name).map_err(|()| invalid())
+}
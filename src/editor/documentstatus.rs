@@ -5,7 +5,15 @@ pub struct DocumentStatus {
     pub total_lines: usize,
     pub current_line_index: usize,
     pub is_modified: bool,
+    /// le fichier n'existe pas encore sur le disque
+    pub is_new_file: bool,
     pub file_name: String,
+    pub file_type: String,
+    pub file_format: String,
+    pub buffer_index: usize,
+    pub buffer_count: usize,
+    /// mode d'édition à la Vim, seulement dans la fenêtre active
+    pub mode: Option<&'static str>,
 }
 
 impl DocumentStatus {
@@ -17,12 +25,30 @@ impl DocumentStatus {
             String::new()
         }
     }
+    /// nom du fichier, suivi de `[New File]` s'il n'existe pas encore
+    pub fn file_name_to_string(&self) -> String {
+        if self.is_new_file {
+            format!("{} [New File]", self.file_name)
+        } else {
+            self.file_name.clone()
+        }
+    }
+    /// position du buffer dans la liste des buffers ouverts, par exemple `2/3`
+    pub fn buffer_indicator_to_string(&self) -> String {
+        format!("{}/{}", self.buffer_index.saturating_add(1), self.buffer_count)
+    }
+    /// mode d'édition suivi d'un espace, vide sans le mode Vim
+    pub fn mode_to_string(&self) -> String {
+        self.mode.map_or_else(String::new, |mode| format!("{mode} "))
+    }
     pub fn line_count_to_string(&self) -> String {
         format!("{} lines", self.total_lines)
     }
     pub fn position_indicator_to_string(&self) -> String {
         format!(
-            "{}/{}",
+            "{} | {} | {}/{}",
+            self.file_type,
+            self.file_format,
             self.current_line_index.saturating_add(1),
             self.total_lines
         )
@@ -0,0 +1,175 @@
+use std::path::Path;
+
+/// ligne visée par un saut ; les numéros commencent à 1
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineTarget {
+    Absolute(usize),
+    /// tant de lignes après le curseur
+    Forward(usize),
+    /// tant de lignes avant le curseur
+    Backward(usize),
+}
+
+/// saut vers une ligne, et une colonne (en graphèmes, à partir de 1) si elle est donnée
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GotoTarget {
+    pub line: LineTarget,
+    pub column: Option<usize>,
+}
+
+impl GotoTarget {
+    /// lit `42`, `42:7`, `+3`, `-3`, ou `:7` pour une colonne de la ligne courante
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let (line, column) = match text.split_once(':') {
+            Some((line, column)) => (line.trim(), Some(column.trim().parse().ok()?)),
+            None => (text, None),
+        };
+        let line = if line.is_empty() && column.is_some() {
+            LineTarget::Forward(0)
+        } else if let Some(count) = line.strip_prefix('+') {
+            LineTarget::Forward(count.parse().ok()?)
+        } else if let Some(count) = line.strip_prefix('-') {
+            LineTarget::Backward(count.parse().ok()?)
+        } else {
+            LineTarget::Absolute(line.parse().ok()?)
+        };
+        Some(Self { line, column })
+    }
+
+    const fn line(number: usize) -> Self {
+        Self {
+            line: LineTarget::Absolute(number),
+            column: None,
+        }
+    }
+}
+
+/// fichier donné sur la ligne de commande, avec la position où l'ouvrir
+pub struct FileArgument {
+    pub path: String,
+    pub target: Option<GotoTarget>,
+}
+
+/// lit les arguments de la ligne de commande : `+42` s'applique au fichier qui suit,
+/// `fichier:42` et `fichier:42:7` (comme dans les messages des compilateurs) ouvrent
+/// le fichier à cette position, sauf si un fichier porte ce nom
+pub fn parse_arguments(arguments: impl IntoIterator<Item = String>) -> Vec<FileArgument> {
+    let mut files = Vec::new();
+    let mut pending = None;
+    for argument in arguments {
+        if let Some(number) = argument
+            .strip_prefix('+')
+            .and_then(|number| number.parse().ok())
+        {
+            pending = Some(GotoTarget::line(number));
+            continue;
+        }
+        let (path, target) = split_position(&argument);
+        files.push(FileArgument {
+            path,
+            target: target.or_else(|| pending.take()),
+        });
+        pending = None;
+    }
+    files
+}
+
+/// sépare `fichier:42:7` ou `fichier:42:` en chemin et position
+fn split_position(argument: &str) -> (String, Option<GotoTarget>) {
+    if Path::new(argument).exists() {
+        return (argument.to_string(), None);
+    }
+    let is_number = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+    let trimmed = argument.strip_suffix(':').unwrap_or(argument);
+    let Some((rest, last)) = trimmed.rsplit_once(':').filter(|(_, last)| is_number(last)) else {
+        return (argument.to_string(), None);
+    };
+    let position = match rest.rsplit_once(':') {
+        Some((path, line)) if is_number(line) && !path.is_empty() => line
+            .parse()
+            .ok()
+            .zip(last.parse().ok())
+            .map(|(line, column)| {
+                let target = GotoTarget {
+                    column: Some(column),
+                    ..GotoTarget::line(line)
+                };
+                (path, target)
+            }),
+        _ if !rest.is_empty() => last
+            .parse()
+            .ok()
+            .map(|line| (rest, GotoTarget::line(line))),
+        _ => None,
+    };
+    position.map_or_else(
+        || (argument.to_string(), None),
+        |(path, target)| (path.to_string(), Some(target)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_arguments, split_position, GotoTarget, LineTarget};
+
+    const fn target(line: LineTarget, column: Option<usize>) -> GotoTarget {
+        GotoTarget { line, column }
+    }
+
+    #[test]
+    fn parse_lines_and_columns() {
+        assert_eq!(GotoTarget::parse("42"), Some(target(LineTarget::Absolute(42), None)));
+        assert_eq!(GotoTarget::parse("42:7"), Some(target(LineTarget::Absolute(42), Some(7))));
+        assert_eq!(GotoTarget::parse(" +3 "), Some(target(LineTarget::Forward(3), None)));
+        assert_eq!(GotoTarget::parse("-3"), Some(target(LineTarget::Backward(3), None)));
+        assert_eq!(GotoTarget::parse(":7"), Some(target(LineTarget::Forward(0), Some(7))));
+        assert_eq!(GotoTarget::parse(""), None);
+        assert_eq!(GotoTarget::parse("42:"), None);
+        assert_eq!(GotoTarget::parse("abc"), None);
+    }
+
+    #[test]
+    fn split_compiler_positions() {
+        assert_eq!(
+            split_position("missing-file.rs:42:7"),
+            (
+                String::from("missing-file.rs"),
+                Some(target(LineTarget::Absolute(42), Some(7)))
+            )
+        );
+        assert_eq!(
+            split_position("missing-file.rs:42:"),
+            (
+                String::from("missing-file.rs"),
+                Some(target(LineTarget::Absolute(42), None))
+            )
+        );
+        assert_eq!(split_position("missing-file.rs"), (String::from("missing-file.rs"), None));
+    }
+
+    #[test]
+    fn existing_file_with_colon_is_not_split() {
+        let path = std::env::temp_dir().join(format!("hecto-goto-{}:12", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let name = path.to_string_lossy().to_string();
+        assert_eq!(split_position(&name), (name.clone(), None));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn line_argument_applies_to_next_file() {
+        let files = parse_arguments(["+42", "missing-file.rs", "other.rs"].map(String::from));
+        let files: Vec<_> = files.into_iter().map(|file| (file.path, file.target)).collect();
+        assert_eq!(
+            files,
+            [
+                (
+                    String::from("missing-file.rs"),
+                    Some(target(LineTarget::Absolute(42), None))
+                ),
+                (String::from("other.rs"), None),
+            ]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::io::Error;
+
+use super::{
+    command::Move,
+    keymap::{keys_to_string, Keymap},
+    Position, Size, Terminal, UIComponent,
+};
+
+/// liste des raccourcis actifs, affichée à la place des fenêtres
+#[derive(Default)]
+pub struct HelpScreen {
+    lines: Vec<String>,
+    /// première ligne affichée sous le titre
+    scroll: usize,
+    size: Size,
+    needs_redraw: bool,
+}
+
+impl HelpScreen {
+    pub fn new(keymap: &Keymap) -> Self {
+        let keys_width = keymap
+            .bindings()
+            .iter()
+            .map(|binding| keys_to_string(&binding.keys).len())
+            .max()
+            .unwrap_or_default();
+        let lines = keymap
+            .bindings()
+            .iter()
+            .map(|binding| {
+                format!(
+                    "  {:<keys_width$}  {}",
+                    keys_to_string(&binding.keys),
+                    binding.command.name()
+                )
+            })
+            .collect();
+        Self {
+            lines,
+            scroll: 0,
+            size: Size::default(),
+            needs_redraw: true,
+        }
+    }
+
+    /// lignes visibles sous le titre
+    fn page_height(&self) -> usize {
+        self.size.height.saturating_sub(1)
+    }
+
+    /// fait défiler la liste, sans dépasser sa fin
+    pub fn scroll(&mut self, movement: Move) {
+        let last = self.lines.len().saturating_sub(self.page_height());
+        self.scroll = match movement {
+            Move::Up | Move::Left => self.scroll.saturating_sub(1),
+            Move::Down | Move::Right => self.scroll.saturating_add(1),
+            Move::PageUp => self.scroll.saturating_sub(self.page_height()),
+            Move::PageDown => self.scroll.saturating_add(self.page_height()),
+            Move::StartOfLine | Move::WordLeft | Move::StartOfBuffer => 0,
+            Move::EndOfLine | Move::WordRight | Move::EndOfBuffer => last,
+        }
+        .min(last);
+        self.set_needs_redraw(true);
+    }
+}
+
+impl UIComponent for HelpScreen {
+    fn set_needs_redraw(&mut self, value: bool) {
+        self.needs_redraw = value;
+    }
+
+    fn needs_redraw(&self) -> bool {
+        self.needs_redraw
+    }
+
+    fn set_size(&mut self, size: Size) {
+        self.size = size;
+    }
+
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
+        let width = self.size.width;
+        Terminal::print_inverted_row(
+            origin,
+            width,
+            "Key bindings (arrows to scroll, any other key to close)",
+        )?;
+        for row in 1..self.size.height {
+            let line = self
+                .lines
+                .get(self.scroll.saturating_add(row).saturating_sub(1))
+                .map_or("", String::as_str);
+            Terminal::print_row(origin.y.saturating_add(row), &format!("{line:.width$}"))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+pub const DEFAULT_TAB_WIDTH: usize = 4;
+
+/// réglages d'indentation d'un buffer
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Indentation {
+    /// distance entre deux taquets de tabulation, au moins 1
+    pub tab_width: usize,
+    /// Tab insère des espaces jusqu'au taquet suivant au lieu d'une tabulation
+    pub expand_tabs: bool,
+    /// Entrée reprend l'indentation de la ligne et l'augmente après une ouverture de bloc
+    pub auto_indent: bool,
+}
+
+impl Default for Indentation {
+    fn default() -> Self {
+        Self {
+            tab_width: DEFAULT_TAB_WIDTH,
+            expand_tabs: false,
+            auto_indent: true,
+        }
+    }
+}
+
+impl Indentation {
+    /// un niveau d'indentation
+    pub fn unit(self) -> String {
+        if self.expand_tabs {
+            " ".repeat(self.tab_width)
+        } else {
+            String::from('\t')
+        }
+    }
+
+    /// texte inséré par Entrée entre `before` et `after`, les deux parties de la ligne
+    /// coupée par le curseur, avec le nombre de graphèmes de l'indentation de la nouvelle
+    /// ligne. La nouvelle ligne reprend l'indentation de la ligne coupée, un niveau de plus
+    /// si `before` finit par un caractère de `indent_after` ; entre deux crochets appariés,
+    /// si l'ouvrant est dans `indent_after`, le crochet fermant passe sur sa propre ligne,
+    /// à l'indentation d'origine.
+    pub fn newline_text(self, before: &str, after: &str, indent_after: &[char]) -> (String, usize) {
+        if !self.auto_indent {
+            return (String::from('\n'), 0);
+        }
+        let leading_len = before.len().saturating_sub(before.trim_start().len());
+        let leading = before.get(..leading_len).unwrap_or_default();
+        let opening = before
+            .trim_end()
+            .chars()
+            .last()
+            .filter(|opening| indent_after.contains(opening));
+        let mut indent = String::from(leading);
+        if opening.is_some() {
+            indent.push_str(&self.unit());
+        }
+        let mut text = format!("\n{indent}");
+        let closing = opening.and_then(closing_bracket);
+        if closing.is_some() && after.trim_start().chars().next() == closing {
+            text.push('\n');
+            text.push_str(leading);
+        }
+        (text, indent.chars().count())
+    }
+
+    /// texte inséré par la touche Tab quand le curseur est à la colonne donnée
+    pub fn tab_text(self, column: usize) -> String {
+        if self.expand_tabs {
+            " ".repeat(tab_stop_distance(column, self.tab_width))
+        } else {
+            String::from('\t')
+        }
+    }
+}
+
+/// nombre de colonnes entre la colonne donnée et le taquet suivant
+pub const fn tab_stop_distance(column: usize, tab_width: usize) -> usize {
+    let tab_width = if tab_width == 0 { 1 } else { tab_width };
+    match column.checked_rem(tab_width) {
+        Some(offset) => tab_width.saturating_sub(offset),
+        None => tab_width,
+    }
+}
+
+/// taquet qui précède la colonne donnée (la colonne elle-même exclue)
+pub const fn previous_tab_stop(column: usize, tab_width: usize) -> usize {
+    let column = column.saturating_sub(1);
+    match column.checked_rem(tab_width) {
+        Some(offset) => column.saturating_sub(offset),
+        None => column,
+    }
+}
+
+/// crochet qui ferme celui donné
+const fn closing_bracket(opening: char) -> Option<char> {
+    match opening {
+        '{' => Some('}'),
+        '(' => Some(')'),
+        '[' => Some(']'),
+        _ => None,
+    }
+}
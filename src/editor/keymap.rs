@@ -0,0 +1,367 @@
+use crossterm::event::{
+    KeyCode::{
+        self, Backspace, Char, Delete, Down, End, Enter, Esc, Home, Left, PageDown, PageUp, Right,
+        Tab, Up,
+    },
+    KeyEvent, KeyModifiers,
+};
+
+use super::command::{Command, Edit};
+
+/// raccourcis par défaut : suite de touches et nom de l'action
+const DEFAULT_KEYMAP: &[(&str, &str)] = &[
+    ("up", "up"),
+    ("down", "down"),
+    ("left", "left"),
+    ("right", "right"),
+    ("pageup", "page_up"),
+    ("pagedown", "page_down"),
+    ("home", "line_start"),
+    ("end", "line_end"),
+    ("shift-up", "select_up"),
+    ("shift-down", "select_down"),
+    ("shift-left", "select_left"),
+    ("shift-right", "select_right"),
+    ("shift-pageup", "select_page_up"),
+    ("shift-pagedown", "select_page_down"),
+    ("shift-home", "select_line_start"),
+    ("shift-end", "select_line_end"),
+    ("ctrl-left", "word_left"),
+    ("ctrl-right", "word_right"),
+    ("ctrl-home", "buffer_start"),
+    ("ctrl-end", "buffer_end"),
+    ("ctrl-shift-left", "select_word_left"),
+    ("ctrl-shift-right", "select_word_right"),
+    ("ctrl-shift-home", "select_buffer_start"),
+    ("ctrl-shift-end", "select_buffer_end"),
+    ("tab", "tab"),
+    ("enter", "newline"),
+    ("backspace", "delete_backward"),
+    ("delete", "delete"),
+    ("ctrl-backspace", "delete_word_backward"),
+    ("alt-backspace", "delete_word_backward"),
+    ("ctrl-delete", "delete_word"),
+    ("alt-d", "delete_word"),
+    ("ctrl-z", "undo"),
+    ("ctrl-y", "redo"),
+    ("ctrl-c", "copy"),
+    ("ctrl-x", "cut"),
+    ("ctrl-v", "paste"),
+    ("ctrl-q", "quit"),
+    ("ctrl-s", "save"),
+    ("ctrl-f", "search"),
+    ("ctrl-r", "replace"),
+    ("ctrl-t", "toggle_line_ending"),
+    ("ctrl-n", "next_buffer"),
+    ("ctrl-p", "previous_buffer"),
+    ("ctrl-b", "pick_buffer"),
+    ("ctrl-l", "cycle_line_numbers"),
+    ("ctrl-g", "goto_line"),
+    ("alt-z", "toggle_soft_wrap"),
+    ("alt-i", "toggle_auto_indent"),
+    ("esc", "dismiss"),
+    ("f1", "help"),
+    ("alt-s", "split_horizontal"),
+    ("alt-v", "split_vertical"),
+    ("alt-up", "focus_up"),
+    ("alt-down", "focus_down"),
+    ("alt-left", "focus_left"),
+    ("alt-right", "focus_right"),
+    ("alt-o", "focus_next"),
+    ("alt-=", "grow_window"),
+    ("alt-+", "grow_window"),
+    ("alt--", "shrink_window"),
+    ("alt-w", "close_window"),
+];
+
+/// raccourcis du profil Emacs ; `ctrl-7` est ce que la plupart des terminaux envoient pour `ctrl-/`
+const EMACS_KEYMAP: &[(&str, &str)] = &[
+    ("up", "up"),
+    ("down", "down"),
+    ("left", "left"),
+    ("right", "right"),
+    ("pageup", "page_up"),
+    ("pagedown", "page_down"),
+    ("home", "line_start"),
+    ("end", "line_end"),
+    ("ctrl-p", "up"),
+    ("ctrl-n", "down"),
+    ("ctrl-b", "left"),
+    ("ctrl-f", "right"),
+    ("ctrl-a", "line_start"),
+    ("ctrl-e", "line_end"),
+    ("alt-b", "word_left"),
+    ("alt-f", "word_right"),
+    ("ctrl-left", "word_left"),
+    ("ctrl-right", "word_right"),
+    ("alt-<", "buffer_start"),
+    ("alt->", "buffer_end"),
+    ("ctrl-home", "buffer_start"),
+    ("ctrl-end", "buffer_end"),
+    ("alt-v", "page_up"),
+    ("ctrl-v", "page_down"),
+    ("shift-up", "select_up"),
+    ("shift-down", "select_down"),
+    ("shift-left", "select_left"),
+    ("shift-right", "select_right"),
+    ("tab", "tab"),
+    ("enter", "newline"),
+    ("backspace", "delete_backward"),
+    ("delete", "delete"),
+    ("ctrl-d", "delete"),
+    ("alt-d", "delete_word"),
+    ("alt-backspace", "delete_word_backward"),
+    ("ctrl-backspace", "delete_word_backward"),
+    ("ctrl-delete", "delete_word"),
+    ("ctrl-space", "set_mark"),
+    ("ctrl-w", "cut"),
+    ("alt-w", "copy"),
+    ("ctrl-k", "kill_line"),
+    ("ctrl-y", "yank"),
+    ("alt-y", "yank_pop"),
+    ("ctrl-/", "undo"),
+    ("ctrl-7", "undo"),
+    ("ctrl-_", "undo"),
+    ("ctrl-x u", "undo"),
+    ("ctrl-g", "dismiss"),
+    ("esc", "dismiss"),
+    ("ctrl-s", "search"),
+    ("ctrl-r", "search"),
+    ("alt-%", "replace"),
+    ("alt-g g", "goto_line"),
+    ("alt-g alt-g", "goto_line"),
+    ("ctrl-x ctrl-s", "save"),
+    ("ctrl-x ctrl-c", "quit"),
+    ("ctrl-x b", "pick_buffer"),
+    ("ctrl-x right", "next_buffer"),
+    ("ctrl-x left", "previous_buffer"),
+    ("ctrl-x 2", "split_horizontal"),
+    ("ctrl-x 3", "split_vertical"),
+    ("ctrl-x o", "focus_next"),
+    ("ctrl-x 0", "close_window"),
+    ("ctrl-x ^", "grow_window"),
+    ("ctrl-x -", "shrink_window"),
+    ("ctrl-x l", "cycle_line_numbers"),
+    ("ctrl-x w", "toggle_soft_wrap"),
+    ("ctrl-x i", "toggle_auto_indent"),
+    ("ctrl-x ctrl-t", "toggle_line_ending"),
+    ("f1", "help"),
+];
+
+/// jeu de raccourcis choisi dans la configuration
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Profile {
+    #[default]
+    Default,
+    /// édition modale à la Vim, les raccourcis restent actifs en mode insertion
+    Vim,
+    Emacs,
+}
+
+impl Profile {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::Default),
+            "vim" => Some(Self::Vim),
+            "emacs" => Some(Self::Emacs),
+            _ => None,
+        }
+    }
+}
+
+/// suite de touches associée à une commande
+pub struct Binding {
+    pub keys: Vec<KeyEvent>,
+    pub command: Command,
+}
+
+/// ce que produit une touche pressée
+pub enum KeyResult {
+    Command(Command),
+    /// début d'une suite de touches, on attend la suite
+    Pending(Vec<KeyEvent>),
+    /// suite de touches qui ne correspond à aucune commande
+    Unbound(Vec<KeyEvent>),
+}
+
+/// associe des suites de touches (`ctrl-k ctrl-c`) aux commandes de l'éditeur
+pub struct Keymap {
+    bindings: Vec<Binding>,
+    /// touches déjà pressées d'une suite en cours
+    pending: Vec<KeyEvent>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::for_profile(Profile::Default)
+    }
+}
+
+impl Keymap {
+    /// raccourcis de départ d'un profil
+    pub fn for_profile(profile: Profile) -> Self {
+        let table = match profile {
+            Profile::Default | Profile::Vim => DEFAULT_KEYMAP,
+            Profile::Emacs => EMACS_KEYMAP,
+        };
+        let mut keymap = Self {
+            bindings: Vec::new(),
+            pending: Vec::new(),
+        };
+        for (keys, name) in table {
+            if let (Some(keys), Some(command)) = (parse_keys(keys), Command::from_name(name)) {
+                keymap.bind(keys, Some(command));
+            }
+        }
+        keymap
+    }
+
+    /// associe une suite de touches à une commande, ou la libère avec `None`
+    pub fn bind(&mut self, keys: Vec<KeyEvent>, command: Option<Command>) {
+        self.bindings.retain(|binding| binding.keys != keys);
+        if let Some(command) = command {
+            self.bindings.push(Binding { keys, command });
+        }
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    /// première suite de touches associée à une commande
+    pub fn keys_for(&self, command: Command) -> Option<&[KeyEvent]> {
+        self.bindings
+            .iter()
+            .find(|binding| binding.command == command)
+            .map(|binding| binding.keys.as_slice())
+    }
+
+    /// traite une touche pressée. Une suite qui commence une suite plus longue attend la
+    /// touche suivante ; hors d'une suite, un caractère sans raccourci est du texte tapé.
+    pub fn resolve(&mut self, key: &KeyEvent) -> KeyResult {
+        self.pending.push(key_chord(key));
+        if self.bindings.iter().any(|binding| {
+            binding.keys.len() > self.pending.len() && binding.keys.starts_with(&self.pending)
+        }) {
+            return KeyResult::Pending(self.pending.clone());
+        }
+        let keys = std::mem::take(&mut self.pending);
+        if let Some(binding) = self.bindings.iter().find(|binding| binding.keys == keys) {
+            return KeyResult::Command(binding.command);
+        }
+        match keys.as_slice() {
+            [KeyEvent {
+                code: Char(character),
+                modifiers: KeyModifiers::NONE,
+                ..
+            }] => KeyResult::Command(Command::Edit(Edit::Insert(*character))),
+            _ => KeyResult::Unbound(keys),
+        }
+    }
+}
+
+/// lit une suite de touches séparées par des espaces, comme `ctrl-k ctrl-c`
+pub fn parse_keys(sequence: &str) -> Option<Vec<KeyEvent>> {
+    let keys: Option<Vec<KeyEvent>> = sequence.split_whitespace().map(parse_key).collect();
+    keys.filter(|keys| !keys.is_empty())
+}
+
+/// lit une combinaison de touches écrite comme `ctrl-s`, `alt-shift-left` ou `f5`
+fn parse_key(chord: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut parts: Vec<&str> = chord.split('-').collect();
+    // `ctrl--` : la touche elle-même est un tiret
+    let key = match parts.pop()? {
+        "" if parts.last() == Some(&"") => {
+            parts.pop();
+            "-"
+        }
+        key => key,
+    };
+    for modifier in parts {
+        modifiers |= match modifier.to_ascii_lowercase().as_str() {
+            "ctrl" => KeyModifiers::CONTROL,
+            "alt" => KeyModifiers::ALT,
+            "shift" => KeyModifiers::SHIFT,
+            _ => return None,
+        };
+    }
+    let code = match key.to_ascii_lowercase().as_str() {
+        "up" => Up,
+        "down" => Down,
+        "left" => Left,
+        "right" => Right,
+        "home" => Home,
+        "end" => End,
+        "pageup" => PageUp,
+        "pagedown" => PageDown,
+        "enter" => Enter,
+        "tab" => Tab,
+        "backspace" => Backspace,
+        "delete" | "del" => Delete,
+        "esc" => Esc,
+        "space" => Char(' '),
+        lower => match (lower.strip_prefix('f'), key.chars().count()) {
+            (Some(number), _) if !number.is_empty() => KeyCode::F(number.parse().ok()?),
+            (_, 1) => Char(key.chars().next()?),
+            _ => return None,
+        },
+    };
+    Some(key_chord(&KeyEvent::new(code, modifiers)))
+}
+
+/// forme normalisée d'une touche, pour comparer une touche pressée à une touche configurée :
+/// la casse d'un caractère remplace Shift
+pub fn key_chord(event: &KeyEvent) -> KeyEvent {
+    match event.code {
+        Char(character) if event.modifiers.contains(KeyModifiers::SHIFT) => KeyEvent::new(
+            Char(character.to_ascii_uppercase()),
+            event.modifiers.difference(KeyModifiers::SHIFT),
+        ),
+        code => KeyEvent::new(code, event.modifiers),
+    }
+}
+
+/// écrit une suite de touches pour l'utilisateur, comme `Ctrl-K Ctrl-C`
+pub fn keys_to_string(keys: &[KeyEvent]) -> String {
+    keys.iter().map(key_to_string).collect::<Vec<_>>().join(" ")
+}
+
+fn key_to_string(key: &KeyEvent) -> String {
+    let mut result = String::new();
+    for (modifier, name) in [
+        (KeyModifiers::CONTROL, "Ctrl-"),
+        (KeyModifiers::ALT, "Alt-"),
+        (KeyModifiers::SHIFT, "Shift-"),
+    ] {
+        if key.modifiers.contains(modifier) {
+            result.push_str(name);
+        }
+    }
+    let name = match key.code {
+        Char(' ') => String::from("Space"),
+        // les lettres des raccourcis s'écrivent en majuscules, comme Ctrl-S
+        Char(character) if !key.modifiers.is_empty() && character.is_uppercase() => {
+            format!("Shift-{character}")
+        }
+        Char(character) if !key.modifiers.is_empty() => character.to_uppercase().to_string(),
+        Char(character) => character.to_string(),
+        KeyCode::F(number) => format!("F{number}"),
+        Up => String::from("Up"),
+        Down => String::from("Down"),
+        Left => String::from("Left"),
+        Right => String::from("Right"),
+        Home => String::from("Home"),
+        End => String::from("End"),
+        PageUp => String::from("PageUp"),
+        PageDown => String::from("PageDown"),
+        Enter => String::from("Enter"),
+        Tab => String::from("Tab"),
+        Backspace => String::from("Backspace"),
+        Delete => String::from("Delete"),
+        Esc => String::from("Esc"),
+        code => format!("{code:?}"),
+    };
+    result.push_str(&name);
+    result
+}
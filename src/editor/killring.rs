@@ -0,0 +1,48 @@
+/// nombre de textes gardés, les plus anciens sont oubliés
+const KILL_RING_CAPACITY: usize = 60;
+
+/// textes coupés ou copiés, du plus ancien au plus récent, que `yank` et `yank_pop` collent
+#[derive(Default)]
+pub struct KillRing {
+    entries: Vec<String>,
+    /// distance depuis le plus récent du texte collé par le dernier `yank` ou `yank_pop`
+    yank_offset: usize,
+}
+
+impl KillRing {
+    /// ajoute un texte ; avec `append`, il prolonge le précédent (suppressions consécutives)
+    pub fn push(&mut self, text: String, append: bool) {
+        match self.entries.last_mut() {
+            Some(last) if append => last.push_str(&text),
+            _ => {
+                if self.entries.len() >= KILL_RING_CAPACITY {
+                    self.entries.remove(0);
+                }
+                self.entries.push(text);
+            }
+        }
+    }
+
+    /// texte le plus récent
+    pub fn yank(&mut self) -> Option<&str> {
+        self.yank_offset = 0;
+        self.entries.last().map(String::as_str)
+    }
+
+    /// texte qui précède celui du dernier collage, en revenant au plus récent après le plus ancien
+    pub fn yank_pop(&mut self) -> Option<&str> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        self.yank_offset = self
+            .yank_offset
+            .saturating_add(1)
+            .checked_rem(self.entries.len())
+            .unwrap_or_default();
+        self.entries
+            .len()
+            .checked_sub(self.yank_offset.saturating_add(1))
+            .and_then(|index| self.entries.get(index))
+            .map(String::as_str)
+    }
+}
@@ -0,0 +1,604 @@
+use std::{collections::BTreeMap, io::Error};
+
+use super::{Position, Size, StatusBar, Terminal, View};
+
+/// part minimale d'une fenêtre dans un découpage, en pourcentage
+const MIN_RATIO: usize = 10;
+/// variation de la part d'une fenêtre à chaque agrandissement ou réduction
+const RATIO_STEP: usize = 10;
+/// hauteur minimale d'une fenêtre : une ligne de texte et sa ligne de status
+const MIN_PANE_HEIGHT: usize = 2;
+const MIN_PANE_WIDTH: usize = 1;
+/// caractère de la colonne séparant deux fenêtres côte à côte
+const SEPARATOR: &str = "│";
+
+pub type PaneId = usize;
+
+/// zone rectangulaire de l'écran
+#[derive(Copy, Clone, Default)]
+pub struct Rect {
+    pub origin: Position,
+    pub size: Size,
+}
+
+impl Rect {
+    const fn bottom(&self) -> usize {
+        self.origin.y.saturating_add(self.size.height)
+    }
+
+    const fn right(&self) -> usize {
+        self.origin.x.saturating_add(self.size.width)
+    }
+}
+
+/// fenêtre : une vue et sa ligne de status, sur la dernière ligne de la fenêtre
+#[derive(Default)]
+pub struct Pane {
+    pub view: View,
+    pub status_bar: StatusBar,
+}
+
+/// sens du découpage d'une fenêtre
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum SplitDirection {
+    /// fenêtres l'une au-dessus de l'autre
+    Horizontal,
+    /// fenêtres côte à côte, séparées par une colonne
+    Vertical,
+}
+
+/// direction pour passer à la fenêtre voisine
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// arbre des découpages : chaque feuille est une fenêtre
+enum Node {
+    Pane(PaneId),
+    Split {
+        direction: SplitDirection,
+        /// part de la première fenêtre, en pourcentage
+        ratio: usize,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+impl Node {
+    /// fenêtres de l'arbre, de haut en bas et de gauche à droite
+    fn pane_ids(&self, ids: &mut Vec<PaneId>) {
+        match self {
+            Self::Pane(id) => ids.push(*id),
+            Self::Split { first, second, .. } => {
+                first.pane_ids(ids);
+                second.pane_ids(ids);
+            }
+        }
+    }
+
+    fn first_pane_id(&self) -> PaneId {
+        match self {
+            Self::Pane(id) => *id,
+            Self::Split { first, .. } => first.first_pane_id(),
+        }
+    }
+
+    fn contains(&self, id: PaneId) -> bool {
+        match self {
+            Self::Pane(pane_id) => *pane_id == id,
+            Self::Split { first, second, .. } => first.contains(id) || second.contains(id),
+        }
+    }
+
+    /// plus petite taille que peut prendre le sous-arbre sans qu'une fenêtre disparaisse
+    fn min_size(&self) -> Size {
+        match self {
+            Self::Pane(_) => Size {
+                height: MIN_PANE_HEIGHT,
+                width: MIN_PANE_WIDTH,
+            },
+            Self::Split {
+                direction,
+                first,
+                second,
+                ..
+            } => {
+                let (first, second) = (first.min_size(), second.min_size());
+                match direction {
+                    SplitDirection::Horizontal => Size {
+                        height: first.height.saturating_add(second.height),
+                        width: first.width.max(second.width),
+                    },
+                    SplitDirection::Vertical => Size {
+                        height: first.height.max(second.height),
+                        width: first.width.saturating_add(second.width).saturating_add(1),
+                    },
+                }
+            }
+        }
+    }
+
+    /// taille de la première branche : la part demandée, ramenée entre les tailles minimales
+    /// des deux branches. Si la zone est trop petite pour les deux, la première est servie d'abord.
+    // we allow this since we don't care if the split is _exactly_ at the ratio
+    #[allow(clippy::integer_division)]
+    fn first_extent(total: usize, ratio: usize, first_min: usize, second_min: usize) -> usize {
+        (total.saturating_mul(ratio) / 100)
+            .min(total.saturating_sub(second_min))
+            .max(first_min)
+            .min(total)
+    }
+
+    /// découpe une zone entre les deux branches d'un découpage, de tailles minimales données.
+    /// Le découpage vertical garde une colonne pour le séparateur.
+    fn split_rect(
+        rect: Rect,
+        direction: SplitDirection,
+        ratio: usize,
+        (first_min, second_min): (Size, Size),
+    ) -> (Rect, Rect, Option<Rect>) {
+        match direction {
+            SplitDirection::Horizontal => {
+                let first_height =
+                    Self::first_extent(rect.size.height, ratio, first_min.height, second_min.height);
+                let first = Rect {
+                    origin: rect.origin,
+                    size: Size {
+                        height: first_height,
+                        width: rect.size.width,
+                    },
+                };
+                let second = Rect {
+                    origin: Position {
+                        x: rect.origin.x,
+                        y: rect.origin.y.saturating_add(first_height),
+                    },
+                    size: Size {
+                        height: rect.size.height.saturating_sub(first_height),
+                        width: rect.size.width,
+                    },
+                };
+                (first, second, None)
+            }
+            SplitDirection::Vertical => {
+                let available = rect.size.width.saturating_sub(1);
+                let first_width =
+                    Self::first_extent(available, ratio, first_min.width, second_min.width);
+                let first = Rect {
+                    origin: rect.origin,
+                    size: Size {
+                        height: rect.size.height,
+                        width: first_width,
+                    },
+                };
+                let separator = Rect {
+                    origin: Position {
+                        x: first.right(),
+                        y: rect.origin.y,
+                    },
+                    size: Size {
+                        height: rect.size.height,
+                        width: 1,
+                    },
+                };
+                let second = Rect {
+                    origin: Position {
+                        x: separator.right(),
+                        y: rect.origin.y,
+                    },
+                    size: Size {
+                        height: rect.size.height,
+                        width: available.saturating_sub(first_width),
+                    },
+                };
+                (first, second, Some(separator))
+            }
+        }
+    }
+
+    /// calcule la zone de chaque fenêtre et des séparateurs
+    fn layout(&self, rect: Rect, panes: &mut Vec<(PaneId, Rect)>, separators: &mut Vec<Rect>) {
+        match self {
+            Self::Pane(id) => panes.push((*id, rect)),
+            Self::Split {
+                direction,
+                ratio,
+                first,
+                second,
+            } => {
+                let minimums = (first.min_size(), second.min_size());
+                let (first_rect, second_rect, separator) =
+                    Self::split_rect(rect, *direction, *ratio, minimums);
+                first.layout(first_rect, panes, separators);
+                second.layout(second_rect, panes, separators);
+                separators.extend(separator);
+            }
+        }
+    }
+
+    /// remplace la fenêtre par un découpage entre elle et une nouvelle fenêtre
+    fn split(&mut self, id: PaneId, new_id: PaneId, direction: SplitDirection) {
+        match self {
+            Self::Pane(pane_id) if *pane_id == id => {
+                *self = Self::Split {
+                    direction,
+                    ratio: 50,
+                    first: Box::new(Self::Pane(id)),
+                    second: Box::new(Self::Pane(new_id)),
+                };
+            }
+            Self::Pane(_) => {}
+            Self::Split { first, second, .. } => {
+                first.split(id, new_id, direction);
+                second.split(id, new_id, direction);
+            }
+        }
+    }
+
+    /// retire la fenêtre de l'arbre : le découpage qui la contenait est remplacé par l'autre branche
+    fn remove(&mut self, id: PaneId) {
+        let Self::Split { first, second, .. } = self else {
+            return;
+        };
+        let remaining = if matches!(**first, Self::Pane(pane_id) if pane_id == id) {
+            std::mem::replace(&mut **second, Self::Pane(id))
+        } else if matches!(**second, Self::Pane(pane_id) if pane_id == id) {
+            std::mem::replace(&mut **first, Self::Pane(id))
+        } else {
+            first.remove(id);
+            second.remove(id);
+            return;
+        };
+        *self = remaining;
+    }
+
+    /// change la part de la fenêtre dans le découpage le plus proche qui la contient.
+    /// Retourne `false` si la fenêtre n'est dans aucun découpage.
+    fn resize(&mut self, id: PaneId, grow: bool) -> bool {
+        let Self::Split {
+            ratio,
+            first,
+            second,
+            ..
+        } = self
+        else {
+            return false;
+        };
+        if first.resize(id, grow) || second.resize(id, grow) {
+            return true;
+        }
+        let first_grows = if first.contains(id) {
+            grow
+        } else if second.contains(id) {
+            !grow
+        } else {
+            return false;
+        };
+        *ratio = if first_grows {
+            ratio.saturating_add(RATIO_STEP)
+        } else {
+            ratio.saturating_sub(RATIO_STEP)
+        }
+        .clamp(MIN_RATIO, 100_usize.saturating_sub(MIN_RATIO));
+        true
+    }
+}
+
+/// découpage de la zone d'édition en fenêtres.
+/// La fenêtre active est gardée par l'éditeur, `Layout` garde les autres.
+pub struct Layout {
+    root: Node,
+    focused: PaneId,
+    panes: BTreeMap<PaneId, Pane>,
+    next_id: PaneId,
+    size: Size,
+    needs_redraw: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self {
+            root: Node::Pane(0),
+            focused: 0,
+            panes: BTreeMap::new(),
+            next_id: 1,
+            size: Size::default(),
+            needs_redraw: true,
+        }
+    }
+}
+
+impl Layout {
+    pub const fn focused(&self) -> PaneId {
+        self.focused
+    }
+
+    /// fenêtre inactive
+    pub fn pane_mut(&mut self, id: PaneId) -> Option<&mut Pane> {
+        self.panes.get_mut(&id)
+    }
+
+    /// fenêtres inactives
+    pub fn panes_mut(&mut self) -> impl Iterator<Item = &mut Pane> {
+        self.panes.values_mut()
+    }
+
+    /// met à jour la taille de la zone d'édition
+    pub fn set_size(&mut self, size: Size) {
+        self.size = size;
+        self.needs_redraw = true;
+    }
+
+    /// zone de chaque fenêtre, dans l'ordre de l'arbre
+    pub fn rects(&self) -> Vec<(PaneId, Rect)> {
+        let mut panes = Vec::new();
+        self.root.layout(self.area(), &mut panes, &mut Vec::new());
+        panes
+    }
+
+    /// zone de la fenêtre active
+    pub fn focused_rect(&self) -> Rect {
+        self.rects()
+            .into_iter()
+            .find(|(id, _)| *id == self.focused)
+            .map(|(_, rect)| rect)
+            .unwrap_or_default()
+    }
+
+    const fn area(&self) -> Rect {
+        Rect {
+            origin: Position { x: 0, y: 0 },
+            size: self.size,
+        }
+    }
+
+    /// vrai si la fenêtre active est assez grande pour être découpée :
+    /// chaque moitié doit garder au moins une ligne de texte et sa ligne de status
+    pub fn can_split(&self, direction: SplitDirection) -> bool {
+        let Size { height, width } = self.focused_rect().size;
+        match direction {
+            SplitDirection::Horizontal => height >= MIN_PANE_HEIGHT.saturating_mul(2),
+            SplitDirection::Vertical => width >= MIN_PANE_WIDTH.saturating_mul(2).saturating_add(1),
+        }
+    }
+
+    /// découpe la fenêtre active en deux. La nouvelle fenêtre, en bas ou à droite,
+    /// devient active : `current` est la fenêtre qui était active.
+    pub fn split(&mut self, direction: SplitDirection, current: Pane) {
+        let new_id = self.next_id;
+        self.next_id = self.next_id.saturating_add(1);
+        self.root.split(self.focused, new_id, direction);
+        self.panes.insert(self.focused, current);
+        self.focused = new_id;
+        self.needs_redraw = true;
+    }
+
+    /// rend active une autre fenêtre : `current` est la fenêtre qui était active,
+    /// la fenêtre retournée est celle qui le devient (`current` si `id` n'existe pas)
+    pub fn focus(&mut self, id: PaneId, current: Pane) -> Pane {
+        let Some(pane) = self.panes.remove(&id) else {
+            return current;
+        };
+        self.panes.insert(self.focused, current);
+        self.focused = id;
+        pane
+    }
+
+    /// ferme la fenêtre active et retourne celle qui la remplace,
+    /// `None` s'il ne reste qu'une fenêtre
+    pub fn close(&mut self) -> Option<Pane> {
+        if self.panes.is_empty() {
+            return None;
+        }
+        let closed = self.focused;
+        self.root.remove(closed);
+        let mut ids = Vec::new();
+        self.root.pane_ids(&mut ids);
+        // la fenêtre qui suivait prend la place, ou la première s'il n'y en avait pas
+        let next = ids
+            .into_iter()
+            .find(|id| *id > closed)
+            .unwrap_or_else(|| self.root.first_pane_id());
+        self.focused = next;
+        self.needs_redraw = true;
+        self.panes.remove(&next)
+    }
+
+    /// fenêtre suivante dans l'ordre de l'arbre, en revenant à la première après la dernière
+    pub fn next_pane(&self) -> PaneId {
+        let mut ids = Vec::new();
+        self.root.pane_ids(&mut ids);
+        let position = ids.iter().position(|id| *id == self.focused).unwrap_or_default();
+        ids.get(position.saturating_add(1))
+            .or_else(|| ids.first())
+            .copied()
+            .unwrap_or(self.focused)
+    }
+
+    /// fenêtre voisine dans une direction : parmi celles qui touchent la fenêtre active
+    /// de ce côté, celle qui est la plus proche du point donné (le curseur)
+    pub fn neighbor(&self, direction: Direction, from: Position) -> Option<PaneId> {
+        let rects = self.rects();
+        let current = rects
+            .iter()
+            .find(|(id, _)| *id == self.focused)
+            .map(|(_, rect)| *rect)?;
+        rects
+            .into_iter()
+            .filter(|(id, rect)| {
+                *id != self.focused
+                    && match direction {
+                        Direction::Up => rect.bottom() <= current.origin.y,
+                        Direction::Down => rect.origin.y >= current.bottom(),
+                        Direction::Left => rect.right() <= current.origin.x,
+                        Direction::Right => rect.origin.x >= current.right(),
+                    }
+            })
+            .min_by_key(|(_, rect)| {
+                let gap = match direction {
+                    Direction::Up => current.origin.y.saturating_sub(rect.bottom()),
+                    Direction::Down => rect.origin.y.saturating_sub(current.bottom()),
+                    Direction::Left => current.origin.x.saturating_sub(rect.right()),
+                    Direction::Right => rect.origin.x.saturating_sub(current.right()),
+                };
+                let offset = match direction {
+                    Direction::Up | Direction::Down => {
+                        distance_to_range(from.x, rect.origin.x, rect.right())
+                    }
+                    Direction::Left | Direction::Right => {
+                        distance_to_range(from.y, rect.origin.y, rect.bottom())
+                    }
+                };
+                (gap, offset)
+            })
+            .map(|(id, _)| id)
+    }
+
+    /// agrandit (ou réduit) la fenêtre active. Retourne `false` s'il n'y a qu'une fenêtre.
+    pub fn resize_focused(&mut self, grow: bool) -> bool {
+        let resized = self.root.resize(self.focused, grow);
+        self.needs_redraw |= resized;
+        resized
+    }
+
+    /// dessine les séparateurs entre fenêtres côte à côte, si le découpage a changé
+    pub fn render_separators(&mut self) {
+        if !self.needs_redraw {
+            return;
+        }
+        let mut separators = Vec::new();
+        self.root.layout(self.area(), &mut Vec::new(), &mut separators);
+        let result: Result<(), Error> = separators.iter().try_for_each(|separator| {
+            (separator.origin.y..separator.bottom()).try_for_each(|y| {
+                Terminal::move_cursor_to(Position {
+                    x: separator.origin.x,
+                    y,
+                })?;
+                Terminal::print(SEPARATOR)
+            })
+        });
+        if result.is_ok() {
+            self.needs_redraw = false;
+        }
+    }
+}
+
+/// distance entre une coordonnée et l'intervalle `[start, end)`
+const fn distance_to_range(value: usize, start: usize, end: usize) -> usize {
+    if value < start {
+        start.saturating_sub(value)
+    } else if value >= end {
+        value.saturating_sub(end).saturating_add(1)
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Direction, Layout, Pane, PaneId, Position, Size, SplitDirection};
+
+    fn layout(height: usize, width: usize) -> Layout {
+        let mut layout = Layout::default();
+        layout.set_size(Size { height, width });
+        layout
+    }
+
+    /// (fenêtre, ligne, colonne, hauteur, largeur) de chaque fenêtre
+    fn rects(layout: &Layout) -> Vec<(PaneId, usize, usize, usize, usize)> {
+        layout
+            .rects()
+            .into_iter()
+            .map(|(id, rect)| (id, rect.origin.y, rect.origin.x, rect.size.height, rect.size.width))
+            .collect()
+    }
+
+    #[test]
+    fn split_shares_the_area_and_focuses_the_new_pane() {
+        let mut layout = layout(24, 80);
+        layout.split(SplitDirection::Horizontal, Pane::default());
+        assert_eq!(layout.focused(), 1);
+        assert_eq!(rects(&layout), [(0, 0, 0, 12, 80), (1, 12, 0, 12, 80)]);
+        layout.split(SplitDirection::Vertical, Pane::default());
+        assert_eq!(layout.focused(), 2);
+        // une colonne est gardée pour le séparateur
+        assert_eq!(
+            rects(&layout),
+            [(0, 0, 0, 12, 80), (1, 12, 0, 12, 39), (2, 12, 40, 12, 40)]
+        );
+    }
+
+    #[test]
+    fn close_gives_the_area_back() {
+        let mut layout = layout(24, 80);
+        assert!(layout.close().is_none());
+        layout.split(SplitDirection::Vertical, Pane::default());
+        layout.split(SplitDirection::Horizontal, Pane::default());
+        assert!(layout.close().is_some());
+        assert_eq!(layout.focused(), 0);
+        assert_eq!(rects(&layout), [(0, 0, 0, 24, 39), (1, 0, 40, 24, 40)]);
+        assert!(layout.close().is_some());
+        assert_eq!(layout.focused(), 1);
+        assert_eq!(rects(&layout), [(1, 0, 0, 24, 80)]);
+    }
+
+    #[test]
+    fn resize_changes_the_share_of_the_focused_pane() {
+        let mut layout = layout(20, 80);
+        assert!(!layout.resize_focused(true));
+        layout.split(SplitDirection::Horizontal, Pane::default());
+        assert!(layout.resize_focused(true));
+        assert_eq!(rects(&layout), [(0, 0, 0, 8, 80), (1, 8, 0, 12, 80)]);
+        for _ in 0..20 {
+            layout.resize_focused(true);
+        }
+        assert_eq!(rects(&layout), [(0, 0, 0, 2, 80), (1, 2, 0, 18, 80)]);
+    }
+
+    #[test]
+    fn panes_keep_their_minimum_size() {
+        let mut small = layout(5, 80);
+        small.split(SplitDirection::Horizontal, Pane::default());
+        for _ in 0..20 {
+            small.resize_focused(true);
+        }
+        // 10 % de 5 lignes ne laisserait aucune ligne à la première fenêtre
+        assert_eq!(rects(&small), [(0, 0, 0, 2, 80), (1, 2, 0, 3, 80)]);
+
+        let mut nested = layout(10, 80);
+        nested.split(SplitDirection::Horizontal, Pane::default());
+        nested.split(SplitDirection::Horizontal, Pane::default());
+        assert!(!nested.can_split(SplitDirection::Horizontal));
+        nested.focus(0, Pane::default());
+        for _ in 0..20 {
+            nested.resize_focused(true);
+        }
+        // la branche du bas contient deux fenêtres, elle garde deux fois la hauteur minimale
+        assert_eq!(
+            rects(&nested),
+            [(0, 0, 0, 6, 80), (1, 6, 0, 2, 80), (2, 8, 0, 2, 80)]
+        );
+    }
+
+    #[test]
+    fn neighbor_is_the_closest_pane_on_that_side() {
+        let mut layout = layout(24, 80);
+        layout.split(SplitDirection::Vertical, Pane::default());
+        layout.split(SplitDirection::Horizontal, Pane::default());
+        // fenêtres : 0 à gauche, 1 en haut à droite, 2 en bas à droite (active)
+        let caret = Position { x: 50, y: 20 };
+        assert_eq!(layout.neighbor(Direction::Up, caret), Some(1));
+        assert_eq!(layout.neighbor(Direction::Left, caret), Some(0));
+        assert_eq!(layout.neighbor(Direction::Right, caret), None);
+        assert_eq!(layout.neighbor(Direction::Down, caret), None);
+        layout.focus(0, Pane::default());
+        let caret = Position { x: 10, y: 2 };
+        assert_eq!(layout.neighbor(Direction::Right, caret), Some(1));
+        let caret = Position { x: 10, y: 20 };
+        assert_eq!(layout.neighbor(Direction::Right, caret), Some(2));
+    }
+}
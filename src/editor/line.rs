@@ -1,92 +1,115 @@
-use std::{fmt, ops::Range};
+use std::{cell::RefCell, cmp::{max, min}, fmt, ops::Range};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
+use super::{
+    indentation::{previous_tab_stop, tab_stop_distance, DEFAULT_TAB_WIDTH},
+    AnnotatedString, Annotation, AnnotationType, Pattern,
+};
+
+mod chunks;
+use chunks::Chunks;
 
 // en gros type pour une ligne
 
-#[derive(Default)]
 pub struct Line {
-    fragments: Vec<TextFragment>,
+    string: String,
+    /// distance entre deux taquets de tabulation
+    tab_width: usize,
+    /// morceaux de la ligne, découpés en graphèmes seulement quand on y accède
+    chunks: RefCell<Chunks>,
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self {
+            string: String::new(),
+            tab_width: DEFAULT_TAB_WIDTH,
+            chunks: RefCell::default(),
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
 enum GraphemeWidth {
     Half,
-    Full
+    Full,
+    /// tabulation, qui s'étend jusqu'au taquet suivant
+    Tab(usize),
 }
 
 impl GraphemeWidth {
-    const fn saturating_add(self, other: usize) -> usize {
+    const fn to_usize(self) -> usize {
         match self {
-            Self::Half => other.saturating_add(1),
-            Self::Full => other.saturating_add(2),
+            Self::Half => 1,
+            Self::Full => 2,
+            Self::Tab(width) => width,
         }
     }
 }
 
-#[derive(Debug)]
+#[derive(Copy, Clone, Debug)]
 struct TextFragment {
-    grapheme: String,
+    start_byte_idx: usize,
+    byte_len: usize,
     rendered_width: GraphemeWidth,
-    replacement: Option<char>
+    replacement: Option<char>,
+}
+
+impl TextFragment {
+    const fn end_byte_idx(&self) -> usize {
+        self.start_byte_idx.saturating_add(self.byte_len)
+    }
 }
 
 impl Line {
 
     /// instancie une Line à partir d'une string brute
     pub fn from(line_str: &str) -> Self {
-        let fragments = Self::str_to_fragments(line_str);
-            Self { fragments }
+        Self::with_tab_width(line_str, DEFAULT_TAB_WIDTH)
+    }
+
+    /// instancie une Line dont les tabulations s'étendent jusqu'au taquet suivant
+    pub fn with_tab_width(line_str: &str, tab_width: usize) -> Self {
+        Self {
+            chunks: RefCell::new(Chunks::new(line_str)),
+            string: String::from(line_str),
+            tab_width,
+        }
     }
 
     /// concat une ligne à une autre
     pub fn append(&mut self, other: &Self) {
-        let mut concat = self.to_string();
-        concat.push_str(&other.to_string());
-        self.fragments = Self::str_to_fragments(&concat);
-    }
-
-    /// permet de convertir une string en un vecteur de TextFragment, avec les graphèmes etc
-    fn str_to_fragments(line_str: &str) -> Vec<TextFragment> {
-        line_str
-            .graphemes(true)
-            .map(|grapheme| {
-                let (replacement, rendered_width) = Self::replacement_character(grapheme)
-                    .map_or_else(
-                        || {
-                            let unicode_width = grapheme.width();
-                            let rendered_width = match unicode_width {
-                                0 | 1 => GraphemeWidth::Half,
-                                _ => GraphemeWidth::Full,
-                            };
-                            (None, rendered_width)
-                        },
-                        |replacement| (Some(replacement), GraphemeWidth::Half),
-                    );
-
-                TextFragment {
-                    grapheme: grapheme.to_string(),
-                    rendered_width,
-                    replacement,
-                }
-            })
-            .collect()
+        let end = self.string.len();
+        self.replace_bytes(end..end, &other.string);
+    }
+
+    /// remplace une plage d'octets par `text` ; seuls les morceaux touchés sont redécoupés
+    pub fn replace_bytes(&mut self, byte_range: Range<usize>, text: &str) {
+        if byte_range.start > byte_range.end
+            || !self.string.is_char_boundary(byte_range.start)
+            || !self.string.is_char_boundary(byte_range.end)
+        {
+            return;
+        }
+        self.string.replace_range(byte_range.clone(), text);
+        self.chunks
+            .get_mut()
+            .edited(&self.string, &byte_range, text.len());
+    }
+
+    /// retourne le fragment du graphème donné
+    fn fragment(&self, grapheme_idx: usize) -> Option<TextFragment> {
+        let mut chunks = self.chunks.borrow_mut();
+        let info = chunks.find(&self.string, self.tab_width, |info| {
+            info.end_grapheme_idx() > grapheme_idx
+        })?;
+        chunks.fragment(&info, grapheme_idx.saturating_sub(info.first_grapheme_idx))
     }
 
     /// permet d'insérer un charactère dans une ligne
     pub fn insert_char(&mut self, character: char, at: usize) {
-        let mut result = String::new();
-
-        for (index, fragment) in self.fragments.iter().enumerate() {
-            if index == at {
-                result.push(character);
-            }
-            result.push_str(&fragment.grapheme);
-        }
-        if at >= self.fragments.len() {
-            result.push(character);
-        }
-        self.fragments = Self::str_to_fragments(&result);
+        let byte_idx = self.grapheme_idx_to_byte_idx(at);
+        self.replace_bytes(byte_idx..byte_idx, character.encode_utf8(&mut [0; 4]));
     }
 
     /// cas où un char n'est pas supporté
@@ -94,7 +117,6 @@ impl Line {
         let width = for_str.width();
         match for_str {
             " " => None,
-            "\t" => Some(' '),
             _ if width > 0 && for_str.trim().is_empty() => Some('␣'),
             _ if width == 0 => {
                 let mut chars = for_str.chars();
@@ -109,36 +131,329 @@ impl Line {
         }
     }
 
+    /// construit une annotation couvrant la plage de graphèmes donnée
+    pub fn annotation_for(&self, kind: AnnotationType, range: Range<usize>) -> Annotation {
+        Annotation {
+            kind,
+            start_byte_idx: self.grapheme_idx_to_byte_idx(range.start),
+            end_byte_idx: self.grapheme_idx_to_byte_idx(range.end),
+        }
+    }
+
     /// convertit les graphemes bizarres en grapèmes ok
     pub fn get_visible_graphemes(&self, range: Range<usize>) -> String {
+        self.get_annotated_visible_substr(range, &[]).to_string()
+    }
+
+    /// annote les correspondances de `pattern`.
+    /// `selected_match` est l'index du graphème où commence la correspondance courante.
+    pub fn match_annotations(
+        &self,
+        pattern: &Pattern,
+        selected_match: Option<usize>,
+    ) -> Vec<Annotation> {
+        self.find_all(pattern)
+            .into_iter()
+            .map(|line_match| Annotation {
+                kind: if selected_match == Some(line_match.grapheme_range.start) {
+                    AnnotationType::SelectedMatch
+                } else {
+                    AnnotationType::Match
+                },
+                start_byte_idx: line_match.byte_range.start,
+                end_byte_idx: line_match.byte_range.end,
+            })
+            .collect()
+    }
+
+    /// retourne la partie visible de la ligne avec les annotations données (en index d'octets),
+    /// les dernières annotations étant prioritaires. Seuls les graphèmes visibles sont parcourus.
+    pub fn get_annotated_visible_substr(
+        &self,
+        range: Range<usize>,
+        annotations: &[Annotation],
+    ) -> AnnotatedString {
         if range.start >= range.end {
-            return String::new();
+            return AnnotatedString::default();
         }
-        let mut result = String::new();
-        let mut current_pos = 0;
-        for fragment in &self.fragments {
-            let fragment_end = fragment.rendered_width.saturating_add(current_pos);
-            if current_pos >= range.end {
-                break;
+        let (graphemes, first_column) = self.graphemes_in_columns(range.clone());
+        let visible_start = self.grapheme_idx_to_byte_idx(graphemes.start);
+        let visible_end = self.grapheme_idx_to_byte_idx(graphemes.end);
+        let mut result =
+            AnnotatedString::from(self.string.get(visible_start..visible_end).unwrap_or_default());
+        for annotation in annotations {
+            let start = annotation.start_byte_idx.clamp(visible_start, visible_end);
+            let end = annotation.end_byte_idx.clamp(visible_start, visible_end);
+            if start < end {
+                result.add_annotation(
+                    annotation.kind,
+                    start.saturating_sub(visible_start),
+                    end.saturating_sub(visible_start),
+                );
             }
-            if fragment_end > range.start {
-                if fragment_end > range.end || current_pos < range.start {
-                    // Clip on the right or left
-                    result.push('⋯');
-                } else if let Some(char) = fragment.replacement {
-                    result.push(char);
+        }
+
+        // On remplace les graphèmes en partant de la fin, pour que les index d'octets
+        // des fragments restants restent valides.
+        let visible_width: usize = graphemes
+            .clone()
+            .filter_map(|idx| self.fragment(idx))
+            .map(|fragment| fragment.rendered_width.to_usize())
+            .sum();
+        let mut fragment_start = first_column.saturating_add(visible_width);
+        for fragment in graphemes.rev().filter_map(|idx| self.fragment(idx)) {
+            let fragment_end = fragment_start;
+            fragment_start = fragment_start.saturating_sub(fragment.rendered_width.to_usize());
+            let start_byte_idx = fragment.start_byte_idx.saturating_sub(visible_start);
+            let end_byte_idx = fragment.end_byte_idx().saturating_sub(visible_start);
+            if fragment_end > range.end || fragment_start < range.start {
+                // Clip on the right or left
+                let clipped = if let GraphemeWidth::Tab(_) = fragment.rendered_width {
+                    // une tabulation coupée garde ses colonnes visibles
+                    let visible_columns = min(fragment_end, range.end)
+                        .saturating_sub(max(fragment_start, range.start));
+                    " ".repeat(visible_columns)
                 } else {
-                    result.push_str(&fragment.grapheme);
-                }
+                    String::from("⋯")
+                };
+                result.replace(start_byte_idx, end_byte_idx, &clipped);
+            } else if let Some(replacement) = fragment.replacement {
+                let replacement =
+                    replacement.to_string().repeat(fragment.rendered_width.to_usize());
+                result.replace(start_byte_idx, end_byte_idx, &replacement);
             }
-            current_pos = fragment_end;
         }
         result
     }
 
+    /// graphèmes au moins en partie visibles entre les colonnes données,
+    /// avec la colonne où commence le premier d'entre eux
+    fn graphemes_in_columns(&self, columns: Range<usize>) -> (Range<usize>, usize) {
+        // on saute directement au morceau où commence la partie visible
+        let found = {
+            let mut chunks = self.chunks.borrow_mut();
+            let found = chunks.find(&self.string, self.tab_width, |info| {
+                info.end_column() > columns.start
+            });
+            found.map(|info| (info.first_grapheme_idx, info.start_column)).ok_or_else(|| {
+                let (count, width) = chunks.totals(&self.string, self.tab_width);
+                (count..count, width)
+            })
+        };
+        let (mut idx, mut column) = match found {
+            Ok(start) => start,
+            Err(past_end) => return past_end,
+        };
+        while let Some(fragment) = self.fragment(idx) {
+            let fragment_end = column.saturating_add(fragment.rendered_width.to_usize());
+            if fragment_end > columns.start {
+                break;
+            }
+            column = fragment_end;
+            idx = idx.saturating_add(1);
+        }
+        let (start, start_column) = (idx, column);
+        while column < columns.end {
+            let Some(fragment) = self.fragment(idx) else {
+                break;
+            };
+            column = column.saturating_add(fragment.rendered_width.to_usize());
+            idx = idx.saturating_add(1);
+        }
+        (start..idx, start_column)
+    }
+
+    /// découpe la ligne en rangées d'au plus `width` colonnes, pour le retour à la ligne
+    /// automatique. La coupure se fait de préférence après une espace ; un graphème large
+    /// qui dépasserait du bord passe entièrement à la rangée suivante.
+    /// Retourne la plage de graphèmes de chaque rangée, il y en a toujours au moins une.
+    pub fn wrap(&self, width: usize) -> Vec<Range<usize>> {
+        let count = self.grapheme_count();
+        if width == 0 {
+            return vec![Range { start: 0, end: count }];
+        }
+        let mut rows = Vec::new();
+        let mut row_start = 0;
+        let mut row_width: usize = 0;
+        // graphème qui suit la dernière espace de la rangée en cours
+        let mut after_last_space = None;
+        let mut idx = 0;
+        while let Some(fragment) = self.fragment(idx) {
+            let grapheme_width = fragment.rendered_width.to_usize();
+            if row_width.saturating_add(grapheme_width) > width && idx > row_start {
+                let row_end = after_last_space
+                    .filter(|after_space| *after_space > row_start)
+                    .unwrap_or(idx);
+                rows.push(row_start..row_end);
+                row_start = row_end;
+                row_width = self.width_of(row_start..idx);
+                after_last_space = None;
+                continue;
+            }
+            row_width = row_width.saturating_add(grapheme_width);
+            if self.is_whitespace(fragment) {
+                after_last_space = Some(idx.saturating_add(1));
+            }
+            idx = idx.saturating_add(1);
+        }
+        rows.push(row_start..count);
+        rows
+    }
+
+    fn is_whitespace(&self, fragment: TextFragment) -> bool {
+        self.string
+            .get(fragment.start_byte_idx..fragment.end_byte_idx())
+            .is_some_and(|grapheme| grapheme.chars().all(char::is_whitespace))
+    }
+
+    /// largeur d'une plage de graphèmes
+    pub fn width_of(&self, graphemes: Range<usize>) -> usize {
+        graphemes
+            .filter_map(|idx| self.fragment(idx))
+            .map(|fragment| fragment.rendered_width.to_usize())
+            .sum()
+    }
+
+    /// graphème de la plage qui occupe la colonne donnée (comptée depuis le début de la plage),
+    /// ou la fin de la plage si la colonne est au-delà
+    pub fn grapheme_idx_at_column(&self, graphemes: Range<usize>, column: usize) -> usize {
+        let mut start_column: usize = 0;
+        for idx in graphemes.clone() {
+            let end_column = start_column.saturating_add(
+                self.fragment(idx)
+                    .map_or(0, |fragment| fragment.rendered_width.to_usize()),
+            );
+            if end_column > column {
+                return idx;
+            }
+            start_column = end_column;
+        }
+        graphemes.end
+    }
+
+    /// début de l'indentation retirée par un retour arrière au graphème donné : si le curseur
+    /// est dans les espaces du début de la ligne, on revient jusqu'au taquet précédent.
+    /// Retourne `None` hors de l'indentation, où le retour arrière ne retire qu'un graphème.
+    pub fn unindent_start(&self, grapheme_idx: usize) -> Option<usize> {
+        let leading_whitespace = (0..grapheme_idx)
+            .all(|idx| matches!(self.grapheme(idx), Some(" " | "\t")));
+        if grapheme_idx == 0
+            || !leading_whitespace
+            || self.grapheme(grapheme_idx.saturating_sub(1)) != Some(" ")
+        {
+            return None;
+        }
+        let tab_stop = previous_tab_stop(self.width_until(grapheme_idx), self.tab_width);
+        let mut start = grapheme_idx;
+        while start > 0
+            && self.grapheme(start.saturating_sub(1)) == Some(" ")
+            && self.width_until(start.saturating_sub(1)) >= tab_stop
+        {
+            start = start.saturating_sub(1);
+        }
+        Some(start)
+    }
+
+    /// texte du graphème donné
+    fn grapheme(&self, grapheme_idx: usize) -> Option<&str> {
+        self.fragment(grapheme_idx)
+            .and_then(|fragment| self.string.get(fragment.start_byte_idx..fragment.end_byte_idx()))
+    }
+
+    /// classe d'un graphème pour les déplacements par mot ; au-delà de la ligne, un blanc
+    fn word_class(&self, grapheme_idx: usize) -> WordClass {
+        match self.grapheme(grapheme_idx).and_then(|grapheme| grapheme.chars().next()) {
+            None => WordClass::Blank,
+            Some(character) if character.is_whitespace() => WordClass::Blank,
+            Some(character) => match cjk_script(character) {
+                Some(script) => WordClass::Cjk(script),
+                None if character.is_alphanumeric() || character == '_' => WordClass::Word,
+                None => WordClass::Punctuation,
+            },
+        }
+    }
+
+    /// vrai si le graphème est un blanc ou s'il est au-delà de la ligne
+    pub fn is_blank(&self, grapheme_idx: usize) -> bool {
+        self.word_class(grapheme_idx) == WordClass::Blank
+    }
+
+    /// premier graphème qui n'est pas un blanc, ou la fin de la ligne
+    pub fn first_non_blank(&self) -> usize {
+        (0..self.grapheme_count())
+            .find(|idx| self.word_class(*idx) != WordClass::Blank)
+            .unwrap_or_else(|| self.grapheme_count())
+    }
+
+    /// plages de graphèmes des mots de la ligne, dans l'ordre : la ligne est découpée aux
+    /// frontières de mots Unicode, puis chaque mot Unicode aux changements de classe
+    /// (`foo.bar` donne trois mots) sauf aux apostrophes (`l'été` reste un mot) ;
+    /// la ponctuation et les caractères CJK d'une même écriture qui se suivent forment
+    /// un seul mot (`日本語のテキスト` donne `日本語`, `の` et `テキスト`)
+    fn words(&self) -> Vec<Range<usize>> {
+        let mut words: Vec<(Range<usize>, WordClass)> = Vec::new();
+        for (byte_idx, segment) in self.string.split_word_bound_indices() {
+            let start = self.grapheme_idx_at_byte(byte_idx);
+            let end = self.grapheme_idx_at_byte(byte_idx.saturating_add(segment.len()));
+            for idx in start..end {
+                let inside = idx > start && idx.saturating_add(1) < end;
+                let class = match self.word_class(idx) {
+                    WordClass::Punctuation if inside && matches!(self.grapheme(idx), Some("'" | "’")) => {
+                        WordClass::Word
+                    }
+                    class => class,
+                };
+                match words.last_mut() {
+                    Some((range, last_class))
+                        if *last_class == class && (idx > start || class != WordClass::Word) =>
+                    {
+                        range.end = idx.saturating_add(1);
+                    }
+                    _ => words.push((idx..idx.saturating_add(1), class)),
+                }
+            }
+        }
+        words
+            .into_iter()
+            .filter(|(_, class)| *class != WordClass::Blank)
+            .map(|(range, _)| range)
+            .collect()
+    }
+
+    /// début du mot suivant sur la ligne, `None` s'il n'y en a plus
+    pub fn next_word_start(&self, grapheme_idx: usize) -> Option<usize> {
+        self.words()
+            .into_iter()
+            .find(|word| word.start > grapheme_idx)
+            .map(|word| word.start)
+    }
+
+    /// début du mot qui précède le graphème donné, `None` s'il n'y en a pas sur la ligne
+    pub fn previous_word_start(&self, grapheme_idx: usize) -> Option<usize> {
+        self.words()
+            .into_iter()
+            .rev()
+            .find(|word| word.start < grapheme_idx)
+            .map(|word| word.start)
+    }
+
+    /// dernier graphème du premier mot qui finit au graphème donné ou après lui
+    pub fn word_end(&self, grapheme_idx: usize) -> Option<usize> {
+        self.next_word_boundary(grapheme_idx)
+            .map(|end| end.saturating_sub(1))
+    }
+
+    /// fin (exclue) du premier mot qui finit après le graphème donné, `None` s'il n'y en a plus
+    pub fn next_word_boundary(&self, grapheme_idx: usize) -> Option<usize> {
+        self.words()
+            .into_iter()
+            .find(|word| word.end > grapheme_idx)
+            .map(|word| word.end)
+    }
+
     /// compte les graphèmes
     pub fn grapheme_count(&self) -> usize {
-        self.fragments.len()
+        self.chunks.borrow_mut().totals(&self.string, self.tab_width).0
     }
 
     pub fn width(&self) -> usize {
@@ -149,55 +464,249 @@ impl Line {
         self.insert_char(character, self.grapheme_count());
     }
 
-    /// je suis pas sur mais il me semble que c'est pour split une ligne si jamais la taille dépasse ?
-    pub fn split(&mut self, at: usize) -> Self {
-        if at > self.fragments.len() {
-            return Self::default();
-        }
-        let remainder = self.fragments.split_off(at);
-        Self {
-            fragments: remainder,
-        }
-    }
-
     pub fn delete_last(&mut self) {
         self.delete(self.grapheme_count().saturating_sub(1));
     }
 
     /// calcule la largeur dispo
     pub fn width_until(&self, grapheme_index: usize) -> usize {
-        self.fragments
-            .iter()
-            .take(grapheme_index)
-            .map(|fragment| match fragment.rendered_width {
-                GraphemeWidth::Half => 1,
-                GraphemeWidth::Full => 2,
-            })
-            .sum()
+        let mut chunks = self.chunks.borrow_mut();
+        let Some(info) = chunks.find(&self.string, self.tab_width, |info| {
+            info.end_grapheme_idx() > grapheme_index
+        }) else {
+            return chunks.totals(&self.string, self.tab_width).1;
+        };
+        let local_count = grapheme_index.saturating_sub(info.first_grapheme_idx);
+        info.start_column
+            .saturating_add(chunks.width_until(&info, local_count))
     }
 
-    /// supprime une ligne j'imagine
+    /// supprime le graphème à l'index donné
     pub fn delete(&mut self, at: usize) {
-        let mut result = String::new();
+        if let Some(fragment) = self.fragment(at) {
+            self.replace_bytes(fragment.start_byte_idx..fragment.end_byte_idx(), "");
+        }
+    }
 
-        for (index, fragment) in self.fragments.iter().enumerate() {
-            if index != at {
-                result.push_str(&fragment.grapheme);
-            }
+    /// convertit un index de graphème en index d'octet dans la string
+    pub fn grapheme_idx_to_byte_idx(&self, grapheme_idx: usize) -> usize {
+        self.fragment(grapheme_idx)
+            .map_or(self.string.len(), |fragment| fragment.start_byte_idx)
+    }
+
+    /// convertit un index d'octet en index de graphème, seulement si l'octet commence un graphème
+    fn byte_idx_to_grapheme_idx(&self, byte_idx: usize) -> Option<usize> {
+        self.graphemes_before_byte(byte_idx, true)
+    }
+
+    /// index du premier graphème qui commence à l'octet donné ou après
+    pub fn grapheme_idx_at_byte(&self, byte_idx: usize) -> usize {
+        self.graphemes_before_byte(byte_idx, false)
+            .unwrap_or_default()
+    }
+
+    /// nombre de graphèmes qui commencent avant l'octet donné ; avec `exact`,
+    /// `None` si l'octet ne commence pas un graphème
+    fn graphemes_before_byte(&self, byte_idx: usize, exact: bool) -> Option<usize> {
+        let mut chunks = self.chunks.borrow_mut();
+        let Some(info) = chunks.find(&self.string, self.tab_width, |info| {
+            info.end_byte_idx > byte_idx
+        }) else {
+            let count = chunks.totals(&self.string, self.tab_width).0;
+            return (!exact || byte_idx == self.string.len()).then_some(count);
+        };
+        chunks
+            .graphemes_before(&info, byte_idx, exact)
+            .map(|local_count| info.first_grapheme_idx.saturating_add(local_count))
+    }
+
+    /// trouve toutes les correspondances de `pattern`, en ne gardant que celles
+    /// qui commencent et finissent sur une frontière de graphème
+    fn find_all(&self, pattern: &Pattern) -> Vec<LineMatch> {
+        pattern
+            .find_iter(&self.string)
+            .into_iter()
+            .filter_map(|byte_range| {
+                let grapheme_start = self.byte_idx_to_grapheme_idx(byte_range.start)?;
+                let grapheme_end = self.byte_idx_to_grapheme_idx(byte_range.end)?;
+                Some(LineMatch {
+                    byte_range,
+                    grapheme_range: grapheme_start..grapheme_end,
+                })
+            })
+            .collect()
+    }
+
+    /// cherche la première correspondance commençant au graphème donné ou après,
+    /// retourne la plage de graphèmes correspondante
+    pub fn find_match(&self, pattern: &Pattern, from_grapheme_idx: usize) -> Option<Range<usize>> {
+        if from_grapheme_idx > self.grapheme_count() {
+            return None;
         }
-        self.fragments = Self::str_to_fragments(&result);
+        self.find_all(pattern)
+            .into_iter()
+            .find(|line_match| line_match.grapheme_range.start >= from_grapheme_idx)
+            .map(|line_match| line_match.grapheme_range)
     }
-    
+
+    /// cherche `pattern` à partir du graphème donné (inclus), retourne l'index du graphème trouvé
+    pub fn search_forward(&self, pattern: &Pattern, from_grapheme_idx: usize) -> Option<usize> {
+        self.find_match(pattern, from_grapheme_idx)
+            .map(|grapheme_range| grapheme_range.start)
+    }
+
+    /// cherche `pattern` avant le graphème donné (exclu), retourne l'index du graphème trouvé
+    pub fn search_backward(&self, pattern: &Pattern, from_grapheme_idx: usize) -> Option<usize> {
+        self.find_all(pattern)
+            .into_iter()
+            .rev()
+            .find(|line_match| line_match.grapheme_range.start < from_grapheme_idx)
+            .map(|line_match| line_match.grapheme_range.start)
+    }
+
+    /// calcule le texte qui remplace la correspondance commençant au graphème donné
+    pub fn expand_match(&self, pattern: &Pattern, grapheme_idx: usize, replacement: &str) -> String {
+        pattern.expand(&self.string, self.grapheme_idx_to_byte_idx(grapheme_idx), replacement)
+    }
+}
+
+/// sorte de graphème : un mot est une suite de graphèmes de la même classe
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum WordClass {
+    Blank,
+    /// lettres, chiffres et `_`
+    Word,
+    /// idéogrammes et kanas, une suite de la même écriture forme un mot
+    Cjk(CjkScript),
+    Punctuation,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum CjkScript {
+    Han,
+    Hiragana,
+    Katakana,
+}
+
+/// écriture CJK d'un caractère : les mots Unicode n'y font qu'un caractère, faute d'espaces
+const fn cjk_script(character: char) -> Option<CjkScript> {
+    match character {
+        '\u{3040}'..='\u{309F}' => Some(CjkScript::Hiragana),
+        '\u{30A0}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}' | '\u{FF66}'..='\u{FF9F}' => {
+            Some(CjkScript::Katakana)
+        }
+        '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{3134F}' => Some(CjkScript::Han),
+        _ => None,
+    }
+}
+
+/// correspondance trouvée dans une ligne
+struct LineMatch {
+    byte_range: Range<usize>,
+    grapheme_range: Range<usize>,
 }
 
 // trait display pour une Line (permet de print une Line facilement)
 impl fmt::Display for Line {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        let result: String = self
-            .fragments
-            .iter()
-            .map(|fragment| fragment.grapheme.clone())
+        write!(formatter, "{}", self.string)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use unicode_segmentation::UnicodeSegmentation;
+    use super::{Line, TextFragment};
+
+    /// texte de plusieurs morceaux, avec des tabulations, des caractères larges et combinants
+    fn long_text() -> String {
+        "ab\tcé日本\u{301}x🇫🇷 ".repeat(1500)
+    }
+
+    fn fragments(line: &Line) -> Vec<(usize, usize, usize, Option<char>)> {
+        (0..line.grapheme_count())
+            .filter_map(|idx| line.fragment(idx))
+            .map(|fragment: TextFragment| {
+                (
+                    fragment.start_byte_idx,
+                    fragment.byte_len,
+                    fragment.rendered_width.to_usize(),
+                    fragment.replacement,
+                )
+            })
+            .collect()
+    }
+
+    /// la ligne modifiée se comporte comme une ligne découpée d'un coup
+    fn assert_segmented_like_fresh(line: &Line) {
+        let fresh = Line::with_tab_width(&line.string, line.tab_width);
+        let expected: Vec<(usize, usize)> = line
+            .string
+            .grapheme_indices(true)
+            .map(|(byte_idx, grapheme)| (byte_idx, grapheme.len()))
+            .collect();
+        let found: Vec<(usize, usize)> = fragments(line)
+            .into_iter()
+            .map(|(byte_idx, byte_len, _, _)| (byte_idx, byte_len))
             .collect();
-        write!(formatter, "{result}")
+        assert_eq!(found, expected);
+        assert_eq!(fragments(line), fragments(&fresh));
+        assert_eq!(line.width(), fresh.width());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn long_line_matches_full_segmentation() {
+        let text = long_text();
+        let line = Line::from(&text);
+        let expected: Vec<usize> = text.grapheme_indices(true).map(|(byte_idx, _)| byte_idx).collect();
+        assert_eq!(line.grapheme_count(), expected.len());
+        for (idx, byte_idx) in expected.iter().enumerate() {
+            assert_eq!(line.grapheme_idx_to_byte_idx(idx), *byte_idx);
+            assert_eq!(line.grapheme_idx_at_byte(*byte_idx), idx);
+        }
+    }
+
+    #[test]
+    fn visible_part_is_found_without_segmenting_everything() {
+        let line = Line::from(&long_text());
+        let (graphemes, first_column) = line.graphemes_in_columns(20_000..20_010);
+        assert!(first_column <= 20_000);
+        assert_eq!(line.width_until(graphemes.start), first_column);
+        assert!(line.width_until(graphemes.end) >= 20_010);
+        // seuls les morceaux jusqu'à la partie visible ont été découpés
+        assert!(line.chunks.borrow().placed_count() < line.chunks.borrow().len());
+    }
+
+    #[test]
+    fn edits_resegment_only_what_changed() {
+        let mut line = Line::from(&long_text());
+        line.width();
+        for at in [0, 1, 1000, 2047, 2048, 4095, 4096, 9000] {
+            line.insert_char('\t', at);
+            assert_segmented_like_fresh(&line);
+            line.insert_char('\u{301}', at);
+            assert_segmented_like_fresh(&line);
+            line.delete(at.saturating_add(1));
+            assert_segmented_like_fresh(&line);
+        }
+        line.replace_bytes(100..9000, "");
+        assert_segmented_like_fresh(&line);
+        line.delete_last();
+        line.append_char('日');
+        line.append(&Line::from("\t🇫🇷"));
+        assert_segmented_like_fresh(&line);
+    }
+
+    #[test]
+    fn grapheme_split_across_chunks_is_merged() {
+        // un caractère combinant inséré juste après une frontière de morceau rejoint le graphème qui précède
+        let mut line = Line::from(&"a".repeat(10_000));
+        line.width();
+        line.replace_bytes(4096..4096, "\u{301}");
+        assert_eq!(line.grapheme_count(), 10_000);
+        assert_segmented_like_fresh(&line);
+    }
+}
@@ -0,0 +1,360 @@
+use std::ops::Range;
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
+use unicode_width::UnicodeWidthStr;
+use super::{tab_stop_distance, GraphemeWidth, Line, TextFragment};
+
+/// taille visée d'un morceau de ligne, en octets
+const CHUNK_LEN: usize = 4096;
+
+/// Une ligne découpée en morceaux d'environ `CHUNK_LEN` octets, qui commencent sur une
+/// frontière de graphème. Un morceau n'est découpé en graphèmes que lorsqu'on y accède, et
+/// une modification ne redécoupe que les morceaux qu'elle touche : sur une très longue ligne,
+/// seuls la partie affichée et l'endroit modifié sont parcourus.
+#[derive(Default)]
+pub struct Chunks {
+    list: Vec<Chunk>,
+    /// nombre de morceaux, depuis le début, dont le premier graphème et la colonne sont connus
+    placed: usize,
+}
+
+struct Chunk {
+    byte_range: Range<usize>,
+    /// index du premier graphème et colonne du début, valables pour les morceaux placés
+    first_grapheme_idx: usize,
+    start_column: usize,
+    segments: Option<Segments>,
+}
+
+/// graphèmes d'un morceau
+struct Segments {
+    /// index d'octets relatifs au début du morceau ; `None` pour de l'ASCII imprimable,
+    /// où chaque octet est un graphème d'une colonne
+    fragments: Option<Vec<TextFragment>>,
+    grapheme_count: usize,
+    width: usize,
+    /// colonne de début, modulo la largeur des tabulations, pour laquelle elles ont été
+    /// mesurées ; `None` sans tabulation, la colonne de début est alors sans effet
+    tab_phase: Option<usize>,
+}
+
+/// position d'un morceau placé
+#[derive(Clone, Copy)]
+pub struct ChunkInfo {
+    index: usize,
+    pub start_byte_idx: usize,
+    pub end_byte_idx: usize,
+    pub first_grapheme_idx: usize,
+    pub start_column: usize,
+    grapheme_count: usize,
+    width: usize,
+}
+
+impl ChunkInfo {
+    pub const fn end_grapheme_idx(&self) -> usize {
+        self.first_grapheme_idx.saturating_add(self.grapheme_count)
+    }
+
+    pub const fn end_column(&self) -> usize {
+        self.start_column.saturating_add(self.width)
+    }
+}
+
+impl Chunk {
+    const fn new(byte_range: Range<usize>) -> Self {
+        Self {
+            byte_range,
+            first_grapheme_idx: 0,
+            start_column: 0,
+            segments: None,
+        }
+    }
+
+    fn info(&self, index: usize) -> ChunkInfo {
+        let (grapheme_count, width) = self
+            .segments
+            .as_ref()
+            .map_or((0, 0), |segments| (segments.grapheme_count, segments.width));
+        ChunkInfo {
+            index,
+            start_byte_idx: self.byte_range.start,
+            end_byte_idx: self.byte_range.end,
+            first_grapheme_idx: self.first_grapheme_idx,
+            start_column: self.start_column,
+            grapheme_count,
+            width,
+        }
+    }
+}
+
+impl Chunks {
+    pub fn new(string: &str) -> Self {
+        Self {
+            list: split(string, 0..string.len()),
+            placed: 0,
+        }
+    }
+
+    /// premier morceau pour lequel `reached` est vrai, en plaçant les morceaux au fur et à
+    /// mesure ; `reached` doit rester vrai pour tous les morceaux qui suivent
+    pub fn find(
+        &mut self,
+        string: &str,
+        tab_width: usize,
+        reached: impl Fn(&ChunkInfo) -> bool,
+    ) -> Option<ChunkInfo> {
+        let index = self
+            .list
+            .get(..self.placed)
+            .unwrap_or_default()
+            .partition_point(|chunk| !reached(&chunk.info(0)));
+        if index < self.placed {
+            return self.list.get(index).map(|chunk| chunk.info(index));
+        }
+        while self.placed < self.list.len() {
+            let info = self.place(string, tab_width);
+            if reached(&info) {
+                return Some(info);
+            }
+        }
+        None
+    }
+
+    #[cfg(test)]
+    pub const fn placed_count(&self) -> usize {
+        self.placed
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    /// nombre de graphèmes et largeur de toute la ligne
+    pub fn totals(&mut self, string: &str, tab_width: usize) -> (usize, usize) {
+        self.find(string, tab_width, |_| false);
+        self.placed
+            .checked_sub(1)
+            .and_then(|last| self.list.get(last))
+            .map_or((0, 0), |chunk| {
+                let info = chunk.info(0);
+                (info.end_grapheme_idx(), info.end_column())
+            })
+    }
+
+    /// place le morceau qui suit le dernier placé, en le découpant en graphèmes si besoin
+    fn place(&mut self, string: &str, tab_width: usize) -> ChunkInfo {
+        let index = self.placed;
+        let (first_grapheme_idx, start_column) = index
+            .checked_sub(1)
+            .and_then(|previous| self.list.get(previous))
+            .map_or((0, 0), |previous| {
+                let info = previous.info(0);
+                (info.end_grapheme_idx(), info.end_column())
+            });
+        let phase = start_column.checked_rem(tab_width).unwrap_or_default();
+        let Some(chunk) = self.list.get_mut(index) else {
+            return ChunkInfo {
+                index,
+                start_byte_idx: string.len(),
+                end_byte_idx: string.len(),
+                first_grapheme_idx,
+                start_column,
+                grapheme_count: 0,
+                width: 0,
+            };
+        };
+        chunk.first_grapheme_idx = first_grapheme_idx;
+        chunk.start_column = start_column;
+        let stale = chunk.segments.as_ref().is_none_or(|segments| {
+            segments.tab_phase.is_some_and(|tab_phase| tab_phase != phase)
+        });
+        if stale {
+            let text = string.get(chunk.byte_range.clone()).unwrap_or_default();
+            chunk.segments = Some(segment(text, tab_width, start_column));
+        }
+        self.placed = index.saturating_add(1);
+        chunk.info(index)
+    }
+
+    /// graphème d'un morceau placé, avec des index d'octets relatifs à la ligne
+    pub fn fragment(&self, info: &ChunkInfo, local_idx: usize) -> Option<TextFragment> {
+        let segments = self.list.get(info.index)?.segments.as_ref()?;
+        match &segments.fragments {
+            Some(fragments) => fragments.get(local_idx).map(|fragment| TextFragment {
+                start_byte_idx: fragment.start_byte_idx.saturating_add(info.start_byte_idx),
+                ..*fragment
+            }),
+            None => (local_idx < segments.grapheme_count).then_some(TextFragment {
+                start_byte_idx: info.start_byte_idx.saturating_add(local_idx),
+                byte_len: 1,
+                rendered_width: GraphemeWidth::Half,
+                replacement: None,
+            }),
+        }
+    }
+
+    /// largeur des `local_count` premiers graphèmes d'un morceau placé
+    pub fn width_until(&self, info: &ChunkInfo, local_count: usize) -> usize {
+        (0..local_count)
+            .filter_map(|local_idx| self.fragment(info, local_idx))
+            .map(|fragment| fragment.rendered_width.to_usize())
+            .sum()
+    }
+
+    /// nombre de graphèmes d'un morceau placé qui commencent avant l'octet donné
+    /// (relatif à la ligne) ; avec `exact`, `None` si l'octet ne commence pas un graphème
+    pub fn graphemes_before(&self, info: &ChunkInfo, byte_idx: usize, exact: bool) -> Option<usize> {
+        let local_byte_idx = byte_idx.saturating_sub(info.start_byte_idx);
+        let segments = self.list.get(info.index)?.segments.as_ref()?;
+        match &segments.fragments {
+            Some(fragments) => {
+                let count =
+                    fragments.partition_point(|fragment| fragment.start_byte_idx < local_byte_idx);
+                let starts_grapheme = fragments
+                    .get(count)
+                    .is_some_and(|fragment| fragment.start_byte_idx == local_byte_idx);
+                (!exact || starts_grapheme).then_some(count)
+            }
+            None => Some(local_byte_idx.min(segments.grapheme_count)),
+        }
+    }
+
+    /// prend en compte le remplacement des octets `removed` (index de l'ancienne ligne)
+    /// par `inserted_len` octets : seuls les morceaux qui touchent la modification sont
+    /// redécoupés, ceux qui suivent sont seulement décalés
+    pub fn edited(&mut self, string: &str, removed: &Range<usize>, inserted_len: usize) {
+        if self.list.is_empty() {
+            *self = Self::new(string);
+            return;
+        }
+        let shift = |byte_idx: usize| {
+            byte_idx
+                .saturating_sub(removed.len())
+                .saturating_add(inserted_len)
+        };
+        // les morceaux qui bordent la modification sont repris : un graphème peut s'y étendre
+        let mut first = self
+            .list
+            .partition_point(|chunk| chunk.byte_range.end < removed.start);
+        let mut end = self
+            .list
+            .partition_point(|chunk| chunk.byte_range.start <= removed.end);
+        while first > 0
+            && self
+                .list
+                .get(first)
+                .is_some_and(|chunk| !is_boundary(string, chunk.byte_range.start))
+        {
+            first = first.saturating_sub(1);
+        }
+        while self
+            .list
+            .get(end)
+            .is_some_and(|chunk| !is_boundary(string, shift(chunk.byte_range.start)))
+        {
+            end = end.saturating_add(1);
+        }
+        let start_byte_idx = self
+            .list
+            .get(first)
+            .map_or(0, |chunk| chunk.byte_range.start.min(removed.start));
+        let end_byte_idx = end
+            .checked_sub(1)
+            .and_then(|last| self.list.get(last))
+            .map_or(string.len(), |chunk| shift(chunk.byte_range.end));
+        for chunk in self.list.iter_mut().skip(end) {
+            chunk.byte_range = shift(chunk.byte_range.start)..shift(chunk.byte_range.end);
+        }
+        self.list
+            .splice(first..end, split(string, start_byte_idx..end_byte_idx));
+        self.placed = self.placed.min(first);
+    }
+}
+
+/// découpe une partie de la ligne en morceaux d'environ `CHUNK_LEN` octets
+fn split(string: &str, byte_range: Range<usize>) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut start = byte_range.start;
+    while start < byte_range.end {
+        let end = if byte_range.end.saturating_sub(start) <= CHUNK_LEN {
+            byte_range.end
+        } else {
+            boundary_at_or_after(string, start.saturating_add(CHUNK_LEN)).min(byte_range.end)
+        };
+        chunks.push(Chunk::new(start..end));
+        start = end;
+    }
+    chunks
+}
+
+fn is_boundary(string: &str, byte_idx: usize) -> bool {
+    string.is_char_boundary(byte_idx)
+        && GraphemeCursor::new(byte_idx, string.len(), true)
+            .is_boundary(string, 0)
+            .unwrap_or(true)
+}
+
+/// première frontière de graphème à l'octet donné ou après lui
+fn boundary_at_or_after(string: &str, byte_idx: usize) -> usize {
+    let mut byte_idx = byte_idx.min(string.len());
+    while !string.is_char_boundary(byte_idx) {
+        byte_idx = byte_idx.saturating_add(1);
+    }
+    if is_boundary(string, byte_idx) {
+        return byte_idx;
+    }
+    GraphemeCursor::new(byte_idx, string.len(), true)
+        .next_boundary(string, 0)
+        .ok()
+        .flatten()
+        .unwrap_or(string.len())
+}
+
+/// découpe un morceau en graphèmes ; les tabulations s'étendent jusqu'au taquet suivant,
+/// compté depuis `start_column`
+fn segment(text: &str, tab_width: usize, start_column: usize) -> Segments {
+    if text.bytes().all(|byte| byte.is_ascii_graphic() || byte == b' ') {
+        return Segments {
+            fragments: None,
+            grapheme_count: text.len(),
+            width: text.len(),
+            tab_phase: None,
+        };
+    }
+    let mut column = start_column;
+    let mut has_tab = false;
+    let fragments: Vec<TextFragment> = text
+        .grapheme_indices(true)
+        .map(|(byte_idx, grapheme)| {
+            let (replacement, rendered_width) = if grapheme == "\t" {
+                // la largeur d'une tabulation dépend de la colonne où elle commence
+                has_tab = true;
+                (Some(' '), GraphemeWidth::Tab(tab_stop_distance(column, tab_width)))
+            } else {
+                Line::replacement_character(grapheme).map_or_else(
+                    || {
+                        let rendered_width = match grapheme.width() {
+                            0 | 1 => GraphemeWidth::Half,
+                            _ => GraphemeWidth::Full,
+                        };
+                        (None, rendered_width)
+                    },
+                    |replacement| (Some(replacement), GraphemeWidth::Half),
+                )
+            };
+            column = column.saturating_add(rendered_width.to_usize());
+            TextFragment {
+                start_byte_idx: byte_idx,
+                byte_len: grapheme.len(),
+                rendered_width,
+                replacement,
+            }
+        })
+        .collect();
+    Segments {
+        grapheme_count: fragments.len(),
+        width: column.saturating_sub(start_column),
+        tab_phase: has_tab.then(|| start_column.checked_rem(tab_width).unwrap_or_default()),
+        fragments: Some(fragments),
+    }
+}
@@ -4,9 +4,9 @@ use std::{
     time::{Duration, Instant},
 };
 
-const DEFAULT_DURATION: Duration = Duration::new(5, 0);
+pub const DEFAULT_DURATION: Duration = Duration::new(5, 0);
 
-use super::{Size, Terminal, UIComponent};
+use super::{Position, Size, Terminal, UIComponent};
 
 /// message affichée en bas de l'écran
 struct Message {
@@ -25,20 +25,36 @@ impl Default for Message {
 
 impl Message {
     /// Vérifie si le message est expiré
-    fn is_expired(&self) -> bool {
-        Instant::now().duration_since(self.time) > DEFAULT_DURATION
+    fn is_expired(&self, timeout: Duration) -> bool {
+        Instant::now().duration_since(self.time) > timeout
     }
 }
 
 /// Barre de message
-#[derive(Default)]
 pub struct MessageBar {
     current_message: Message,
     needs_redraw: bool,
     cleared_after_expiry: bool, //ensures we can properly hide expired messages
+    /// durée d'affichage d'un message
+    timeout: Duration,
+}
+
+impl Default for MessageBar {
+    fn default() -> Self {
+        Self {
+            current_message: Message::default(),
+            needs_redraw: false,
+            cleared_after_expiry: false,
+            timeout: DEFAULT_DURATION,
+        }
+    }
 }
 
 impl MessageBar {
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
     /// Met à jour le message
     pub fn update_message(&mut self, new_message: &str) {
         self.current_message = Message {
@@ -49,7 +65,7 @@ impl MessageBar {
         self.set_needs_redraw(true);
     }
 }
-/// implémentation de UIComponent pour MessageBar
+/// implémentation de `UIComponent` pour `MessageBar`
 impl UIComponent for MessageBar {
     /// Met à jour le besoin de redessiner
     fn set_needs_redraw(&mut self, value: bool) {
@@ -58,23 +74,23 @@ impl UIComponent for MessageBar {
 
     /// Vérifie si le composant a besoin d'être redessiné
     fn needs_redraw(&self) -> bool {
-        (!self.cleared_after_expiry && self.current_message.is_expired()) || self.needs_redraw
+        (!self.cleared_after_expiry && self.current_message.is_expired(self.timeout)) || self.needs_redraw
     }
 
     /// Met à jour la taille
     fn set_size(&mut self, _: Size) {}
 
     /// Dessine le composant
-    fn draw(&mut self, origin: usize) -> Result<(), Error> {
-        if self.current_message.is_expired() {
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
+        if self.current_message.is_expired(self.timeout) {
             self.cleared_after_expiry = true; // Upon expiration, we need to write out "" once to clear the message. To avoid clearing more than necessary, we  keep track of the fact that we've already cleared the expired message once..
         }
-        let message = if self.current_message.is_expired() {
+        let message = if self.current_message.is_expired(self.timeout) {
             ""
         } else {
             &self.current_message.text
         };
 
-        Terminal::print_row(origin, message)
+        Terminal::print_row(origin.y, message)
     }
 }
\ No newline at end of file
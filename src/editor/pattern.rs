@@ -0,0 +1,67 @@
+use regex::Regex;
+use std::ops::Range;
+
+/// motif recherché : texte littéral, ou expression régulière quand il est écrit entre `/`
+pub enum Pattern {
+    Literal(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// interprète la saisie de l'utilisateur : `/expr/` donne une regex, le reste est littéral
+    pub fn parse(query: &str) -> Result<Self, regex::Error> {
+        match query
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(expression) if !expression.is_empty() => Ok(Self::Regex(Regex::new(expression)?)),
+            _ => Ok(Self::literal(query)),
+        }
+    }
+
+    pub fn literal(query: &str) -> Self {
+        Self::Literal(query.to_string())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Literal(query) => query.is_empty(),
+            Self::Regex(regex) => regex.as_str().is_empty(),
+        }
+    }
+
+    /// vrai si `haystack` contient au moins une correspondance
+    pub fn is_match(&self, haystack: &str) -> bool {
+        match self {
+            Self::Literal(query) => !query.is_empty() && haystack.contains(query.as_str()),
+            Self::Regex(regex) => regex.is_match(haystack),
+        }
+    }
+
+    /// retourne les plages d'octets de toutes les correspondances dans `haystack`
+    pub fn find_iter(&self, haystack: &str) -> Vec<Range<usize>> {
+        match self {
+            Self::Literal(query) if query.is_empty() => Vec::new(),
+            Self::Literal(query) => haystack
+                .match_indices(query.as_str())
+                .map(|(start, matched)| start..start.saturating_add(matched.len()))
+                .collect(),
+            Self::Regex(regex) => regex.find_iter(haystack).map(|found| found.range()).collect(),
+        }
+    }
+
+    /// calcule le texte de remplacement pour la correspondance qui commence à `start_byte_idx`.
+    /// Pour une regex, `$1`, `${nom}`... sont remplacés par les groupes capturés.
+    pub fn expand(&self, haystack: &str, start_byte_idx: usize, replacement: &str) -> String {
+        match self {
+            Self::Literal(_) => replacement.to_string(),
+            Self::Regex(regex) => {
+                let mut result = String::new();
+                if let Some(captures) = regex.captures_at(haystack, start_byte_idx) {
+                    captures.expand(replacement, &mut result);
+                }
+                result
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use super::{DocumentStatus, Size, Terminal, UIComponent};
+use super::{DocumentStatus, Position, Size, Terminal, UIComponent};
 use std::io::Error;
 
 /// Status bar component
@@ -36,14 +36,16 @@ impl UIComponent for StatusBar {
     fn set_size(&mut self, size: Size) {
         self.size = size;
     }
-    fn draw(&mut self, origin_y: usize) -> Result<(), Error> {
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
         //Assemble the first part of the status bar
         let line_count = self.current_status.line_count_to_string();
         let modified_indicator = self.current_status.modified_indicator_to_string();
 
         let beginning = format!(
-            "{} - {line_count} {modified_indicator}",
-            self.current_status.file_name
+            "{}[{}] {} - {line_count} {modified_indicator}",
+            self.current_status.mode_to_string(),
+            self.current_status.buffer_indicator_to_string(),
+            self.current_status.file_name_to_string()
         );
 
         // Assemble the whole status bar, with the position indicator at the back
@@ -51,13 +53,13 @@ impl UIComponent for StatusBar {
         let remainder_len = self.size.width.saturating_sub(beginning.len());
         let status = format!("{beginning}{position_indicator:>remainder_len$}");
 
-        //Only print out the status if it fits. Otherwise write out an empty string to ensure the row is cleared.
+        //Only print out the whole status if it fits. Otherwise print the beginning, cut to the width of the bar (narrow windows).
         let to_print = if status.len() <= self.size.width {
             status
         } else {
-            String::new()
+            beginning
         };
-        Terminal::print_inverted_row(origin_y, &to_print)?;
+        Terminal::print_inverted_row(origin, self.size.width, &to_print)?;
 
         Ok(())
     }
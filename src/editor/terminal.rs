@@ -1,15 +1,27 @@
-use crossterm::style::{Attribute,Print};
+use crossterm::style::{
+    Attribute, Print, ResetColor, SetAttribute, SetBackgroundColor, SetForegroundColor,
+};
 use crossterm::{ queue,Command};
 use std::io::stdout;
-use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::cursor::{Hide, MoveTo, SetCursorStyle, Show};
+use crossterm::event::{
+    DisableBracketedPaste, DisableFocusChange, EnableBracketedPaste, EnableFocusChange,
+    KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
 use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, size, Clear, ClearType, EnterAlternateScreen,EnableLineWrap,DisableLineWrap,SetTitle,
+    disable_raw_mode, enable_raw_mode, size, supports_keyboard_enhancement, Clear, ClearType, EnterAlternateScreen,EnableLineWrap,DisableLineWrap,SetTitle,
     LeaveAlternateScreen,
 };
-use super::{Position, Size};
+use super::{AnnotatedString, Position, Size};
+mod attribute;
+pub use attribute::{set_theme, Attribute as TextAttribute};
 use std::io::Write;
 use std::io::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use base64::{engine::general_purpose::STANDARD, Engine};
 
+/// le terminal distingue les touches que l'encodage classique confond (Ctrl-Backspace et Ctrl-H)
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
 
 pub struct Terminal {
 
@@ -19,8 +31,14 @@ impl Terminal {
 
     /// Termine l'application
     pub fn terminate() -> Result<(), Error> {
+        if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+            Self::queue_command(PopKeyboardEnhancementFlags)?;
+        }
         Self::leave_alternate_screen()?;
+        Self::disable_bracketed_paste()?;
+        Self::queue_command(DisableFocusChange)?;
         Self::enable_line_wrap()?;
+        Self::set_caret_style(SetCursorStyle::DefaultUserShape)?;
         Self::show_cursor()?;
         Self::execute()?;
         disable_raw_mode()?;
@@ -31,6 +49,16 @@ impl Terminal {
     pub fn initialize() -> Result<(), Error> {
         enable_raw_mode()?;
         Self::enter_alternate_screen()?;
+        Self::enable_bracketed_paste()?;
+        // le terminal signale quand il reprend le focus, pour vérifier le fichier sur le disque
+        Self::queue_command(EnableFocusChange)?;
+        // sans cela, Ctrl-Backspace arrive comme Ctrl-H et son raccourci ne fonctionne pas
+        if supports_keyboard_enhancement().unwrap_or(false) {
+            Self::queue_command(PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES,
+            ))?;
+            KEYBOARD_ENHANCED.store(true, Ordering::Relaxed);
+        }
         Self::disable_line_wrap()?;
         Self::clear_screen()?;
         Self::move_cursor_to(Position{x:0, y:0})?;
@@ -44,6 +72,18 @@ impl Terminal {
         Ok(())
     }
 
+    /// Demande au terminal d'envoyer les collages d'un seul bloc plutôt que touche par touche
+    pub fn enable_bracketed_paste() -> Result<(), Error> {
+        Self::queue_command(EnableBracketedPaste)?;
+        Ok(())
+    }
+
+    /// Rétablit l'envoi des collages touche par touche
+    pub fn disable_bracketed_paste() -> Result<(), Error> {
+        Self::queue_command(DisableBracketedPaste)?;
+        Ok(())
+    }
+
     /// Active le retour à la ligne
     pub fn enable_line_wrap() -> Result<(), Error> {
         Self::queue_command(EnableLineWrap)?;
@@ -56,18 +96,23 @@ impl Terminal {
         Ok(())
     }
 
-    /// Imprime une ligne de texte inversée
-    pub fn print_inverted_row(row: usize, line_text: &str) -> Result<(), Error> {
-        let width = Self::size()?.width;
-        Self::print_row(
-            row,
-            &format!(
-                "{}{:width$.width$}{}",
-                Attribute::Reverse,
-                line_text,
-                Attribute::Reset
-            ),
-        )
+    /// Copie du texte dans le presse-papier système avec une séquence OSC 52,
+    /// que le terminal transmet à l'hôte même à travers SSH
+    pub fn copy_to_clipboard(text: &str) -> Result<(), Error> {
+        Self::print(&format!("\x1b]52;c;{}\x07", STANDARD.encode(text)))?;
+        Self::execute()?;
+        Ok(())
+    }
+
+    /// Imprime une ligne de texte inversée sur une largeur donnée, à partir d'une position
+    pub fn print_inverted_row(at: Position, width: usize, line_text: &str) -> Result<(), Error> {
+        Self::move_cursor_to(at)?;
+        Self::print(&format!(
+            "{}{:width$.width$}{}",
+            Attribute::Reverse,
+            line_text,
+            Attribute::Reset
+        ))
     }
 
     /// rentre dans le mode d'écran alternatif
@@ -90,6 +135,61 @@ impl Terminal {
         Ok(())
     }
 
+    /// Imprime une ligne de texte annotée dans une zone de l'écran,
+    /// avec les couleurs correspondant aux annotations
+    pub fn print_annotated_row_at(
+        at: Position,
+        width: usize,
+        annotated_string: &AnnotatedString,
+    ) -> Result<(), Error> {
+        Self::clear_region(at, width)?;
+        Self::print_annotated(annotated_string)
+    }
+
+    /// Imprime du texte annoté à la position du curseur
+    pub fn print_annotated(annotated_string: &AnnotatedString) -> Result<(), Error> {
+        for part in annotated_string {
+            if let Some(annotation_type) = part.annotation_type {
+                let attribute = TextAttribute::from(annotation_type);
+                Self::set_attribute(&attribute)?;
+                Self::print(part.string)?;
+                Self::reset_color()?;
+            } else {
+                Self::print(part.string)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Efface `width` colonnes à partir d'une position et y replace le curseur
+    fn clear_region(at: Position, width: usize) -> Result<(), Error> {
+        Self::move_cursor_to(at)?;
+        Self::print(&" ".repeat(width))?;
+        Self::move_cursor_to(at)?;
+        Ok(())
+    }
+
+    /// Applique les couleurs d'un attribut
+    fn set_attribute(attribute: &TextAttribute) -> Result<(), Error> {
+        if attribute.reverse {
+            Self::queue_command(SetAttribute(Attribute::Reverse))?;
+        }
+        if let Some(foreground) = attribute.foreground {
+            Self::queue_command(SetForegroundColor(foreground))?;
+        }
+        if let Some(background) = attribute.background {
+            Self::queue_command(SetBackgroundColor(background))?;
+        }
+        Ok(())
+    }
+
+    /// Remet les couleurs par défaut
+    fn reset_color() -> Result<(), Error> {
+        Self::queue_command(SetAttribute(Attribute::Reset))?;
+        Self::queue_command(ResetColor)?;
+        Ok(())
+    }
+
     /// Efface l'écran
     pub fn clear_screen() -> Result<(), Error> {
         Self::queue_command(Clear(ClearType::All))?;
@@ -103,9 +203,11 @@ impl Terminal {
     }
 
 
-    /// Déplace le curseur à une position donnée
+    /// Déplace le curseur à une position donnée, ramenée dans les limites du terminal
     pub fn move_cursor_to(position:Position) -> Result<(), Error> {
-        Self::queue_command(MoveTo(position.x as u16, position.y as u16))?;
+        let x = u16::try_from(position.x).unwrap_or(u16::MAX);
+        let y = u16::try_from(position.y).unwrap_or(u16::MAX);
+        Self::queue_command(MoveTo(x, y))?;
         
         Ok(())
     }
@@ -134,6 +236,12 @@ impl Terminal {
         Ok(())
     }
 
+    /// Change la forme du curseur (bloc, barre...)
+    pub fn set_caret_style(style: SetCursorStyle) -> Result<(),Error> {
+        Self::queue_command(style)?;
+        Ok(())
+    }
+
     /// Imprime un message dans le terminal
     pub fn print(message: &str) -> Result<(), Error> {
         Self::queue_command(Print(message))?;
@@ -0,0 +1,95 @@
+use std::sync::Mutex;
+
+use crossterm::style::Color;
+
+use super::super::AnnotationType;
+
+/// couleurs choisies dans la configuration, à la place de celles par défaut
+static THEME: Mutex<Vec<(AnnotationType, Attribute)>> = Mutex::new(Vec::new());
+
+/// remplace les couleurs par défaut des types d'annotation donnés
+pub fn set_theme(theme: Vec<(AnnotationType, Attribute)>) {
+    if let Ok(mut current) = THEME.lock() {
+        *current = theme;
+    }
+}
+
+/// couleurs (et vidéo inverse) utilisées pour afficher un morceau de texte annoté
+#[derive(Copy, Clone, Debug)]
+pub struct Attribute {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub reverse: bool,
+}
+
+impl From<AnnotationType> for Attribute {
+    fn from(annotation_type: AnnotationType) -> Self {
+        THEME
+            .lock()
+            .ok()
+            .and_then(|theme| {
+                theme
+                    .iter()
+                    .find(|(themed_type, _)| *themed_type == annotation_type)
+                    .map(|(_, attribute)| *attribute)
+            })
+            .unwrap_or_else(|| Self::default_for(annotation_type))
+    }
+}
+
+impl Attribute {
+    pub const fn colors(foreground: Option<Color>, background: Option<Color>) -> Self {
+        Self {
+            foreground,
+            background,
+            reverse: false,
+        }
+    }
+
+    /// couleurs du thème par défaut
+    const fn default_for(annotation_type: AnnotationType) -> Self {
+        match annotation_type {
+            AnnotationType::Match => Self::colors(
+                Some(Color::Rgb { r: 255, g: 255, b: 255 }),
+                Some(Color::Rgb { r: 100, g: 100, b: 100 }),
+            ),
+            AnnotationType::SelectedMatch => Self::colors(
+                Some(Color::Rgb { r: 255, g: 255, b: 255 }),
+                Some(Color::Rgb { r: 255, g: 150, b: 0 }),
+            ),
+            AnnotationType::Keyword => Self::colors(
+                Some(Color::Rgb { r: 198, g: 120, b: 221 }),
+                None,
+            ),
+            AnnotationType::Type => Self::colors(
+                Some(Color::Rgb { r: 86, g: 182, b: 194 }),
+                None,
+            ),
+            AnnotationType::String => Self::colors(
+                Some(Color::Rgb { r: 152, g: 195, b: 121 }),
+                None,
+            ),
+            AnnotationType::Comment => Self::colors(
+                Some(Color::Rgb { r: 120, g: 120, b: 120 }),
+                None,
+            ),
+            AnnotationType::Selection => Self {
+                foreground: None,
+                background: None,
+                reverse: true,
+            },
+            AnnotationType::Number => Self::colors(
+                Some(Color::Rgb { r: 209, g: 154, b: 102 }),
+                None,
+            ),
+            AnnotationType::LineNumber => Self::colors(
+                Some(Color::Rgb { r: 100, g: 100, b: 100 }),
+                None,
+            ),
+            AnnotationType::CurrentLineNumber => Self::colors(
+                Some(Color::Rgb { r: 220, g: 220, b: 220 }),
+                None,
+            ),
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use std::io::Error;
-use super::Size;
+use super::{Position, Size};
 
 pub trait UIComponent {
     // Marks this UI component as in need of redrawing (or not)
@@ -16,9 +16,9 @@ pub trait UIComponent {
     fn set_size(&mut self, size: Size);
 
     /// Draw this component if it's visible and in need of redrawing
-    fn render(&mut self, origin_y: usize) {
+    fn render(&mut self, origin: Position) {
         if self.needs_redraw() {
-            match self.draw(origin_y) {
+            match self.draw(origin) {
                 Ok(()) => self.set_needs_redraw(false),
                 Err(err) => {
                     #[cfg(debug_assertions)]
@@ -29,7 +29,7 @@ pub trait UIComponent {
             }
         }
     }
-    /// Method to actually draw the component, must be implemented by each component
-    fn draw(&mut self, origin_y: usize) -> Result<(), Error>;
+    /// Method to actually draw the component at the given top-left corner, must be implemented by each component
+    fn draw(&mut self, origin: Position) -> Result<(), Error>;
 
 }
\ No newline at end of file
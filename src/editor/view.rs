@@ -1,30 +1,126 @@
 
 mod buffer;
-use buffer::Buffer;
+pub use buffer::{flush_swap_files, Buffer};
 use super::{
     command::{Edit, Move},
-    DocumentStatus, Line, Position, Size, Terminal, UIComponent, NAME, VERSION,
+    goto::{GotoTarget, LineTarget},
+    vim::{InsertPosition, Motion, Operator, Register},
+    AnnotatedString, Annotation, AnnotationType, DocumentStatus, Indentation, Line, Pattern,
+    Position, Size, Terminal, UIComponent, NAME, VERSION,
+};
+use std::{
+    cell::RefCell,
+    cmp::min,
+    collections::HashMap,
+    io::Error,
+    mem,
+    ops::Range,
+    path::{Path, PathBuf},
+    rc::Rc,
 };
-use std::{cmp::min, io::Error};
 mod fileinfo;
-use fileinfo::FileInfo;
+use fileinfo::{DiskState, FileInfo, LineEnding};
+pub use fileinfo::ExternalChange;
+mod history;
+use history::EditKind;
+mod highlighter;
+use highlighter::{Highlighter, Syntax};
+mod gutter;
+use gutter::{Gutter, GutterContext};
+pub use gutter::LineNumbers;
+mod searchinfo;
+use searchinfo::{ReplaceInfo, SearchDirection, SearchInfo};
+
 
 
+/// taille maximale du texte collé par un `p` répété, en octets
+const MAX_PUT_LEN: usize = 16 * 1024 * 1024;
+/// nombre de lignes dont les rangées sont gardées en mémoire
+const WRAP_CACHE_CAPACITY: usize = 4096;
+
+/// buffer partagé par toutes les vues qui l'affichent
+pub type SharedBuffer = Rc<RefCell<Buffer>>;
 
 #[derive(Default)]
 pub struct View {
-    buffer:Buffer,
+    buffer: SharedBuffer,
+    /// révision du buffer lors du dernier affichage, pour voir les modifications faites ailleurs
+    seen_revision: u64,
     needs_redraw: bool,
     size: Size,
     text_location:Location,
     scroll_offset:Position,
+    search_info: Option<SearchInfo>,
+    replace_info: Option<ReplaceInfo>,
+    selection_anchor: Option<Location>,
+    selection_kind: SelectionKind,
+    gutter: Gutter,
+    /// retour à la ligne automatique : les lignes trop longues continuent sur les rangées suivantes
+    soft_wrap: bool,
+    /// avec le retour à la ligne automatique, première rangée affichée de la ligne `scroll_offset.y`
+    scroll_row: usize,
+    wrap_cache: RefCell<WrapCache>,
+}
+
+/// rangées des lignes déjà découpées pour le retour à la ligne automatique,
+/// valables tant que la largeur du texte ne change pas. Les modifications du buffer
+/// n'invalident que les lignes qu'elles touchent.
+#[derive(Default)]
+struct WrapCache {
+    revision: u64,
+    width: usize,
+    rows: HashMap<usize, Rc<[Range<usize>]>>,
+}
+
+impl WrapCache {
+    /// met le cache à jour pour la révision du buffer : les lignes modifiées sont oubliées,
+    /// les suivantes suivent les lignes insérées ou supprimées
+    fn sync(&mut self, buffer: &Buffer) {
+        let revision = buffer.revision();
+        if revision == self.revision {
+            return;
+        }
+        match buffer.changes_since(self.revision) {
+            Some(changes) => {
+                for change in changes {
+                    self.rows = mem::take(&mut self.rows)
+                        .into_iter()
+                        .filter_map(|(line_index, rows)| {
+                            change.moved_line(line_index).map(|line_index| (line_index, rows))
+                        })
+                        .collect();
+                }
+            }
+            None => self.rows.clear(),
+        }
+        self.revision = revision;
+    }
 }
 
-#[derive(Copy, Clone, Default)]
+// l'ordre des champs compte : les positions sont comparées ligne par ligne
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Location {
-    pub grapheme_index: usize,
     pub line_index: usize,
-    
+    pub grapheme_index: usize,
+}
+
+/// façon dont la sélection a été commencée
+#[derive(Copy, Clone, Default, PartialEq, Eq)]
+enum SelectionKind {
+    /// déplacements avec Shift : un déplacement sans Shift l'abandonne
+    #[default]
+    Shift,
+    /// mode visuel de Vim : la sélection inclut le caractère sous le curseur
+    Visual,
+    /// région depuis la marque posée par `set_mark` : les déplacements l'étendent
+    Mark,
+}
+
+/// rangée d'écran d'une ligne, quand le retour à la ligne automatique est actif
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct VisualRow {
+    line_index: usize,
+    row: usize,
 }
 
 
@@ -47,24 +143,1194 @@ impl View {
 
     }
 
-    /// affiche une ligne de texte à l'écran
-    fn render_line(at:usize, line_text: &str) -> Result<(),Error> {
-        Terminal::print_row(at, line_text)
+    /// affiche une ligne de texte à l'écran, après la marge
+    fn render_line(
+        at: Position,
+        width: usize,
+        gutter: &AnnotatedString,
+        line_text: &str,
+    ) -> Result<(), Error> {
+        Terminal::print_annotated_row_at(at, width, gutter)?;
+        Terminal::print(line_text)
+    }
+
+    /// affiche une ligne de texte annotée à l'écran, après la marge
+    fn render_annotated_line(
+        at: Position,
+        width: usize,
+        gutter: &AnnotatedString,
+        annotated_string: &AnnotatedString,
+    ) -> Result<(), Error> {
+        Terminal::print_annotated_row_at(at, width, gutter)?;
+        Terminal::print_annotated(annotated_string)
+    }
+
+    // region: gutter
+
+    fn gutter_context(&self) -> GutterContext {
+        GutterContext {
+            line_count: self.buffer.borrow().height(),
+            caret_line: self.text_location.line_index,
+        }
+    }
+
+    /// largeur de la marge, qui disparaît si la vue est trop étroite pour y laisser du texte
+    fn gutter_width(&self) -> usize {
+        let width = self.gutter.width(self.gutter_context());
+        if width < self.size.width {
+            width
+        } else {
+            0
+        }
+    }
+
+    /// largeur disponible pour le texte, à droite de la marge
+    fn text_width(&self) -> usize {
+        self.size.width.saturating_sub(self.gutter_width())
+    }
+
+    pub const fn line_numbers(&self) -> LineNumbers {
+        self.gutter.line_numbers
+    }
+
+    /// choisit les numéros de ligne affichés dans la marge
+    pub fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.gutter.line_numbers = line_numbers;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    // endregion
+
+    // region: shared buffer
+
+    /// buffer affiché par la vue
+    pub fn buffer(&self) -> SharedBuffer {
+        Rc::clone(&self.buffer)
+    }
+
+    /// vrai si la vue affiche ce buffer
+    pub fn shows(&self, buffer: &SharedBuffer) -> bool {
+        Rc::ptr_eq(&self.buffer, buffer)
+    }
+
+    /// nouvelle vue sur le même buffer, à la même position
+    pub fn split(&self) -> Self {
+        Self {
+            buffer: self.buffer(),
+            seen_revision: self.seen_revision,
+            needs_redraw: true,
+            size: self.size,
+            text_location: self.text_location,
+            scroll_offset: self.scroll_offset,
+            gutter: self.gutter.clone(),
+            soft_wrap: self.soft_wrap,
+            scroll_row: self.scroll_row,
+            ..Self::default()
+        }
+    }
+
+    /// affiche un autre buffer, à la position où on l'avait laissé
+    pub fn show_buffer(&mut self, buffer: SharedBuffer) {
+        self.buffer.borrow_mut().last_location = self.text_location;
+        self.buffer = buffer;
+        self.seen_revision = self.buffer.borrow().revision();
+        self.search_info = None;
+        self.replace_info = None;
+        self.selection_anchor = None;
+        self.text_location = self.buffer.borrow().last_location;
+        self.scroll_offset = Position::default();
+        self.scroll_row = 0;
+        self.wrap_cache.get_mut().rows.clear();
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.center_text_location();
+    }
+
+    /// prend en compte les modifications faites au buffer depuis une autre vue : le curseur,
+    /// la sélection et le haut de la vue suivent le texte déplacé par les lignes insérées ou
+    /// supprimées avant eux, puis sont ramenés dans le texte. Avec `edited_here`, les
+    /// modifications viennent de cette vue, qui a déjà placé son curseur.
+    pub fn sync_with_buffer(&mut self, edited_here: bool) {
+        let revision = self.buffer.borrow().revision();
+        if revision == self.seen_revision {
+            return;
+        }
+        let changes = if edited_here {
+            Vec::new()
+        } else {
+            self.buffer
+                .borrow()
+                .changes_since(self.seen_revision)
+                .unwrap_or_default()
+        };
+        self.seen_revision = revision;
+        for change in changes {
+            self.text_location = change.adjust(self.text_location);
+            self.selection_anchor = self.selection_anchor.map(|anchor| change.adjust(anchor));
+            let top = change.adjust(Location {
+                line_index: self.scroll_offset.y,
+                grapheme_index: 0,
+            });
+            if top.line_index != self.scroll_offset.y {
+                self.scroll_offset.y = top.line_index;
+                self.scroll_row = 0;
+            }
+        }
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.selection_anchor = self
+            .selection_anchor
+            .map(|anchor| self.valid_location(anchor));
+        self.set_needs_redraw(true);
+    }
+
+    // endregion
+
+    // region: soft wrap
+
+    pub const fn soft_wrap(&self) -> bool {
+        self.soft_wrap
+    }
+
+    /// active ou désactive le retour à la ligne automatique
+    pub fn set_soft_wrap(&mut self, soft_wrap: bool) {
+        self.soft_wrap = soft_wrap;
+        self.scroll_offset.x = 0;
+        self.scroll_row = 0;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    /// rangées d'une ligne (une seule, vide, après la fin du buffer)
+    fn line_rows(&self, line_index: usize) -> Rc<[Range<usize>]> {
+        let width = self.text_width();
+        let buffer = self.buffer.borrow();
+        let mut cache = self.wrap_cache.borrow_mut();
+        if cache.width != width || cache.rows.len() >= WRAP_CACHE_CAPACITY {
+            *cache = WrapCache {
+                revision: buffer.revision(),
+                width,
+                rows: HashMap::new(),
+            };
+        }
+        cache.sync(&buffer);
+        let rows = cache.rows.entry(line_index).or_insert_with(|| {
+            buffer
+                .line(line_index)
+                .map_or_else(|| Rc::from([Range::default()]), |line| Rc::from(line.wrap(width)))
+        });
+        Rc::clone(rows)
+    }
+
+    /// rangée où se trouve un graphème : la fin d'une rangée coupée appartient à la suivante
+    fn row_of(rows: &[Range<usize>], grapheme_index: usize) -> usize {
+        rows.iter()
+            .position(|row| grapheme_index < row.end)
+            .unwrap_or(rows.len().saturating_sub(1))
+    }
+
+    fn caret_row(&self) -> VisualRow {
+        let rows = self.line_rows(self.text_location.line_index);
+        VisualRow {
+            line_index: self.text_location.line_index,
+            row: Self::row_of(&rows, self.text_location.grapheme_index),
+        }
     }
 
+    fn next_row(&self, from: VisualRow) -> Option<VisualRow> {
+        if from.row.saturating_add(1) < self.line_rows(from.line_index).len() {
+            Some(VisualRow {
+                row: from.row.saturating_add(1),
+                ..from
+            })
+        } else if from.line_index < self.buffer.borrow().height() {
+            Some(VisualRow {
+                line_index: from.line_index.saturating_add(1),
+                row: 0,
+            })
+        } else {
+            None
+        }
+    }
+
+    fn previous_row(&self, from: VisualRow) -> Option<VisualRow> {
+        if from.row > 0 {
+            Some(VisualRow {
+                row: from.row.saturating_sub(1),
+                ..from
+            })
+        } else {
+            let line_index = from.line_index.checked_sub(1)?;
+            Some(VisualRow {
+                line_index,
+                row: self.line_rows(line_index).len().saturating_sub(1),
+            })
+        }
+    }
+
+    /// rangée située `count` rangées plus haut, ou la première du buffer
+    fn rows_before(&self, from: VisualRow, count: usize) -> VisualRow {
+        let mut row = from;
+        for _ in 0..count {
+            match self.previous_row(row) {
+                Some(previous) => row = previous,
+                None => break,
+            }
+        }
+        row
+    }
+
+    const fn top_row(&self) -> VisualRow {
+        VisualRow {
+            line_index: self.scroll_offset.y,
+            row: self.scroll_row,
+        }
+    }
+
+    fn set_top_row(&mut self, top: VisualRow) {
+        if top != self.top_row() {
+            self.scroll_offset.y = top.line_index;
+            self.scroll_row = top.row;
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// nombre de rangées entre le haut de la vue et une rangée,
+    /// `None` si elle est au-dessus ou à plus de `limit` rangées
+    fn rows_from_top(&self, to: VisualRow, limit: usize) -> Option<usize> {
+        let mut row = self.top_row();
+        if to < row {
+            return None;
+        }
+        let mut distance: usize = 0;
+        while row < to {
+            if distance >= limit {
+                return None;
+            }
+            row = self.next_row(row)?;
+            distance = distance.saturating_add(1);
+        }
+        Some(distance)
+    }
+
+    fn scroll_rows_into_view(&mut self) {
+        let caret = self.caret_row();
+        let last_row = self.size.height.saturating_sub(1);
+        if caret < self.top_row() {
+            self.set_top_row(caret);
+        } else if self.rows_from_top(caret, last_row).is_none() {
+            self.set_top_row(self.rows_before(caret, last_row));
+        }
+    }
+
+    /// déplace le curseur de `step` rangées vers le haut ou le bas, en gardant sa colonne
+    fn move_by_rows(&mut self, step: usize, forward: bool) {
+        let caret = self.caret_row();
+        let rows = self.line_rows(caret.line_index);
+        let column = self
+            .buffer
+            .borrow()
+            .line(caret.line_index)
+            .zip(rows.get(caret.row))
+            .map_or(0, |(line, row)| {
+                line.width_of(row.start..self.text_location.grapheme_index)
+            });
+        let mut target = caret;
+        for _ in 0..step {
+            let next = if forward {
+                self.next_row(target)
+            } else {
+                self.previous_row(target)
+            };
+            match next {
+                Some(row) => target = row,
+                None => break,
+            }
+        }
+        let rows = self.line_rows(target.line_index);
+        let range = rows.get(target.row).cloned().unwrap_or_default();
+        let is_last_row = target.row.saturating_add(1) >= rows.len();
+        let grapheme_index = self
+            .buffer
+            .borrow()
+            .line(target.line_index)
+            .map_or(0, |line| line.grapheme_idx_at_column(range.clone(), column));
+        self.text_location = Location {
+            line_index: target.line_index,
+            grapheme_index: if is_last_row || grapheme_index < range.end {
+                grapheme_index
+            } else {
+                range.end.saturating_sub(1).max(range.start)
+            },
+        };
+    }
+
+    /// lignes affichées dans la vue, rangée par rangée : index de la ligne,
+    /// colonnes affichées, et vrai si c'est la première rangée de la ligne
+    fn visible_rows(&self) -> Vec<(usize, Range<usize>, bool)> {
+        let height = self.size.height;
+        let text_width = self.text_width();
+        let buffer = self.buffer.borrow();
+        if !self.soft_wrap {
+            let left = self.scroll_offset.x;
+            let right = left.saturating_add(text_width);
+            let end = min(self.scroll_offset.y.saturating_add(height), buffer.height());
+            return (self.scroll_offset.y..end)
+                .map(|line_index| (line_index, left..right, true))
+                .collect();
+        }
+        let mut result = Vec::with_capacity(height);
+        let mut line_index = self.scroll_offset.y;
+        let mut first_row = self.scroll_row;
+        while result.len() < height {
+            let Some(line) = buffer.line(line_index) else {
+                break;
+            };
+            let rows = self.line_rows(line_index);
+            let skipped = min(first_row, rows.len().saturating_sub(1));
+            for (row_index, row) in rows.iter().enumerate().skip(skipped) {
+                if result.len() >= height {
+                    break;
+                }
+                let columns = line.width_until(row.start)..line.width_until(row.end);
+                result.push((line_index, columns, row_index == 0));
+            }
+            first_row = 0;
+            line_index = line_index.saturating_add(1);
+        }
+        result
+    }
+
+    // endregion
+
+    // region: search
+
+    /// démarre une recherche en retenant la position actuelle
+    pub fn enter_search(&mut self) {
+        self.clear_selection();
+        self.search_info = Some(SearchInfo {
+            prev_location: self.text_location,
+            prev_scroll_offset: self.scroll_offset,
+            pattern: None,
+        });
+    }
+
+    /// termine la recherche en restant sur la correspondance courante
+    pub fn exit_search(&mut self) {
+        self.search_info = None;
+        self.set_needs_redraw(true);
+    }
+
+    /// annule la recherche et revient à la position de départ
+    pub fn dismiss_search(&mut self) {
+        if let Some(search_info) = &self.search_info {
+            self.text_location = search_info.prev_location;
+            self.scroll_offset = search_info.prev_scroll_offset;
+            self.scroll_text_location_into_view();
+        }
+        self.exit_search();
+    }
+
+    /// met à jour la recherche et va à la prochaine correspondance
+    pub fn search(&mut self, query: &str) {
+        if let Some(search_info) = &mut self.search_info {
+            search_info.pattern = Some(Pattern::literal(query));
+        }
+        self.search_in_direction(self.text_location, SearchDirection::Forward);
+    }
+
+    fn get_search_pattern(&self) -> Option<&Pattern> {
+        self.search_info
+            .as_ref()
+            .and_then(|search_info| search_info.pattern.as_ref())
+    }
+
+    /// motif dont les correspondances sont surlignées (recherche ou remplacement en cours)
+    fn get_highlighted_pattern(&self) -> Option<&Pattern> {
+        self.get_search_pattern().or_else(|| {
+            self.replace_info
+                .as_ref()
+                .map(|replace_info| &replace_info.pattern)
+        })
+    }
+
+    fn search_in_direction(&mut self, from: Location, direction: SearchDirection) {
+        if let Some(location) = self.get_search_pattern().and_then(|pattern| match direction {
+            SearchDirection::Forward => self.buffer.borrow().search_forward(pattern, from),
+            SearchDirection::Backward => self.buffer.borrow().search_backward(pattern, from),
+        }) {
+            self.text_location = location;
+            self.center_text_location();
+        }
+        self.set_needs_redraw(true);
+    }
+
+    /// va à la correspondance suivante
+    pub fn search_next(&mut self) {
+        let from = Location {
+            line_index: self.text_location.line_index,
+            grapheme_index: self.text_location.grapheme_index.saturating_add(1),
+        };
+        self.search_in_direction(from, SearchDirection::Forward);
+    }
+
+    /// va à la correspondance précédente
+    pub fn search_prev(&mut self) {
+        self.search_in_direction(self.text_location, SearchDirection::Backward);
+    }
+
+    // endregion
+
+    // region: replace
+
+    /// démarre un remplacement en cherchant la première correspondance à partir du curseur.
+    /// Tout le remplacement forme une seule étape d'annulation.
+    /// Retourne `false` s'il n'y a aucune correspondance.
+    pub fn start_replace(&mut self, pattern: Pattern, replacement: &str) -> bool {
+        self.clear_selection();
+        self.buffer.borrow_mut().begin_edit(EditKind::Other, self.text_location);
+        self.replace_info = Some(ReplaceInfo {
+            pattern,
+            replacement: replacement.to_string(),
+            current_match_end: self.text_location,
+            stop_at: self.text_location,
+            wrapped: false,
+            count: 0,
+        });
+        self.select_next_replace_match(self.text_location)
+    }
+
+    /// remplace la correspondance courante puis passe à la suivante.
+    /// Retourne `false` quand il n'y a plus de correspondance.
+    pub fn replace_current_match(&mut self) -> bool {
+        let Some(replace_info) = &mut self.replace_info else {
+            return false;
+        };
+        let start = self.text_location;
+        let match_end = replace_info.current_match_end;
+        let replacement =
+            self.buffer
+                .borrow()
+                .expand_match(&replace_info.pattern, start, &replace_info.replacement);
+        self.buffer.borrow_mut().delete_range(start, match_end);
+        let end = self.buffer.borrow_mut().insert_text(&replacement, start);
+        if replace_info.wrapped && replace_info.stop_at.line_index == start.line_index {
+            // la correspondance remplacée précède le point d'arrêt sur la même ligne
+            replace_info.stop_at.grapheme_index = replace_info
+                .stop_at
+                .grapheme_index
+                .saturating_sub(match_end.grapheme_index.saturating_sub(start.grapheme_index))
+                .saturating_add(end.grapheme_index.saturating_sub(start.grapheme_index));
+        }
+        replace_info.count = replace_info.count.saturating_add(1);
+        self.set_needs_redraw(true);
+        let next_from = if end == start {
+            Self::next_grapheme(end)
+        } else {
+            end
+        };
+        self.select_next_replace_match(next_from)
+    }
+
+    /// laisse la correspondance courante telle quelle et passe à la suivante
+    pub fn skip_current_match(&mut self) -> bool {
+        let match_end = self
+            .replace_info
+            .as_ref()
+            .map_or(self.text_location, |replace_info| replace_info.current_match_end);
+        let next_from = if match_end > self.text_location {
+            match_end
+        } else {
+            Self::next_grapheme(self.text_location)
+        };
+        self.select_next_replace_match(next_from)
+    }
+
+    /// remplace toutes les correspondances restantes sans confirmation
+    pub fn replace_all_matches(&mut self) {
+        while self.replace_current_match() {}
+    }
+
+    /// termine le remplacement et retourne le nombre de correspondances remplacées
+    pub fn finish_replace(&mut self) -> usize {
+        let count = self
+            .replace_info
+            .take()
+            .map_or(0, |replace_info| replace_info.count);
+        self.buffer.borrow_mut().end_edit(self.text_location);
+        self.set_needs_redraw(true);
+        count
+    }
+
+    const fn next_grapheme(location: Location) -> Location {
+        Location {
+            line_index: location.line_index,
+            grapheme_index: location.grapheme_index.saturating_add(1),
+        }
+    }
+
+    /// place le curseur sur la prochaine correspondance, en repartant du début du buffer
+    /// jusqu'au point de départ une fois la fin atteinte
+    fn select_next_replace_match(&mut self, from: Location) -> bool {
+        let Some(replace_info) = &mut self.replace_info else {
+            return false;
+        };
+        let end_of_buffer = Location {
+            line_index: self.buffer.borrow().height(),
+            grapheme_index: 0,
+        };
+        let until = if replace_info.wrapped {
+            replace_info.stop_at
+        } else {
+            end_of_buffer
+        };
+        let mut found = self.buffer.borrow().find_match(&replace_info.pattern, from, until);
+        if found.is_none() && !replace_info.wrapped {
+            replace_info.wrapped = true;
+            found = self.buffer.borrow().find_match(
+                &replace_info.pattern,
+                Location::default(),
+                replace_info.stop_at,
+            );
+        }
+        let Some((start, end)) = found else {
+            return false;
+        };
+        replace_info.current_match_end = end;
+        self.text_location = start;
+        self.center_text_location();
+        true
+    }
+
+    // endregion
+
     /// gère les commandes d'édition
     pub fn handle_edit_command(&mut self, command: Edit) {
+        match command {
+            Edit::Undo => {
+                self.clear_selection();
+                return self.undo();
+            }
+            Edit::Redo => {
+                self.clear_selection();
+                return self.redo();
+            }
+            _ => {}
+        }
+        // comme dans Emacs, taper n'efface pas la région : la marque est simplement enlevée
+        self.deactivate_mark();
+        let kind = match command {
+            _ if self.selection_range().is_some() => EditKind::Other,
+            Edit::Insert(_) => EditKind::Typing,
+            Edit::DeleteBackward => EditKind::Erasing,
+            Edit::Delete
+            | Edit::DeleteWord
+            | Edit::DeleteWordBackward
+            | Edit::InsertNewline
+            | Edit::Undo
+            | Edit::Redo => EditKind::Other,
+        };
+        self.buffer.borrow_mut().begin_edit(kind, self.text_location);
+        // taper ou supprimer remplace la sélection
+        let replaced_selection = self.delete_selection();
         match command {
             Edit::Insert(character) => self.insert_char(character),
+            Edit::Delete
+            | Edit::DeleteBackward
+            | Edit::DeleteWord
+            | Edit::DeleteWordBackward
+                if replaced_selection => {}
             Edit::Delete => self.delete(),
             Edit::DeleteBackward => self.delete_backward(),
+            Edit::DeleteWord => self.delete_word(true),
+            Edit::DeleteWordBackward => self.delete_word(false),
             Edit::InsertNewline => self.insert_newline(),
+            Edit::Undo | Edit::Redo => {}
+        }
+        self.buffer.borrow_mut().end_edit(self.text_location);
+    }
+
+    // region: selection and clipboard
+
+    /// retourne le début et la fin de la sélection, si elle n'est pas vide
+    fn selection_range(&self) -> Option<(Location, Location)> {
+        let anchor = self.selection_anchor?;
+        if self.selection_kind == SelectionKind::Visual {
+            let start = min(anchor, self.text_location);
+            let end = anchor.max(self.text_location);
+            let line_end = self.line_end(end.line_index);
+            return Some((start, min(Self::next_grapheme(end), line_end)));
+        }
+        match anchor.cmp(&self.text_location) {
+            std::cmp::Ordering::Less => Some((anchor, self.text_location)),
+            std::cmp::Ordering::Greater => Some((self.text_location, anchor)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    fn clear_selection(&mut self) {
+        if self.selection_anchor.take().is_some() {
+            self.set_needs_redraw(true);
+        }
+    }
+
+    /// supprime le texte sélectionné, retourne `false` s'il n'y avait pas de sélection
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection_range();
+        self.selection_anchor = None;
+        let Some((start, end)) = selection else {
+            return false;
+        };
+        self.buffer.borrow_mut().delete_range(start, end);
+        self.text_location = start;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        true
+    }
+
+    /// pose la marque au curseur, ou l'enlève si elle y est déjà ; retourne `true` si elle est posée
+    pub fn set_mark(&mut self) -> bool {
+        if self.selection_kind == SelectionKind::Mark
+            && self.selection_anchor == Some(self.text_location)
+        {
+            self.cancel_selection();
+            return false;
+        }
+        self.selection_anchor = Some(self.text_location);
+        self.selection_kind = SelectionKind::Mark;
+        self.set_needs_redraw(true);
+        true
+    }
+
+    /// abandonne la sélection et la marque
+    pub fn cancel_selection(&mut self) {
+        self.clear_selection();
+        self.selection_kind = SelectionKind::Shift;
+    }
+
+    /// une région copiée n'est plus étendue par les déplacements
+    pub fn deactivate_mark(&mut self) {
+        if self.selection_kind == SelectionKind::Mark {
+            self.cancel_selection();
+        }
+    }
+
+    /// coupe la fin de la ligne, ou le retour à la ligne si le curseur est déjà au bout
+    pub fn kill_line(&mut self) -> Option<String> {
+        let caret = self.text_location;
+        self.cancel_selection();
+        if caret.line_index >= self.buffer.borrow().height() {
+            return None;
+        }
+        let line_end = self.line_end(caret.line_index);
+        let end = if caret == line_end {
+            Location {
+                line_index: caret.line_index.saturating_add(1),
+                grapheme_index: 0,
+            }
+        } else {
+            line_end
+        };
+        self.buffer.borrow_mut().begin_edit(EditKind::Other, caret);
+        let text = self.buffer.borrow_mut().delete_range(caret, end);
+        self.buffer.borrow_mut().end_edit(caret);
+        self.set_needs_redraw(true);
+        (!text.is_empty()).then_some(text)
+    }
+
+    /// retourne le texte sélectionné
+    pub fn copy_selection(&self) -> Option<String> {
+        self.selection_range()
+            .map(|(start, end)| self.buffer.borrow().text_in_range(start, end))
+    }
+
+    /// retourne le texte sélectionné et le supprime
+    pub fn cut_selection(&mut self) -> Option<String> {
+        let text = self.copy_selection()?;
+        self.buffer.borrow_mut().begin_edit(EditKind::Other, self.text_location);
+        self.delete_selection();
+        self.buffer.borrow_mut().end_edit(self.text_location);
+        Some(text)
+    }
+
+    /// insère du texte (éventuellement sur plusieurs lignes) à la place de la sélection,
+    /// ou au curseur quand la région d'Emacs est active, en une seule étape d'annulation. Les fins de ligne `\r\n` et `\r` deviennent `\n`.
+    pub fn paste(&mut self, text: &str) {
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        self.deactivate_mark();
+        self.buffer.borrow_mut().begin_edit(EditKind::Other, self.text_location);
+        self.delete_selection();
+        self.text_location = self.buffer.borrow_mut().insert_text(&text, self.text_location);
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+        self.buffer.borrow_mut().end_edit(self.text_location);
+    }
+
+    // endregion
+
+    // region: vim
+
+    /// début de la ligne donnée, après son indentation
+    fn first_non_blank(&self, line_index: usize) -> Location {
+        Location {
+            line_index,
+            grapheme_index: self
+                .buffer
+                .borrow()
+                .line(line_index)
+                .map_or(0, |line| line.first_non_blank()),
+        }
+    }
+
+    /// position après le dernier graphème de la ligne donnée
+    fn line_end(&self, line_index: usize) -> Location {
+        Location {
+            line_index,
+            grapheme_index: self.buffer.borrow().grapheme_count(line_index),
+        }
+    }
+
+    /// place le curseur à une position, en faisant défiler la vue si besoin
+    fn jump_to(&mut self, location: Location) {
+        // les numéros relatifs changent avec la ligne du curseur, la sélection avec le curseur
+        if (self.gutter.line_numbers.follows_caret()
+            && location.line_index != self.text_location.line_index)
+            || self.selection_anchor.is_some()
+        {
+            self.set_needs_redraw(true);
+        }
+        self.text_location = location;
+        self.scroll_text_location_into_view();
+    }
+
+    /// position atteinte par un déplacement de Vim répété `count` fois
+    fn motion_target(&self, motion: Motion, count: usize) -> Location {
+        let caret = self.text_location;
+        let last_line = self.buffer.borrow().height().saturating_sub(1);
+        match motion {
+            Motion::Left => Location {
+                grapheme_index: caret.grapheme_index.saturating_sub(count),
+                ..caret
+            },
+            Motion::Right => Location {
+                grapheme_index: min(
+                    caret.grapheme_index.saturating_add(count),
+                    self.line_end(caret.line_index).grapheme_index,
+                ),
+                ..caret
+            },
+            Motion::Up | Motion::Down => {
+                let line_index = if motion == Motion::Up {
+                    caret.line_index.saturating_sub(count)
+                } else {
+                    min(caret.line_index.saturating_add(count), last_line)
+                };
+                Location {
+                    line_index,
+                    grapheme_index: min(
+                        caret.grapheme_index,
+                        self.line_end(line_index).grapheme_index,
+                    ),
+                }
+            }
+            Motion::LineStart => Location {
+                grapheme_index: 0,
+                ..caret
+            },
+            Motion::FirstNonBlank => self.first_non_blank(caret.line_index),
+            Motion::LineEnd => self.line_end(min(
+                caret.line_index.saturating_add(count.saturating_sub(1)),
+                last_line,
+            )),
+            Motion::GotoLine(number) => {
+                self.first_non_blank(min(number.saturating_sub(1), last_line))
+            }
+            Motion::LastLine => self.first_non_blank(last_line),
+            Motion::WordForward | Motion::WordBackward | Motion::WordEnd => {
+                let buffer = self.buffer.borrow();
+                (0..count).fold(caret, |from, _| Self::word_motion(&buffer, motion, from))
+            }
+        }
+    }
+
+    /// un pas d'un déplacement par mot, qui continue sur les lignes suivantes ou précédentes.
+    /// Une ligne vide compte comme un mot pour `w` et `b`.
+    fn word_motion(buffer: &Buffer, motion: Motion, from: Location) -> Location {
+        let on_line = buffer.line(from.line_index).and_then(|line| match motion {
+            Motion::WordForward => line.next_word_start(from.grapheme_index),
+            Motion::WordBackward => line.previous_word_start(from.grapheme_index),
+            _ => line.word_end(from.grapheme_index.saturating_add(1)),
+        });
+        if let Some(grapheme_index) = on_line {
+            return Location {
+                grapheme_index,
+                ..from
+            };
+        }
+        let word_on = |line_index: usize| {
+            let line = buffer.line(line_index)?;
+            let grapheme_index = match motion {
+                Motion::WordEnd => line.word_end(0),
+                _ if line.grapheme_count() == 0 => Some(0),
+                Motion::WordForward => {
+                    Some(line.first_non_blank()).filter(|idx| *idx < line.grapheme_count())
+                }
+                _ => line.previous_word_start(line.grapheme_count()),
+            }?;
+            Some(Location {
+                line_index,
+                grapheme_index,
+            })
+        };
+        if motion == Motion::WordBackward {
+            (0..from.line_index)
+                .rev()
+                .find_map(word_on)
+                .unwrap_or_default()
+        } else {
+            let last_line = buffer.height().saturating_sub(1);
+            (from.line_index.saturating_add(1)..buffer.height())
+                .find_map(word_on)
+                .unwrap_or(Location {
+                    line_index: last_line,
+                    grapheme_index: buffer.grapheme_count(last_line),
+                })
+        }
+    }
+
+    /// déplace le curseur ; en mode visuel, la sélection suit
+    pub fn move_by_motion(&mut self, motion: Motion, count: usize) {
+        let target = self.motion_target(motion, count);
+        self.jump_to(target);
+    }
+
+    /// en mode normal, le curseur est sur un caractère, jamais après le dernier de la ligne
+    pub fn snap_caret_to_character(&mut self) {
+        let height = self.buffer.borrow().height();
+        let line_index = min(self.text_location.line_index, height.saturating_sub(1));
+        let grapheme_index = min(
+            self.text_location.grapheme_index,
+            self.line_end(line_index).grapheme_index.saturating_sub(1),
+        );
+        self.jump_to(Location {
+            line_index,
+            grapheme_index,
+        });
+    }
+
+    /// applique un opérateur du curseur jusqu'à la destination d'un déplacement,
+    /// retourne le texte supprimé ou copié
+    pub fn apply_operator(
+        &mut self,
+        operator: Operator,
+        motion: Motion,
+        count: usize,
+    ) -> Option<Register> {
+        let caret = self.text_location;
+        let target = self.motion_target(motion, count);
+        if motion.is_linewise() {
+            return self.operate_on_lines(
+                operator,
+                min(caret.line_index, target.line_index),
+                caret.line_index.max(target.line_index),
+            );
+        }
+        let line = self.buffer.borrow().line(caret.line_index);
+        let end = match motion {
+            // `cw` s'arrête à la fin du mot, comme `ce`
+            Motion::WordForward
+                if operator == Operator::Change
+                    && line
+                        .as_ref()
+                        .is_some_and(|line| !line.is_blank(caret.grapheme_index)) =>
+            {
+                let word_end = Location {
+                    grapheme_index: line
+                        .and_then(|line| line.word_end(caret.grapheme_index))
+                        .unwrap_or(caret.grapheme_index),
+                    ..caret
+                };
+                let buffer = self.buffer.borrow();
+                Self::next_grapheme((1..count).fold(word_end, |from, _| {
+                    Self::word_motion(&buffer, Motion::WordEnd, from)
+                }))
+            }
+            // `dw` sur le dernier mot d'une ligne garde le retour à la ligne
+            Motion::WordForward if target.line_index > caret.line_index => {
+                self.line_end(caret.line_index)
+            }
+            _ if motion.is_inclusive() => Self::next_grapheme(target),
+            _ => target,
+        };
+        self.operate_on_range(operator, min(caret, end), caret.max(end))
+    }
+
+    /// applique un opérateur à `count` lignes entières à partir de celle du curseur
+    pub fn apply_operator_to_lines(&mut self, operator: Operator, count: usize) -> Option<Register> {
+        let first = self.text_location.line_index;
+        self.operate_on_lines(
+            operator,
+            first,
+            first.saturating_add(count.saturating_sub(1)),
+        )
+    }
+
+    /// applique un opérateur à la sélection du mode visuel, qui se termine
+    pub fn apply_operator_to_selection(&mut self, operator: Operator) -> Option<Register> {
+        let (start, end) = self.selection_range()?;
+        self.end_visual();
+        self.operate_on_range(operator, start, end)
+    }
+
+    fn operate_on_range(
+        &mut self,
+        operator: Operator,
+        start: Location,
+        end: Location,
+    ) -> Option<Register> {
+        if start >= end {
+            return None;
+        }
+        let text = self.buffer.borrow().text_in_range(start, end);
+        if operator != Operator::Yank {
+            self.buffer
+                .borrow_mut()
+                .begin_edit(EditKind::Other, self.text_location);
+            self.buffer.borrow_mut().delete_range(start, end);
+            self.buffer.borrow_mut().end_edit(start);
+            self.set_needs_redraw(true);
         }
+        self.jump_to(start);
+        Some(Register {
+            text,
+            linewise: false,
+        })
     }
 
-    /// gère les commandes de déplacement
+    // clippy::arithmetic_side_effects: `last` est inférieur à la hauteur du buffer
+    #[allow(clippy::arithmetic_side_effects)]
+    fn operate_on_lines(&mut self, operator: Operator, first: usize, last: usize) -> Option<Register> {
+        let height = self.buffer.borrow().height();
+        let last = min(last, height.checked_sub(1)?);
+        let first = min(first, last);
+        let next_line = Location {
+            line_index: last + 1,
+            grapheme_index: 0,
+        };
+        let text = self.buffer.borrow().text_in_range(
+            Location {
+                line_index: first,
+                grapheme_index: 0,
+            },
+            next_line,
+        );
+        let (start, end) = match operator {
+            Operator::Yank => {
+                self.jump_to(Location {
+                    line_index: first,
+                    ..self.text_location
+                });
+                return Some(Register {
+                    text,
+                    linewise: true,
+                });
+            }
+            // `cc` garde l'indentation de la première ligne
+            Operator::Change => (self.first_non_blank(first), self.line_end(last)),
+            // la dernière ligne emporte le retour à la ligne qui la précède
+            Operator::Delete if last + 1 == height && first > 0 => {
+                (self.line_end(first - 1), self.line_end(last))
+            }
+            Operator::Delete => (
+                Location {
+                    line_index: first,
+                    grapheme_index: 0,
+                },
+                next_line,
+            ),
+        };
+        self.buffer
+            .borrow_mut()
+            .begin_edit(EditKind::Other, self.text_location);
+        self.buffer.borrow_mut().delete_range(start, end);
+        let caret = if operator == Operator::Delete {
+            self.first_non_blank(min(first, self.buffer.borrow().height().saturating_sub(1)))
+        } else {
+            start
+        };
+        self.buffer.borrow_mut().end_edit(caret);
+        self.set_needs_redraw(true);
+        self.jump_to(caret);
+        Some(Register {
+            text,
+            linewise: true,
+        })
+    }
+
+    /// colle un registre `count` fois après le curseur, ou avant avec `before`, sans
+    /// dépasser `MAX_PUT_LEN`. Des lignes entières sont collées sous la ligne du curseur, ou au-dessus.
+    pub fn put(&mut self, register: &Register, before: bool, count: usize) {
+        let text = register.text.replace("\r\n", "\n").replace('\r', "\n");
+        let count = count.min(MAX_PUT_LEN.checked_div(text.len()).unwrap_or_default().max(1));
+        let text = text.repeat(count);
+        if text.is_empty() {
+            return;
+        }
+        let caret = self.text_location;
+        self.buffer.borrow_mut().begin_edit(EditKind::Other, caret);
+        let new_caret = if register.linewise {
+            let line_index = if before {
+                caret.line_index
+            } else {
+                caret.line_index.saturating_add(1)
+            };
+            let height = self.buffer.borrow().height();
+            if line_index < height {
+                self.buffer.borrow_mut().insert_text(
+                    &text,
+                    Location {
+                        line_index,
+                        grapheme_index: 0,
+                    },
+                );
+            } else {
+                // après la dernière ligne, le retour à la ligne passe devant le texte
+                let text = text.strip_suffix('\n').unwrap_or(&text);
+                if let Some(last_line) = height.checked_sub(1) {
+                    let at = self.line_end(last_line);
+                    self.buffer.borrow_mut().insert_text(&format!("\n{text}"), at);
+                } else {
+                    self.buffer.borrow_mut().insert_text(text, Location::default());
+                }
+            }
+            self.first_non_blank(line_index)
+        } else {
+            let at = if before {
+                caret
+            } else {
+                min(Self::next_grapheme(caret), self.line_end(caret.line_index))
+            };
+            let end = self.buffer.borrow_mut().insert_text(&text, at);
+            // le curseur finit sur le dernier caractère collé
+            Location {
+                grapheme_index: end.grapheme_index.saturating_sub(1),
+                ..end
+            }
+        };
+        self.buffer.borrow_mut().end_edit(new_caret);
+        self.set_needs_redraw(true);
+        self.jump_to(new_caret);
+    }
+
+    /// place le curseur là où le mode insertion commence ; `o` et `O` ouvrent une ligne
+    pub fn start_insert(&mut self, position: InsertPosition) {
+        let caret = self.text_location;
+        match position {
+            InsertPosition::Before => {}
+            InsertPosition::After => {
+                self.jump_to(min(Self::next_grapheme(caret), self.line_end(caret.line_index)));
+            }
+            InsertPosition::LineStart => self.jump_to(self.first_non_blank(caret.line_index)),
+            InsertPosition::LineEnd => self.jump_to(self.line_end(caret.line_index)),
+            InsertPosition::LineBelow => {
+                self.jump_to(self.line_end(caret.line_index));
+                self.handle_edit_command(Edit::InsertNewline);
+            }
+            InsertPosition::LineAbove => {
+                // la nouvelle ligne reprend l'indentation de celle du curseur
+                let indent_end = self.first_non_blank(caret.line_index);
+                let line_start = Location {
+                    grapheme_index: 0,
+                    ..caret
+                };
+                let indentation = if self.buffer.borrow().indentation().auto_indent {
+                    self.buffer.borrow().text_in_range(line_start, indent_end)
+                } else {
+                    String::new()
+                };
+                self.buffer.borrow_mut().begin_edit(EditKind::Other, caret);
+                self.buffer
+                    .borrow_mut()
+                    .insert_text(&format!("{indentation}\n"), line_start);
+                let new_caret = self.line_end(caret.line_index);
+                self.buffer.borrow_mut().end_edit(new_caret);
+                self.set_needs_redraw(true);
+                self.jump_to(new_caret);
+            }
+        }
+    }
+
+    /// sortie du mode insertion : le curseur revient sur le dernier caractère tapé
+    pub fn leave_insert(&mut self) {
+        let caret = self.text_location;
+        self.jump_to(Location {
+            grapheme_index: caret.grapheme_index.saturating_sub(1),
+            ..caret
+        });
+    }
+
+    /// commence une sélection du mode visuel au curseur
+    pub fn start_visual(&mut self) {
+        self.selection_anchor = Some(self.text_location);
+        self.selection_kind = SelectionKind::Visual;
+        self.set_needs_redraw(true);
+    }
+
+    pub fn end_visual(&mut self) {
+        self.cancel_selection();
+    }
+
+    // endregion
+
+    /// annule la dernière modification et remet le curseur où il était
+    fn undo(&mut self) {
+        let location = self.buffer.borrow_mut().undo();
+        if let Some(location) = location {
+            self.restore_text_location(location);
+        }
+    }
+
+    /// rétablit la dernière modification annulée
+    fn redo(&mut self) {
+        let location = self.buffer.borrow_mut().redo();
+        if let Some(location) = location {
+            self.restore_text_location(location);
+        }
+    }
+
+    fn restore_text_location(&mut self, location: Location) {
+        self.text_location = location;
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
+    /// gère les commandes de déplacement ; après `set_mark`, elles étendent la région
     pub fn handle_move_command(&mut self, command: Move) {
+        if self.selection_kind == SelectionKind::Mark && self.selection_anchor.is_some() {
+            self.move_text_location(command);
+            self.set_needs_redraw(true);
+            return;
+        }
+        self.clear_selection();
+        self.move_text_location(command);
+    }
+
+    /// gère les déplacements avec Shift, qui étendent la sélection
+    pub fn handle_select_command(&mut self, command: Move) {
+        if self.selection_anchor.is_none() {
+            self.selection_anchor = Some(self.text_location);
+            self.selection_kind = SelectionKind::Shift;
+        }
+        self.move_text_location(command);
+        self.set_needs_redraw(true);
+    }
+
+    fn move_text_location(&mut self, command: Move) {
         let Size { height, .. } = self.size;
+        let previous_line = self.text_location.line_index;
         // This match moves the positon, but does not check for all boundaries.
         // The final boundarline checking happens after the match statement.
         match command {
@@ -76,6 +1342,19 @@ impl View {
             Move::PageDown => self.move_down(height.saturating_sub(1)),
             Move::StartOfLine => self.move_to_start_of_line(),
             Move::EndOfLine => self.move_to_end_of_line(),
+            Move::WordLeft => self.text_location = self.word_boundary(false),
+            Move::WordRight => self.text_location = self.word_boundary(true),
+            Move::StartOfBuffer => self.text_location = Location::default(),
+            Move::EndOfBuffer => {
+                self.text_location = Location {
+                    line_index: self.buffer.borrow().height(),
+                    grapheme_index: 0,
+                };
+            }
+        }
+        // les numéros relatifs changent avec la ligne du curseur
+        if self.gutter.line_numbers.follows_caret() && previous_line != self.text_location.line_index {
+            self.set_needs_redraw(true);
         }
         self.scroll_text_location_into_view();
     }
@@ -83,73 +1362,195 @@ impl View {
     /// récupère le status du document
     pub fn get_status(&self) -> DocumentStatus {
         DocumentStatus {
-            total_lines: self.buffer.height(),
+            total_lines: self.buffer.borrow().height(),
             current_line_index: self.text_location.line_index,
-            file_name: format!("{}", self.buffer.file_info),
-            is_modified: self.buffer.dirty,
+            file_name: format!("{}", self.buffer.borrow().file_info),
+            is_modified: self.buffer.borrow().is_dirty(),
+            is_new_file: self.buffer.borrow().file_info.is_new(),
+            file_type: self.buffer.borrow().syntax_name().to_string(),
+            file_format: self.buffer.borrow().file_info.format_to_string(),
+            ..DocumentStatus::default()
         }
     }
 
-    /// suppression d'un caractère et retour en arrière
+    /// passe les fins de ligne du fichier de LF à CRLF ou inversement, ou uniformise un fichier mixte
+    pub fn toggle_line_ending(&mut self) -> LineEnding {
+        self.buffer.borrow_mut().toggle_line_ending()
+    }
+
+    /// suppression d'un caractère et retour en arrière ;
+    /// dans l'indentation, retire les espaces jusqu'au taquet précédent
     fn delete_backward(&mut self) {
-        if self.text_location.line_index != 0 || self.text_location.grapheme_index != 0 {
-            self.handle_move_command(Move::Left);
+        let unindent_start = self
+            .buffer
+            .borrow()
+            .line(self.text_location.line_index)
+            .and_then(|line| line.unindent_start(self.text_location.grapheme_index));
+        if let Some(grapheme_index) = unindent_start {
+            let start = Location {
+                line_index: self.text_location.line_index,
+                grapheme_index,
+            };
+            self.buffer.borrow_mut().delete_range(start, self.text_location);
+            self.text_location = start;
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+        } else if self.text_location.line_index != 0 || self.text_location.grapheme_index != 0 {
+            self.move_text_location(Move::Left);
             self.delete();
         }
     }
 
+    /// supprime le texte jusqu'à la fin du mot suivant, ou depuis le début du mot précédent
+    fn delete_word(&mut self, forward: bool) {
+        let target = self.word_boundary(forward);
+        let (start, end) = if forward {
+            (self.text_location, target)
+        } else {
+            (target, self.text_location)
+        };
+        if start == end {
+            return;
+        }
+        self.buffer.borrow_mut().delete_range(start, end);
+        self.text_location = start;
+        self.scroll_text_location_into_view();
+        self.set_needs_redraw(true);
+    }
+
     /// sauvegarde le document
     pub fn save(&mut self) -> Result<(), Error> {
-        self.buffer.save()
+        self.buffer.borrow_mut().save()
     }
 
     /// insère un retour à la ligne
     fn insert_newline(&mut self) {
-        self.buffer.insert_newline(self.text_location);
-        self.handle_move_command(Move::Right);
+        self.text_location = self.buffer.borrow_mut().insert_newline(self.text_location);
+        self.scroll_text_location_into_view();
         self.set_needs_redraw(true);
     }
 
     /// supprime un caractère
     fn delete(&mut self) {
-        self.buffer.delete(self.text_location);
+        self.buffer.borrow_mut().delete(self.text_location);
         self.set_needs_redraw(true);
     }
 
-    /// insère un caractère
+    /// insère un caractère ; Tab insère des espaces si les tabulations sont remplacées
     fn insert_char(&mut self, character: char) {
-        let old_len = self
-            .buffer
-            .lines
-            .get(self.text_location.line_index)
-            .map_or(0, Line::grapheme_count);
-        self.buffer.insert_char(character, self.text_location);
-        let new_len = self
-            .buffer
-            .lines
-            .get(self.text_location.line_index)
-            .map_or(0, Line::grapheme_count);
+        let indentation = self.buffer.borrow().indentation();
+        if character == '\t' && indentation.expand_tabs {
+            let column = self
+                .buffer
+                .borrow()
+                .line(self.text_location.line_index)
+                .map_or(0, |line| line.width_until(self.text_location.grapheme_index));
+            let text = indentation.tab_text(column);
+            self.text_location = self.buffer.borrow_mut().insert_text(&text, self.text_location);
+            self.scroll_text_location_into_view();
+            self.set_needs_redraw(true);
+            return;
+        }
+        let old_len = self.buffer.borrow().grapheme_count(self.text_location.line_index);
+        self.buffer.borrow_mut().insert_char(character, self.text_location);
+        let new_len = self.buffer.borrow().grapheme_count(self.text_location.line_index);
         let grapheme_delta = new_len.saturating_sub(old_len);
         if grapheme_delta > 0 {
-            self.handle_move_command(Move::Right);
+            self.move_text_location(Move::Right);
         }
         self.set_needs_redraw(true);
     }
 
-    /// affiche à l'écran le contenu d'un fichier
+    /// affiche à l'écran le contenu d'un fichier, à la place du buffer actuel
     pub fn load(&mut self, file_name: &str) -> Result<(), Error> {
-        let buffer = Buffer::load(file_name)?;
-        self.buffer = buffer;
+        let mut buffer = Buffer::open(file_name)?;
+        let mut current = self.buffer.borrow_mut();
+        buffer.backup_on_save = current.backup_on_save;
+        buffer.set_indentation(current.indentation());
+        buffer.bump_revision(&current);
+        *current = buffer;
+        drop(current);
+        self.set_needs_redraw(true);
+        Ok(())
+    }
+
+    pub fn is_file_loaded(&self) -> bool {
+        self.buffer.borrow().is_file_loaded()
+    }
+
+    // region: external changes
+
+    /// vérifie si le fichier a été modifié sur le disque par un autre programme
+    pub fn check_external_change(&mut self) -> ExternalChange {
+        self.buffer.borrow_mut().check_external_change()
+    }
+
+    /// garde la version du buffer malgré la modification sur le disque
+    pub fn ignore_external_change(&mut self) {
+        self.buffer.borrow_mut().ignore_external_change();
+    }
+
+    /// recharge le fichier depuis le disque, en gardant le curseur au même endroit si possible
+    pub fn reload(&mut self) -> Result<(), Error> {
+        self.buffer.borrow_mut().reload()?;
+        self.clear_selection();
+        self.snap_to_valid_line();
+        self.snap_to_valid_grapheme();
+        self.scroll_text_location_into_view();
         self.set_needs_redraw(true);
         Ok(())
     }
 
-    pub const fn is_file_loaded(&self) -> bool {
-        self.buffer.is_file_loaded()
+    // endregion
+
+    // region: swap file
+
+    pub fn has_leftover_swap(&self) -> bool {
+        self.buffer.borrow().has_leftover_swap()
+    }
+
+    /// récupère le texte du fichier d'échange laissé par une session précédente
+    pub fn recover_swap(&mut self) -> Result<(), Error> {
+        self.buffer.borrow_mut().recover_swap()?;
+        self.clear_selection();
+        self.text_location = Location::default();
+        self.scroll_offset = Position::default();
+        self.set_needs_redraw(true);
+        Ok(())
+    }
+
+    pub fn diff_swap(&self) -> Result<String, Error> {
+        self.buffer.borrow().diff_swap()
+    }
+
+    pub fn discard_swap(&mut self) {
+        self.buffer.borrow_mut().discard_swap();
+    }
+
+    // endregion
+
+    /// garde ou non une copie `fichier~` de la version précédente à chaque sauvegarde
+    pub fn set_backup_on_save(&mut self, backup_on_save: bool) {
+        self.buffer.borrow_mut().backup_on_save = backup_on_save;
+    }
+
+    /// change les réglages d'indentation du buffer affiché
+    pub fn set_indentation(&mut self, indentation: Indentation) {
+        self.buffer.borrow_mut().set_indentation(indentation);
     }
 
     pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
-        self.buffer.save_as(file_name)
+        self.buffer.borrow_mut().save_as(file_name)
+    }
+
+    /// dossier qui n'existe pas encore et qu'il faut créer pour sauvegarder
+    /// le fichier du buffer, ou `file_name` s'il est donné
+    pub fn missing_directory(&self, file_name: Option<&str>) -> Option<PathBuf> {
+        let buffer = self.buffer.borrow();
+        let path = file_name.map(Path::new).or_else(|| buffer.file_info.get_path())?;
+        path.parent()
+            .filter(|parent| !parent.as_os_str().is_empty() && !parent.exists())
+            .map(Path::to_path_buf)
     }
 
     /// déplace le curseur verticalement
@@ -171,7 +1572,7 @@ impl View {
 
     /// déplace le curseur horizontalement
     fn scroll_horizontally(&mut self, to: usize) {
-        let Size { width, .. } = self.size;
+        let width = self.text_width();
         let offset_changed = if to < self.scroll_offset.x {
             self.scroll_offset.x = to;
             true
@@ -185,7 +1586,36 @@ impl View {
             self.set_needs_redraw(true);
         }
     }
+
+    /// saute à une ligne, ou à une position relative au curseur, placée au milieu de la vue
+    pub fn go_to(&mut self, target: GotoTarget) {
+        self.clear_selection();
+        self.text_location = self.buffer.borrow().goto_location(target, self.text_location);
+        self.center_text_location();
+    }
+
+    /// place la position du curseur au milieu de la vue
+    // we allow this since we don't care if the location is _exactly_ centered
+    #[allow(clippy::integer_division)]
+    fn center_text_location(&mut self) {
+        let Size { height, .. } = self.size;
+        if self.soft_wrap {
+            self.set_top_row(self.rows_before(self.caret_row(), height / 2));
+            self.set_needs_redraw(true);
+            return;
+        }
+        let width = self.text_width();
+        let Position { x, y } = self.text_location_to_position();
+        self.scroll_offset.y = y.saturating_sub(height / 2);
+        self.scroll_offset.x = x.saturating_sub(width / 2);
+        self.set_needs_redraw(true);
+    }
+
     fn scroll_text_location_into_view(&mut self) {
+        if self.soft_wrap {
+            self.scroll_rows_into_view();
+            return;
+        }
         let Position { y, x } = self.text_location_to_position();
         self.scroll_vertically(y);
         self.scroll_horizontally(x);
@@ -197,14 +1627,43 @@ impl View {
 
     /// Returns the current caret position in the view.
     pub fn caret_position(&self) -> Position {
-        self.text_location_to_position()
-            .saturating_sub(self.scroll_offset)
+        let Position { x, y } = if self.soft_wrap {
+            self.caret_row_position()
+        } else {
+            self.text_location_to_position()
+                .saturating_sub(self.scroll_offset)
+        };
+        Position {
+            x: x.saturating_add(self.gutter_width()),
+            y,
+        }
+    }
+
+    /// Returns the caret position in the view when lines are wrapped. The caret stays on the
+    /// last column when it follows a row that fills the whole width.
+    fn caret_row_position(&self) -> Position {
+        let caret = self.caret_row();
+        let rows = self.line_rows(caret.line_index);
+        let x = self
+            .buffer
+            .borrow()
+            .line(caret.line_index)
+            .zip(rows.get(caret.row))
+            .map_or(0, |(line, row)| {
+                line.width_of(row.start..self.text_location.grapheme_index)
+            });
+        Position {
+            x: min(x, self.text_width().saturating_sub(1)),
+            y: self
+                .rows_from_top(caret, self.size.height)
+                .unwrap_or_default(),
+        }
     }
 
     /// Returns the current text location in the view.
     fn text_location_to_position(&self) -> Position {
         let row = self.text_location.line_index;
-        let col = self.buffer.lines.get(row).map_or(0, |line| {
+        let col = self.buffer.borrow().line(row).map_or(0, |line| {
             line.width_until(self.text_location.grapheme_index)
         });
         Position { x:col, y:row }
@@ -215,10 +1674,16 @@ impl View {
     // region: text location movement
 
     fn move_up(&mut self, step: usize) {
+        if self.soft_wrap {
+            return self.move_by_rows(step, false);
+        }
         self.text_location.line_index = self.text_location.line_index.saturating_sub(step);
         self.snap_to_valid_grapheme();
     }
     fn move_down(&mut self, step: usize) {
+        if self.soft_wrap {
+            return self.move_by_rows(step, true);
+        }
         self.text_location.line_index = self.text_location.line_index.saturating_add(step);
         self.snap_to_valid_grapheme();
         self.snap_to_valid_line();
@@ -227,11 +1692,7 @@ impl View {
     // after explicitly checking that the target value will be within bounds.
     #[allow(clippy::arithmetic_side_effects)]
     fn move_right(&mut self) {
-        let line_width = self
-            .buffer
-            .lines
-            .get(self.text_location.line_index)
-            .map_or(0, Line::grapheme_count);
+        let line_width = self.buffer.borrow().grapheme_count(self.text_location.line_index);
         if self.text_location.grapheme_index < line_width {
             self.text_location.grapheme_index += 1;
         } else {
@@ -250,15 +1711,63 @@ impl View {
             self.move_to_end_of_line();
         }
     }
+
+    /// fin du mot suivant ou début du mot précédent ; sans mot de ce côté sur la ligne,
+    /// le bout de la ligne, puis la ligne voisine
+    fn word_boundary(&self, forward: bool) -> Location {
+        let Location {
+            line_index,
+            grapheme_index,
+        } = self.text_location;
+        let buffer = self.buffer.borrow();
+        let line_end = buffer.grapheme_count(line_index);
+        let on_line = buffer.line(line_index).and_then(|line| {
+            if forward {
+                line.next_word_boundary(grapheme_index)
+            } else {
+                line.previous_word_start(grapheme_index)
+            }
+        });
+        match on_line {
+            Some(grapheme_index) => Location {
+                line_index,
+                grapheme_index,
+            },
+            None if forward && grapheme_index < line_end => Location {
+                line_index,
+                grapheme_index: line_end,
+            },
+            None if forward && line_index < buffer.height() => Location {
+                line_index: line_index.saturating_add(1),
+                grapheme_index: 0,
+            },
+            None if !forward && grapheme_index > 0 => Location {
+                line_index,
+                grapheme_index: 0,
+            },
+            None if !forward && line_index > 0 => Location {
+                line_index: line_index.saturating_sub(1),
+                grapheme_index: buffer.grapheme_count(line_index.saturating_sub(1)),
+            },
+            None => self.text_location,
+        }
+    }
+
     fn move_to_start_of_line(&mut self) {
         self.text_location.grapheme_index = 0;
     }
     fn move_to_end_of_line(&mut self) {
-        self.text_location.grapheme_index = self
-            .buffer
-            .lines
-            .get(self.text_location.line_index)
-            .map_or(0, Line::grapheme_count);
+        self.text_location.grapheme_index = self.buffer.borrow().grapheme_count(self.text_location.line_index);
+    }
+
+    /// ramène une position dans le texte du buffer
+    fn valid_location(&self, location: Location) -> Location {
+        let buffer = self.buffer.borrow();
+        let line_index = min(location.line_index, buffer.height());
+        Location {
+            line_index,
+            grapheme_index: min(location.grapheme_index, buffer.grapheme_count(line_index)),
+        }
     }
 
     // Ensures self.location.grapheme_index points to a valid grapheme index by snapping it to the left most grapheme if appropriate.
@@ -266,8 +1775,8 @@ impl View {
     fn snap_to_valid_grapheme(&mut self) {
         self.text_location.grapheme_index = self
             .buffer
-            .lines
-            .get(self.text_location.line_index)
+            .borrow()
+            .line(self.text_location.line_index)
             .map_or(0, |line| {
                 min(line.grapheme_count(), self.text_location.grapheme_index)
             });
@@ -275,7 +1784,7 @@ impl View {
     // Ensures self.location.line_index points to a valid line index by snapping it to the bottom most line if appropriate.
     // Doesn't trigger scrolling.
     fn snap_to_valid_line(&mut self) {
-        self.text_location.line_index = min(self.text_location.line_index, self.buffer.height());
+        self.text_location.line_index = min(self.text_location.line_index, self.buffer.borrow().height());
     }
 
 }
@@ -293,31 +1802,85 @@ impl UIComponent for View {
         self.scroll_text_location_into_view();
     }
 
-    fn draw(&mut self, origin_y: usize) -> Result<(), Error> {
+    fn draw(&mut self, origin: Position) -> Result<(), Error> {
         let Size { height, width } = self.size;
-        let end_y = origin_y.saturating_add(height);
+        let gutter_width = self.gutter_width();
+        let text_width = width.saturating_sub(gutter_width);
+        let gutter_context = self.gutter_context();
+        let render_gutter = |line_index: Option<usize>| {
+            if gutter_width == 0 {
+                AnnotatedString::default()
+            } else {
+                self.gutter.render(line_index, gutter_context)
+            }
+        };
+        let end_y = origin.y.saturating_add(height);
         // we allow this since we don't care if our welcome message is put _exactly_ in the top third.
         // it's allowed to be a bit too far up or down
         #[allow(clippy::integer_division)]
-        let top_third = height / 3;
+        let top_third = origin.y.saturating_add(height / 3);
         let scroll_top = self.scroll_offset.y;
-        for current_row in origin_y..end_y {
-            // to get the correct line index, we have to take current_row (the absolute row on screen),
-            // subtract origin_y to get the current row relative to the view (ranging from 0 to self.size.height)
-            // and add the scroll offset.
-            let line_idx = current_row 
-                .saturating_sub(origin_y)
-                .saturating_add(scroll_top);
-            if let Some(line) = self.buffer.lines.get(line_idx) {
-                let left = self.scroll_offset.x;
-                let right = self.scroll_offset.x.saturating_add(width);
-                Self::render_line(current_row, &line.get_visible_graphemes(left..right))?;
-            } else if current_row == top_third && self.buffer.is_empty() {
-                Self::render_line(current_row, &Self::build_welcome_message(width))?;
+        self.buffer
+            .borrow_mut()
+            .highlight(scroll_top.saturating_add(height));
+        self.seen_revision = self.buffer.borrow().revision();
+        let visible_rows = self.visible_rows();
+        let buffer = self.buffer.borrow();
+        let pattern = self.get_highlighted_pattern();
+        let selection = self.selection_range();
+        for (current_row, visible_row) in (origin.y..end_y).zip(
+            visible_rows
+                .into_iter()
+                .map(Some)
+                .chain(std::iter::repeat(None)),
+        ) {
+            let at = Position {
+                x: origin.x,
+                y: current_row,
+            };
+            let line = visible_row
+                .as_ref()
+                .and_then(|(line_idx, ..)| buffer.line(*line_idx));
+            if let (Some((line_idx, columns, is_first_row)), Some(line)) = (visible_row, line) {
+                let mut annotations = buffer.syntax_annotations(line_idx).to_vec();
+                if let Some(pattern) = pattern {
+                    let selected_match = (line_idx == self.text_location.line_index)
+                        .then_some(self.text_location.grapheme_index);
+                    annotations.extend(line.match_annotations(pattern, selected_match));
+                }
+                if let Some((start, end)) = selection
+                    .filter(|(start, end)| (start.line_index..=end.line_index).contains(&line_idx))
+                {
+                    let from = if line_idx == start.line_index {
+                        start.grapheme_index
+                    } else {
+                        0
+                    };
+                    let to = if line_idx == end.line_index {
+                        end.grapheme_index
+                    } else {
+                        line.grapheme_count()
+                    };
+                    annotations.push(line.annotation_for(AnnotationType::Selection, from..to));
+                }
+                // une ligne coupée n'a son numéro que sur sa première rangée
+                Self::render_annotated_line(
+                    at,
+                    width,
+                    &render_gutter(is_first_row.then_some(line_idx)),
+                    &line.get_annotated_visible_substr(columns, &annotations),
+                )?;
+            } else if current_row == top_third && buffer.is_empty() {
+                Self::render_line(
+                    at,
+                    width,
+                    &render_gutter(None),
+                    &Self::build_welcome_message(text_width),
+                )?;
             } else {
-                Self::render_line(current_row, "~")?;
+                Self::render_line(at, width, &render_gutter(None), "~")?;
             }
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
@@ -1,16 +1,60 @@
-use std::fs::{read_to_string, File};
-use std::io::Error;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, read_to_string};
+use std::io::{Error, ErrorKind, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use ropey::{Rope, RopeBuilder};
 use super::Location;
-use std::io::Write;
-use super::FileInfo;
-use super::Line;
+use super::{GotoTarget, LineTarget};
+use super::{DiskState, ExternalChange, FileInfo, LineEnding};
+use super::{Indentation, Line};
+use super::history::{EditKind, History, Operation};
+use super::Pattern;
+use super::{Annotation, AnnotationType, Highlighter, Syntax};
+mod atomicfile;
+use atomicfile::write_atomically;
+mod swapfile;
+pub use swapfile::flush_pending as flush_swap_files;
 
+/// nombre de lignes découpées en graphèmes gardées en mémoire
+const LINE_CACHE_CAPACITY: usize = 4096;
+/// délai maximal entre deux écritures du fichier d'échange pendant la frappe
+const SWAP_INTERVAL: Duration = Duration::from_secs(10);
+/// nombre de modifications gardées pour que les autres vues suivent le texte
+const CHANGES_CAPACITY: usize = 1024;
 
+/// Le texte est stocké dans une rope : chaque ligne y est suivie d'un `\n`, y compris la dernière.
+/// Les `Line` (découpées en graphèmes) ne sont construites qu'à la demande, pour les lignes
+/// réellement affichées ou parcourues, et gardées en cache jusqu'à leur prochaine modification.
 #[derive(Default)]
 pub struct Buffer {
-    pub lines: Vec<Line>,
-    pub dirty: bool, // en gros pour savoir si on a save ou pas
+    text: Rope,
+    line_cache: RefCell<HashMap<usize, Rc<Line>>>,
     pub file_info: FileInfo,
+    /// fins de ligne du fichier lors de la dernière sauvegarde
+    saved_line_ending: LineEnding,
+    /// le fichier mélangeait LF et CRLF lors de la dernière sauvegarde
+    saved_mixed_endings: bool,
+    /// garde une copie `fichier~` de la version précédente à chaque sauvegarde
+    pub backup_on_save: bool,
+    indentation: Indentation,
+    /// incrémenté à chaque modification du texte
+    revision: u64,
+    /// dernières modifications du texte, avec la révision qu'elles ont produite
+    changes: VecDeque<(u64, TextChange)>,
+    /// révision à partir de laquelle `changes` contient toutes les modifications
+    changes_since: u64,
+    /// révision écrite dans le fichier d'échange, `None` si on n'en a pas écrit
+    swapped_revision: Option<u64>,
+    last_swap_write: Option<Instant>,
+    /// position du curseur quand le buffer a été quitté, restaurée quand on y revient
+    pub last_location: Location,
+    history: History,
+    highlighter: Highlighter,
 }
 
 impl  Buffer {
@@ -18,97 +62,1054 @@ impl  Buffer {
     /// permet de charger le texte d'un fichier dans le buffer
     pub fn load(file_name: &str) -> Result<Self, Error> {
         let contents = read_to_string(file_name)?;
-        let mut lines = Vec::new();
-        for value in contents.lines() {
-            lines.push(Line::from(value));
+        let mut file_info = FileInfo::from(file_name);
+        file_info.disk_state = Some(DiskState::from(&fs::metadata(file_name)?, contents.as_bytes()));
+        let text = file_info.detect_format(&contents);
+        // dans la rope, toutes les lignes se terminent par `\n`
+        let mut builder = RopeBuilder::new();
+        for value in text.lines() {
+            builder.append(value);
+            builder.append("\n");
         }
-        Ok(Self { lines, file_info: FileInfo::from(file_name),dirty: false, })
+        let highlighter = Highlighter::new(Self::syntax_for(&file_info));
+        Ok(Self {
+            text: builder.finish(),
+            line_cache: RefCell::default(),
+            saved_line_ending: file_info.line_ending,
+            saved_mixed_endings: file_info.line_endings.is_some(),
+            backup_on_save: false,
+            indentation: Indentation::default(),
+            revision: 0,
+            changes: VecDeque::new(),
+            changes_since: 0,
+            swapped_revision: None,
+            last_swap_write: None,
+            last_location: Location::default(),
+            file_info,
+            history: History::default(),
+            highlighter,
+        })
+    }
+
+    /// ouvre un fichier ; un chemin qui n'existe pas encore donne un buffer vide,
+    /// le fichier est créé à la première sauvegarde
+    pub fn open(file_name: &str) -> Result<Self, Error> {
+        match Self::load(file_name) {
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                let file_info = FileInfo::from(file_name);
+                Ok(Self {
+                    highlighter: Highlighter::new(Self::syntax_for(&file_info)),
+                    file_info,
+                    ..Self::default()
+                })
+            }
+            result => result,
+        }
+    }
+
+    /// choisit la coloration syntaxique d'après l'extension du fichier
+    fn syntax_for(file_info: &FileInfo) -> Option<&'static Syntax> {
+        file_info.get_extension().and_then(Syntax::for_extension)
+    }
+
+    /// met à jour la coloration syntaxique jusqu'à la ligne donnée
+    pub fn highlight(&mut self, until_line: usize) {
+        let text = &self.text;
+        self.highlighter.update(
+            self.height(),
+            |line_index| Self::line_text(text, line_index),
+            until_line,
+        );
+    }
+
+    /// annotations de coloration syntaxique d'une ligne
+    pub fn syntax_annotations(&self, line_index: usize) -> &[Annotation] {
+        self.highlighter.annotations(line_index)
+    }
+
+    /// nom du langage du fichier
+    pub fn syntax_name(&self) -> &'static str {
+        self.highlighter.syntax_name()
     }
 
     /// permet de savoir si le buffer est vide
     pub fn is_empty(&self) -> bool {
-        return self.lines.is_empty()
+        self.text.len_bytes() == 0
+    }
+
+    /// permet de savoir si on a des modifications non sauvegardées
+    pub fn is_dirty(&self) -> bool {
+        self.history.is_dirty()
+            || self.file_info.line_ending != self.saved_line_ending
+            || self.file_info.line_endings.is_some() != self.saved_mixed_endings
+    }
+
+    /// passe les fins de ligne de LF à CRLF ou inversement, appliqué à la prochaine sauvegarde ;
+    /// un fichier qui mélange les deux est d'abord uniformisé dans son style principal
+    pub fn toggle_line_ending(&mut self) -> LineEnding {
+        if self.file_info.line_endings.take().is_none() {
+            self.file_info.line_ending = self.file_info.line_ending.toggled();
+        }
+        self.file_info.line_ending
     }
 
-    /// permet de sauvegarder le texte écrit dans le terminal dans un fichier
+    /// permet de sauvegarder le texte écrit dans le terminal dans un fichier,
+    /// avec le format (fins de ligne, BOM...) décrit par `file_info`.
+    /// Le fichier est remplacé d'un seul coup, il n'est jamais laissé à moitié écrit.
     fn save_to_file(&self, file_info: &FileInfo) -> Result<(), Error> {
         if let Some(file_path) = &file_info.get_path() {
-            let mut file = File::create(file_path)?;
-            for line in &self.lines {
-                writeln!(file, "{line}")?;
-            }
+            write_atomically(file_path, self.backup_on_save, |writer| {
+                writer.write_all(file_info.bom_to_string().as_bytes())?;
+                let height = self.height();
+                for line_index in 0..height {
+                    writer.write_all(Self::line_text(&self.text, line_index).as_bytes())?;
+                    if line_index.saturating_add(1) < height || file_info.final_newline {
+                        let line_ending = file_info.line_ending_of(line_index);
+                        writer.write_all(line_ending.as_str().as_bytes())?;
+                    }
+                }
+                Ok(())
+            })?;
         }
         Ok(())
     }
 
     pub fn save_as(&mut self, file_name: &str) -> Result<(), Error> {
-        let file_info = FileInfo::from(file_name);
+        let file_info = self.file_info.renamed(file_name);
         self.save_to_file(&file_info)?;
+        self.remove_swap();
+        self.highlighter = Highlighter::new(Self::syntax_for(&file_info));
         self.file_info = file_info;
-        self.dirty = false;
+        self.mark_saved();
         Ok(())
     }
 
+    fn mark_saved(&mut self) {
+        self.file_info.record_disk_state();
+        self.history.mark_saved();
+        self.saved_line_ending = self.file_info.line_ending;
+        self.saved_mixed_endings = self.file_info.line_endings.is_some();
+    }
+
     pub const fn is_file_loaded(&self) -> bool {
         self.file_info.has_path()
     }
 
     pub fn save(&mut self) -> Result<(), Error> {
         self.save_to_file(&self.file_info)?;
-        self.dirty = false;
+        self.mark_saved();
+        Ok(())
+    }
+
+    /// vérifie si le fichier a été modifié sur le disque par un autre programme
+    pub fn check_external_change(&mut self) -> ExternalChange {
+        self.file_info.check_external_change()
+    }
+
+    /// garde la version du buffer : la version actuelle du disque devient la référence
+    pub fn ignore_external_change(&mut self) {
+        self.file_info.record_disk_state();
+    }
+
+    /// recharge le fichier depuis le disque, les modifications non sauvegardées sont perdues
+    pub fn reload(&mut self) -> Result<(), Error> {
+        let path = self
+            .file_info
+            .get_path()
+            .ok_or_else(|| Error::other("no file name"))?
+            .to_string_lossy()
+            .to_string();
+        let mut reloaded = Self::load(&path)?;
+        reloaded.backup_on_save = self.backup_on_save;
+        reloaded.indentation = self.indentation;
+        reloaded.last_location = self.last_location;
+        reloaded.bump_revision(self);
+        self.remove_swap();
+        *self = reloaded;
+        Ok(())
+    }
+
+    /// révision du texte, change à chaque modification
+    pub const fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// donne une révision plus récente que celle du buffer remplacé, pour que les vues
+    /// qui l'affichaient voient le changement
+    pub fn bump_revision(&mut self, replaced: &Self) {
+        self.revision = replaced.revision.wrapping_add(1);
+        self.changes.clear();
+        self.changes_since = self.revision;
+    }
+
+    /// modifications faites depuis la révision donnée, dans l'ordre ;
+    /// `None` si elles ne sont plus toutes connues (buffer rechargé, trop de modifications)
+    pub fn changes_since(&self, revision: u64) -> Option<Vec<TextChange>> {
+        (revision >= self.changes_since).then(|| {
+            self.changes
+                .iter()
+                .filter(|(change_revision, _)| *change_revision > revision)
+                .map(|(_, change)| *change)
+                .collect()
+        })
+    }
+
+    fn record_change(&mut self, change: TextChange) {
+        if self.changes.len() >= CHANGES_CAPACITY {
+            if let Some((dropped_revision, _)) = self.changes.pop_front() {
+                self.changes_since = dropped_revision;
+            }
+        }
+        self.changes.push_back((self.revision, change));
+    }
+
+    /// chemin du fichier d'échange, seulement pour un buffer lié à un fichier
+    fn swap_path(&self) -> Option<PathBuf> {
+        self.file_info.get_path().map(swapfile::swap_path_for)
+    }
+
+    /// tient à jour le fichier d'échange d'un buffer modifié. L'état courant est toujours
+    /// confié au hook de panique, et écrit sur le disque si `force` ou si la dernière
+    /// écriture date de plus de `SWAP_INTERVAL`. Le fichier disparaît quand le buffer
+    /// redevient identique au fichier.
+    pub fn update_swap(&mut self, force: bool) {
+        let Some(swap_path) = self.swap_path() else {
+            return;
+        };
+        if !self.history.is_dirty() {
+            self.remove_swap();
+            return;
+        }
+        if self.swapped_revision == Some(self.revision) {
+            return;
+        }
+        swapfile::set_pending(&swap_path, Some(&self.text));
+        let is_due = force
+            || self
+                .last_swap_write
+                .is_none_or(|last_write| last_write.elapsed() >= SWAP_INTERVAL);
+        if is_due && swapfile::write(&swap_path, &self.text).is_ok() {
+            self.swapped_revision = Some(self.revision);
+            self.last_swap_write = Some(Instant::now());
+        }
+    }
+
+    /// supprime le fichier d'échange écrit par ce buffer
+    pub fn remove_swap(&mut self) {
+        if let Some(swap_path) = self.swap_path() {
+            if self.swapped_revision.take().is_some() {
+                swapfile::remove(&swap_path);
+            } else {
+                swapfile::set_pending(&swap_path, None);
+            }
+        }
+    }
+
+    /// vrai si un fichier d'échange laissé par une autre session existe pour ce fichier
+    pub fn has_leftover_swap(&self) -> bool {
+        self.swapped_revision.is_none()
+            && self
+                .swap_path()
+                .is_some_and(|swap_path| swapfile::exists(&swap_path))
+    }
+
+    /// remplace le texte par celui du fichier d'échange, en une étape annulable
+    pub fn recover_swap(&mut self) -> Result<(), Error> {
+        let swap_path = self.swap_path().ok_or_else(|| Error::other("no file name"))?;
+        let recovered = swapfile::read(&swap_path)?;
+        let start = Location::default();
+        self.begin_edit(EditKind::Other, start);
+        // tout le texte est remplacé, y compris la fin de la dernière ligne :
+        // un fichier d'échange vide redonne un buffer vide
+        let removed = self.raw_delete(0..self.text.len_bytes());
+        if !removed.is_empty() {
+            self.history.record(Operation::Delete {
+                at: start,
+                text: removed,
+            });
+        }
+        if !recovered.is_empty() {
+            self.history.record(Operation::Insert {
+                at: start,
+                text: recovered.clone(),
+            });
+            self.raw_insert(&recovered, start);
+        }
+        self.end_edit(start);
         Ok(())
     }
 
+    /// résume les différences entre le fichier d'échange et le buffer
+    pub fn diff_swap(&self) -> Result<String, Error> {
+        let swap_path = self.swap_path().ok_or_else(|| Error::other("no file name"))?;
+        Ok(swapfile::diff_summary(&self.text, &swapfile::read(&swap_path)?))
+    }
+
+    /// supprime le fichier d'échange laissé par une autre session
+    pub fn discard_swap(&mut self) {
+        if let Some(swap_path) = self.swap_path() {
+            swapfile::remove(&swap_path);
+        }
+    }
+
     /// retourne le nb de lignes
     pub fn height(&self) -> usize {
-        self.lines.len()
+        self.text.len_lines().saturating_sub(1)
     }
 
+    /// texte brut d'une ligne, sans son `\n`
+    fn line_text(text: &Rope, line_index: usize) -> Cow<'_, str> {
+        let line = text.line(line_index);
+        let len = line.len_chars();
+        let content = if len > 0 && line.char(len.saturating_sub(1)) == '\n' {
+            line.slice(..len.saturating_sub(1))
+        } else {
+            line
+        };
+        content.into()
+    }
 
-    /// permet d'insérer un charactère au niveau du curseur
-    pub fn insert_char(&mut self, character: char, at: Location) {
+    /// retourne la ligne donnée, découpée en graphèmes
+    pub fn line(&self, line_index: usize) -> Option<Rc<Line>> {
+        if line_index >= self.height() {
+            return None;
+        }
+        if let Some(line) = self.line_cache.borrow().get(&line_index) {
+            return Some(Rc::clone(line));
+        }
+        let line = Rc::new(Line::with_tab_width(
+            &Self::line_text(&self.text, line_index),
+            self.indentation.tab_width,
+        ));
+        let mut cache = self.line_cache.borrow_mut();
+        if cache.len() >= LINE_CACHE_CAPACITY {
+            cache.clear();
+        }
+        cache.insert(line_index, Rc::clone(&line));
+        Some(line)
+    }
+
+    pub const fn indentation(&self) -> Indentation {
+        self.indentation
+    }
+
+    /// change les réglages d'indentation ; les lignes sont redécoupées si la largeur
+    /// des tabulations change
+    pub fn set_indentation(&mut self, indentation: Indentation) {
+        if indentation.tab_width != self.indentation.tab_width {
+            self.line_cache.get_mut().clear();
+            self.revision = self.revision.wrapping_add(1);
+            // toutes les lignes changent de largeur, pas seulement celles d'une modification
+            self.changes.clear();
+            self.changes_since = self.revision;
+        }
+        self.indentation = indentation;
+    }
+
+    /// nombre de graphèmes de la ligne donnée, 0 si elle n'existe pas
+    pub fn grapheme_count(&self, line_index: usize) -> usize {
+        self.line(line_index).map_or(0, |line| line.grapheme_count())
+    }
+
+    /// position visée par un saut depuis `from`, ramenée dans le texte ;
+    /// sans colonne, le début de la ligne
+    pub fn goto_location(&self, target: GotoTarget, from: Location) -> Location {
+        let line_index = match target.line {
+            LineTarget::Absolute(number) => number.saturating_sub(1),
+            LineTarget::Forward(count) => from.line_index.saturating_add(count),
+            LineTarget::Backward(count) => from.line_index.saturating_sub(count),
+        }
+        .min(self.height().saturating_sub(1));
+        let grapheme_index = target.column.map_or(0, |column| {
+            column
+                .saturating_sub(1)
+                .min(self.grapheme_count(line_index))
+        });
+        Location {
+            line_index,
+            grapheme_index,
+        }
+    }
+
+    /// vrai si la ligne contient une correspondance, sans la découper en graphèmes
+    fn line_matches(&self, pattern: &Pattern, line_index: usize) -> bool {
+        pattern.is_match(&Self::line_text(&self.text, line_index))
+    }
+
+    /// index d'octet dans la rope correspondant à une position
+    fn location_to_byte_idx(&self, location: Location) -> usize {
+        if location.line_index >= self.height() {
+            return self.text.len_bytes();
+        }
+        let line_start = self.text.line_to_byte(location.line_index);
+        let offset = self
+            .line(location.line_index)
+            .map_or(0, |line| line.grapheme_idx_to_byte_idx(location.grapheme_index));
+        line_start.saturating_add(offset)
+    }
+
+    /// position correspondant à un index d'octet dans la rope
+    fn byte_idx_to_location(&self, byte_idx: usize) -> Location {
+        let byte_idx = byte_idx.min(self.text.len_bytes());
+        let line_index = self.text.byte_to_line(byte_idx);
+        let offset = byte_idx.saturating_sub(self.text.line_to_byte(line_index));
+        Location {
+            line_index,
+            grapheme_index: self
+                .line(line_index)
+                .map_or(0, |line| line.grapheme_idx_at_byte(offset)),
+        }
+    }
+
+    /// cherche la prochaine occurrence de `pattern` à partir de `from` (inclus), en revenant au début si besoin
+    pub fn search_forward(&self, pattern: &Pattern, from: Location) -> Option<Location> {
+        if pattern.is_empty() {
+            return None;
+        }
+        let mut is_first = true;
+        for line_index in (0..self.height())
+            .cycle()
+            .skip(from.line_index)
+            .take(self.height().saturating_add(1))
+        {
+            let from_grapheme_index = if is_first {
+                is_first = false;
+                from.grapheme_index
+            } else {
+                0
+            };
+            if !self.line_matches(pattern, line_index) {
+                continue;
+            }
+            let Some(line) = self.line(line_index) else {
+                continue;
+            };
+            if let Some(grapheme_index) = line.search_forward(pattern, from_grapheme_index) {
+                return Some(Location {
+                    line_index,
+                    grapheme_index,
+                });
+            }
+        }
+        None
+    }
+
+    /// cherche l'occurrence de `pattern` précédant `from` (exclu), en repartant de la fin si besoin
+    pub fn search_backward(&self, pattern: &Pattern, from: Location) -> Option<Location> {
+        if pattern.is_empty() {
+            return None;
+        }
+        let mut is_first = true;
+        for line_index in (0..self.height())
+            .rev()
+            .cycle()
+            .skip(
+                self.height()
+                    .saturating_sub(from.line_index)
+                    .saturating_sub(1),
+            )
+            .take(self.height().saturating_add(1))
+        {
+            let was_first = is_first;
+            is_first = false;
+            if !self.line_matches(pattern, line_index) {
+                continue;
+            }
+            let Some(line) = self.line(line_index) else {
+                continue;
+            };
+            let from_grapheme_index = if was_first {
+                from.grapheme_index
+            } else {
+                line.grapheme_count()
+            };
+            if let Some(grapheme_index) = line.search_backward(pattern, from_grapheme_index) {
+                return Some(Location {
+                    line_index,
+                    grapheme_index,
+                });
+            }
+        }
+        None
+    }
+
+    /// cherche la première correspondance qui commence entre `from` (inclus) et `until` (exclu),
+    /// sans revenir au début. Retourne le début et la fin de la correspondance.
+    pub fn find_match(
+        &self,
+        pattern: &Pattern,
+        from: Location,
+        until: Location,
+    ) -> Option<(Location, Location)> {
+        if pattern.is_empty() {
+            return None;
+        }
+        for line_index in from.line_index..self.height() {
+            if !self.line_matches(pattern, line_index) {
+                continue;
+            }
+            let Some(line) = self.line(line_index) else {
+                continue;
+            };
+            let from_grapheme_index = if line_index == from.line_index {
+                from.grapheme_index
+            } else {
+                0
+            };
+            if let Some(range) = line.find_match(pattern, from_grapheme_index) {
+                let start = Location {
+                    line_index,
+                    grapheme_index: range.start,
+                };
+                if start >= until {
+                    return None;
+                }
+                let end = Location {
+                    line_index,
+                    grapheme_index: range.end,
+                };
+                return Some((start, end));
+            }
+        }
+        None
+    }
+
+    /// calcule le texte qui remplace la correspondance commençant à `at`
+    pub fn expand_match(&self, pattern: &Pattern, at: Location, replacement: &str) -> String {
+        self.line(at.line_index)
+            .map(|line| line.expand_match(pattern, at.grapheme_index, replacement))
+            .unwrap_or_default()
+    }
+
+    /// commence une étape d'édition, le curseur est celui à restaurer lors d'un undo
+    pub fn begin_edit(&mut self, kind: EditKind, caret: Location) {
+        self.history.begin(kind, caret);
+    }
+
+    /// termine l'étape d'édition, le curseur est celui à restaurer lors d'un redo
+    pub fn end_edit(&mut self, caret: Location) {
+        self.history.commit(caret);
+    }
+
+    /// annule la dernière étape d'édition et retourne la position du curseur à restaurer
+    pub fn undo(&mut self) -> Option<Location> {
+        let (operations, caret) = self.history.undo()?;
+        for operation in &operations {
+            self.apply(operation);
+        }
+        Some(caret)
+    }
+
+    /// rétablit la dernière étape annulée et retourne la position du curseur à restaurer
+    pub fn redo(&mut self) -> Option<Location> {
+        let (operations, caret) = self.history.redo()?;
+        for operation in &operations {
+            self.apply(operation);
+        }
+        Some(caret)
+    }
+
+    /// applique une opération sans l'enregistrer dans l'historique
+    fn apply(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Insert { at, text } => {
+                self.raw_insert(text, *at);
+            }
+            Operation::Delete { at, text } => {
+                let start = self.location_to_byte_idx(*at);
+                self.raw_delete(start..start.saturating_add(text.len()));
+            }
+        }
+    }
+
+    /// permet d'insérer du texte (éventuellement sur plusieurs lignes), retourne la position après le texte inséré
+    pub fn insert_text(&mut self, text: &str, at: Location) -> Location {
+        if at.line_index > self.height() || text.is_empty() {
+            return at;
+        }
+        if at.line_index == self.height() {
+            self.materialize_last_line();
+        }
+        self.history.record(Operation::Insert {
+            at,
+            text: text.to_string(),
+        });
+        self.raw_insert(text, at)
+    }
+
+    /// retourne le texte compris entre deux positions
+    pub fn text_in_range(&self, from: Location, to: Location) -> String {
+        let start = self.location_to_byte_idx(from);
+        let end = self.location_to_byte_idx(to).max(start);
+        self.text.byte_slice(start..end).to_string()
+    }
+
+    /// permet de supprimer le texte entre deux positions, retourne le texte supprimé
+    pub fn delete_range(&mut self, from: Location, to: Location) -> String {
+        // la ligne après la dernière n'existe pas : on s'arrête à la fin de la dernière ligne
+        let to = match self.height().checked_sub(1) {
+            Some(last_index) if to.line_index > last_index => Location {
+                line_index: last_index,
+                grapheme_index: self.grapheme_count(last_index),
+            },
+            _ => to,
+        };
+        if to <= from {
+            return String::new();
+        }
+        let removed =
+            self.raw_delete(self.location_to_byte_idx(from)..self.location_to_byte_idx(to));
+        if !removed.is_empty() {
+            self.history.record(Operation::Delete {
+                at: from,
+                text: removed.clone(),
+            });
+        }
+        removed
+    }
+
+    /// crée la ligne située juste après la dernière ligne du buffer
+    fn materialize_last_line(&mut self) {
+        let at = self
+            .height()
+            .checked_sub(1)
+            .map_or_else(Location::default, |last_index| Location {
+                line_index: last_index,
+                grapheme_index: self.grapheme_count(last_index),
+            });
+        // enregistrée aussi pour un buffer vide, sinon l'annulation laisserait la ligne créée
+        self.history.record(Operation::Insert {
+            at,
+            text: "\n".to_string(),
+        });
+        self.raw_insert("\n", at);
+    }
+
+    fn raw_insert(&mut self, text: &str, at: Location) -> Location {
         if at.line_index > self.height() {
+            return at;
+        }
+        let start = self.location_to_byte_idx(at);
+        let start_location = self.byte_idx_to_location(start);
+        let inserted_lines = text.matches('\n').count();
+        let cached = (inserted_lines == 0)
+            .then(|| self.take_cached_line(at.line_index))
+            .flatten();
+        self.text.insert(self.text.byte_to_char(start), text);
+        self.lines_changed(at.line_index, 0, inserted_lines);
+        self.patch_cached_line(at.line_index, cached, |line, line_start| {
+            let offset = start.saturating_sub(line_start);
+            line.replace_bytes(offset..offset, text);
+        });
+        let end = self.byte_idx_to_location(start.saturating_add(text.len()));
+        self.record_change(TextChange {
+            start: start_location,
+            old_end: start_location,
+            new_end: end,
+        });
+        end
+    }
+
+    fn raw_delete(&mut self, byte_range: Range<usize>) -> String {
+        let end = byte_range.end.min(self.text.len_bytes());
+        if byte_range.start >= end {
+            return String::new();
+        }
+        let line_index = self.text.byte_to_line(byte_range.start);
+        let start_location = self.byte_idx_to_location(byte_range.start);
+        let old_end = self.byte_idx_to_location(end);
+        let chars = self.text.byte_to_char(byte_range.start)..self.text.byte_to_char(end);
+        let removed = self.text.slice(chars.clone()).to_string();
+        let removed_lines = removed.matches('\n').count();
+        let cached = (removed_lines == 0)
+            .then(|| self.take_cached_line(line_index))
+            .flatten();
+        self.text.remove(chars);
+        self.lines_changed(line_index, removed_lines, 0);
+        self.patch_cached_line(line_index, cached, |line, line_start| {
+            line.replace_bytes(
+                byte_range.start.saturating_sub(line_start)..end.saturating_sub(line_start),
+                "",
+            );
+        });
+        self.record_change(TextChange {
+            start: start_location,
+            old_end,
+            new_end: start_location,
+        });
+        removed
+    }
+
+    /// retire du cache une ligne qu'une modification sans retour à la ligne va changer,
+    /// avec l'octet où elle commence, pour la mettre à jour plutôt que la redécouper
+    fn take_cached_line(&mut self, line_index: usize) -> Option<(Rc<Line>, usize)> {
+        let line = self.line_cache.get_mut().remove(&line_index)?;
+        Some((line, self.text.line_to_byte(line_index)))
+    }
+
+    /// remet en cache une ligne retirée par `take_cached_line`, mise à jour sur place ;
+    /// si elle est encore utilisée ailleurs, elle sera simplement redécoupée
+    fn patch_cached_line(
+        &mut self,
+        line_index: usize,
+        cached: Option<(Rc<Line>, usize)>,
+        edit: impl FnOnce(&mut Line, usize),
+    ) {
+        let Some((mut line, line_start)) = cached else {
             return;
+        };
+        if let Some(patched) = Rc::get_mut(&mut line) {
+            edit(patched, line_start);
+            self.line_cache.get_mut().insert(line_index, line);
         }
-        if at.line_index == self.height() {
-            self.lines.push(Line::from(&character.to_string()));
-            self.dirty = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_index) {
-            line.insert_char(character, at.grapheme_index);
-            self.dirty = true;
+    }
+
+    /// invalide les lignes modifiées : la ligne `line_index` a changé, `removed` lignes
+    /// qui la suivaient ont disparu et `inserted` nouvelles lignes ont été ajoutées après elle
+    fn lines_changed(&mut self, line_index: usize, removed: usize, inserted: usize) {
+        self.revision = self.revision.wrapping_add(1);
+        self.file_info.lines_changed(line_index, removed, inserted);
+        let cache = self.line_cache.get_mut();
+        if removed == inserted {
+            cache.remove(&line_index);
+        } else {
+            // les lignes suivantes changent d'index
+            cache.retain(|cached_index, _| *cached_index < line_index);
         }
+        self.highlighter.lines_changed(line_index, removed, inserted);
     }
 
-    /// permet d'insérer une ligne
-    pub fn insert_newline(&mut self, at: Location) {
-        if at.line_index == self.height() {
-            self.lines.push(Line::default());
-            self.dirty = true;
-        } else if let Some(line) = self.lines.get_mut(at.line_index) {
-            let new = line.split(at.grapheme_index);
-            self.lines.insert(at.line_index.saturating_add(1), new);
-            self.dirty = true;
+    /// permet d'insérer un charactère au niveau du curseur
+    pub fn insert_char(&mut self, character: char, at: Location) {
+        self.insert_text(&character.to_string(), at);
+    }
+
+    /// permet d'insérer une ligne, en l'indentant si l'indentation automatique est active.
+    /// Retourne la position du curseur sur la nouvelle ligne.
+    pub fn insert_newline(&mut self, at: Location) -> Location {
+        let next_line = Location {
+            line_index: at.line_index.saturating_add(1),
+            grapheme_index: 0,
+        };
+        if at.line_index >= self.height() {
+            self.materialize_last_line();
+            return next_line;
+        }
+        self.highlight(at.line_index.saturating_add(1));
+        let line_text = Self::line_text(&self.text, at.line_index);
+        let split_byte_idx = self
+            .line(at.line_index)
+            .map_or(0, |line| line.grapheme_idx_to_byte_idx(at.grapheme_index));
+        let (before, after) = line_text.split_at(split_byte_idx.min(line_text.len()));
+        let before = self.without_trailing_comments(at.line_index, before);
+        let indent_after = self.highlighter.syntax().map_or(&[][..], |syntax| syntax.indent_after);
+        let (text, indent_len) = self.indentation.newline_text(before, after, indent_after);
+        self.insert_text(&text, at);
+        Location {
+            grapheme_index: indent_len,
+            ..next_line
+        }
+    }
+
+    /// début de ligne sans les commentaires qui le terminent : un `:` ou un `{` dans un
+    /// commentaire n'indente pas la ligne suivante
+    fn without_trailing_comments<'a>(&self, line_index: usize, before: &'a str) -> &'a str {
+        let mut code = before;
+        while let Some(comment) = self.syntax_annotations(line_index).iter().find(|annotation| {
+            annotation.kind == AnnotationType::Comment
+                && annotation.start_byte_idx < code.len()
+                && annotation.end_byte_idx >= code.trim_end().len()
+        }) {
+            code = code.get(..comment.start_byte_idx).unwrap_or_default();
         }
+        code
     }
 
     /// permet de supprimer un charactère
     pub fn delete(&mut self, at: Location) {
-        if let Some(line) = self.lines.get(at.line_index) {
-            if at.grapheme_index >= line.grapheme_count()
-                && self.height() > at.line_index.saturating_add(1)
-            {
-                let next_line = self.lines.remove(at.line_index.saturating_add(1));
-                // clippy::indexing_slicing: We checked for existence of this line in the surrounding if statment
-                #[allow(clippy::indexing_slicing)]
-                self.lines[at.line_index].append(&next_line);
-                self.dirty = true;
-            } else if at.grapheme_index < line.grapheme_count() {
-                // clippy::indexing_slicing: We checked for existence of this line in the surrounding if statment
-                #[allow(clippy::indexing_slicing)]
-                self.lines[at.line_index].delete(at.grapheme_index);
-                self.dirty = true;
+        if let Some(line) = self.line(at.line_index) {
+            let to = if at.grapheme_index >= line.grapheme_count() {
+                Location {
+                    line_index: at.line_index.saturating_add(1),
+                    grapheme_index: 0,
+                }
+            } else {
+                Location {
+                    line_index: at.line_index,
+                    grapheme_index: at.grapheme_index.saturating_add(1),
+                }
+            };
+            self.delete_range(at, to);
+        }
+    }
+
+}
+
+/// texte remplacé entre `start` et `old_end` par celui qui va de `start` à `new_end`
+#[derive(Copy, Clone)]
+pub struct TextChange {
+    start: Location,
+    old_end: Location,
+    new_end: Location,
+}
+
+impl TextChange {
+    /// position qui désigne le même texte après la modification ;
+    /// une position dans le texte supprimé va au début de la modification
+    pub fn adjust(&self, location: Location) -> Location {
+        if location < self.start {
+            location
+        } else if location < self.old_end {
+            self.start
+        } else if location.line_index == self.old_end.line_index {
+            Location {
+                line_index: self.new_end.line_index,
+                grapheme_index: location
+                    .grapheme_index
+                    .saturating_sub(self.old_end.grapheme_index)
+                    .saturating_add(self.new_end.grapheme_index),
+            }
+        } else {
+            Location {
+                line_index: location
+                    .line_index
+                    .saturating_sub(self.old_end.line_index)
+                    .saturating_add(self.new_end.line_index),
+                ..location
             }
         }
     }
 
-}
\ No newline at end of file
+    /// ligne qui a le même contenu après la modification ; `None` pour une ligne qu'elle touche
+    pub const fn moved_line(&self, line_index: usize) -> Option<usize> {
+        if line_index < self.start.line_index {
+            Some(line_index)
+        } else if line_index <= self.old_end.line_index {
+            None
+        } else {
+            Some(
+                line_index
+                    .saturating_sub(self.old_end.line_index)
+                    .saturating_add(self.new_end.line_index),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+    use ropey::Rope;
+    use super::{Buffer, EditKind, Highlighter, Line, LineEnding, Location, Syntax};
+
+    fn buffer(text: &str) -> Buffer {
+        Buffer {
+            text: Rope::from_str(text),
+            ..Buffer::default()
+        }
+    }
+
+    fn buffer_with_syntax(text: &str, extension: &str) -> Buffer {
+        Buffer {
+            highlighter: Highlighter::new(Syntax::for_extension(extension)),
+            ..buffer(text)
+        }
+    }
+
+    const fn at(line_index: usize, grapheme_index: usize) -> Location {
+        Location {
+            line_index,
+            grapheme_index,
+        }
+    }
+
+    fn lines(buffer: &Buffer) -> Vec<String> {
+        (0..buffer.height())
+            .filter_map(|line_index| buffer.line(line_index))
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    /// chaque ligne en cache est identique à une ligne découpée à partir du texte
+    fn assert_cache_matches_text(buffer: &Buffer) {
+        for (line_index, line) in buffer.line_cache.borrow().iter() {
+            let text = Buffer::line_text(&buffer.text, *line_index);
+            let fresh = Line::from(&text);
+            assert_eq!(line.to_string(), text);
+            assert_eq!(line.grapheme_count(), fresh.grapheme_count());
+            assert_eq!(line.width(), fresh.width());
+        }
+    }
+
+    #[test]
+    fn edit_within_a_line_patches_the_cached_line() {
+        let mut buffer = buffer("héllo\nwörld\n");
+        let before = buffer.line(1).unwrap();
+        drop(before);
+        buffer.insert_text("日本", at(1, 2));
+        let patched = buffer.line(1).unwrap();
+        assert_eq!(patched.to_string(), "wö日本rld");
+        assert_eq!(patched.width(), 9);
+        drop(patched);
+        buffer.delete(at(1, 1));
+        assert_eq!(lines(&buffer), ["héllo", "w日本rld"]);
+        assert_cache_matches_text(&buffer);
+    }
+
+    #[test]
+    fn shared_cached_line_is_not_modified() {
+        let mut buffer = buffer("abc\n");
+        let held = buffer.line(0).unwrap();
+        buffer.insert_text("x", at(0, 1));
+        assert_eq!(held.to_string(), "abc");
+        assert_eq!(buffer.line(0).unwrap().to_string(), "axbc");
+        assert!(!Rc::ptr_eq(&held, &buffer.line(0).unwrap()));
+    }
+
+    #[test]
+    fn cache_follows_line_changes() {
+        let mut buffer = buffer("one\ntwo\nthree\n");
+        assert_eq!(lines(&buffer), ["one", "two", "three"]);
+        buffer.insert_newline(at(0, 1));
+        assert_eq!(lines(&buffer), ["o", "ne", "two", "three"]);
+        assert_cache_matches_text(&buffer);
+        buffer.delete(at(1, 2));
+        assert_eq!(lines(&buffer), ["o", "netwo", "three"]);
+        assert_cache_matches_text(&buffer);
+    }
+
+    #[test]
+    fn insert_and_delete_across_line_boundaries() {
+        let mut buffer = buffer("first\nsecond\nthird\n");
+        lines(&buffer);
+        let end = buffer.insert_text("A\nB\nC", at(1, 3));
+        assert_eq!(end, at(3, 1));
+        assert_eq!(lines(&buffer), ["first", "secA", "B", "Cond", "third"]);
+        assert_cache_matches_text(&buffer);
+        let removed = buffer.delete_range(at(0, 2), at(3, 1));
+        assert_eq!(removed, "rst\nsecA\nB\nC");
+        assert_eq!(lines(&buffer), ["fiond", "third"]);
+        assert_cache_matches_text(&buffer);
+        buffer.insert_text("\nmiddle", at(0, 5));
+        buffer.delete_range(at(1, 0), at(2, 0));
+        assert_eq!(lines(&buffer), ["fiond", "third"]);
+        // joindre l'avant-dernière ligne à la dernière
+        buffer.delete(at(0, 5));
+        assert_eq!(lines(&buffer), ["fiondthird"]);
+        assert_cache_matches_text(&buffer);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_text_and_cache() {
+        let mut buffer = buffer("abc\ndef\n");
+        lines(&buffer);
+        buffer.begin_edit(EditKind::Other, at(0, 1));
+        buffer.insert_text("X\nY", at(0, 1));
+        buffer.delete(at(2, 0));
+        buffer.end_edit(at(1, 1));
+        assert_eq!(lines(&buffer), ["aX", "Ybc", "ef"]);
+        assert_eq!(buffer.undo(), Some(at(0, 1)));
+        assert_eq!(lines(&buffer), ["abc", "def"]);
+        assert_cache_matches_text(&buffer);
+        assert_eq!(buffer.redo(), Some(at(1, 1)));
+        assert_eq!(lines(&buffer), ["aX", "Ybc", "ef"]);
+        assert_cache_matches_text(&buffer);
+    }
+
+    #[test]
+    fn undo_in_empty_buffer_removes_created_line() {
+        let mut buffer = buffer("");
+        buffer.begin_edit(EditKind::Typing, at(0, 0));
+        buffer.insert_char('a', at(0, 0));
+        buffer.end_edit(at(0, 1));
+        assert_eq!(lines(&buffer), ["a"]);
+        assert!(buffer.is_dirty());
+        assert_eq!(buffer.undo(), Some(at(0, 0)));
+        assert!(buffer.is_empty());
+        assert!(!buffer.is_dirty());
+
+        buffer.begin_edit(EditKind::Other, at(0, 0));
+        assert_eq!(buffer.insert_newline(at(0, 0)), at(1, 0));
+        buffer.end_edit(at(1, 0));
+        assert_eq!(lines(&buffer), [""]);
+        assert_eq!(buffer.undo(), Some(at(0, 0)));
+        assert!(buffer.is_empty());
+        assert!(!buffer.is_dirty());
+        assert_cache_matches_text(&buffer);
+    }
+
+    #[test]
+    fn changes_move_locations_after_them() {
+        let mut buffer = buffer("one\ntwo\nthree\n");
+        let seen = buffer.revision();
+        buffer.insert_text("new\n", at(0, 0));
+        buffer.insert_text("ab\ncd", at(2, 1));
+        buffer.delete_range(at(3, 0), at(3, 3));
+        let changes = buffer.changes_since(seen).unwrap();
+        let follow = |location| changes.iter().fold(location, |location, change| change.adjust(location));
+        // la ligne `three` a été poussée de deux lignes, `two` coupée au milieu
+        assert_eq!(follow(at(2, 2)), at(4, 2));
+        assert_eq!(follow(at(1, 2)), at(3, 0));
+        assert_eq!(follow(at(0, 1)), at(1, 1));
+        assert_eq!(lines(&buffer), ["new", "one", "tab", "o", "three"]);
+    }
+
+    #[test]
+    fn changes_tell_which_lines_kept_their_text() {
+        let mut buffer = buffer("a\nb\nc\nd\n");
+        let seen = buffer.revision();
+        buffer.insert_text("x\ny", at(1, 1));
+        buffer.delete_range(at(0, 0), at(0, 1));
+        let changes = buffer.changes_since(seen).unwrap();
+        let follow = |line_index| {
+            changes
+                .iter()
+                .try_fold(line_index, |line_index, change| change.moved_line(line_index))
+        };
+        assert_eq!(lines(&buffer), ["", "bx", "y", "c", "d"]);
+        assert_eq!(follow(0), None);
+        assert_eq!(follow(1), None);
+        assert_eq!(follow(2), Some(3));
+        assert_eq!(follow(3), Some(4));
+    }
+
+    #[test]
+    fn newline_ignores_trailing_comments() {
+        let mut buffer = buffer_with_syntax("x = 1  # a dict {\nif x:  # note\n", "py");
+        assert_eq!(buffer.insert_newline(at(0, 17)), at(1, 0));
+        assert_eq!(buffer.insert_newline(at(2, 13)), at(3, 1));
+        assert_eq!(lines(&buffer), ["x = 1  # a dict {", "", "if x:  # note", "\t"]);
+    }
+
+    #[test]
+    fn newline_splits_brackets_only_after_indenting_chars() {
+        let mut markdown = buffer_with_syntax("see (\n", "md");
+        markdown.insert_text(")", at(0, 5));
+        assert_eq!(markdown.insert_newline(at(0, 5)), at(1, 0));
+        assert_eq!(lines(&markdown), ["see (", ")"]);
+        let mut rust = buffer_with_syntax("fn f() {}\n", "rs");
+        assert_eq!(rust.insert_newline(at(0, 8)), at(1, 1));
+        assert_eq!(lines(&rust), ["fn f() {", "\t", "}"]);
+    }
+
+    #[test]
+    fn mixed_line_endings_are_kept_on_save() {
+        let path = std::env::temp_dir().join(format!("hecto-mixed-{}.txt", std::process::id()));
+        let path_name = path.to_string_lossy().to_string();
+        std::fs::write(&path, "a\r\nb\nc\r\nd").unwrap();
+        let mut buffer = Buffer::load(&path_name).unwrap();
+        assert_eq!(buffer.file_info.format_to_string(), "Mixed noeol");
+        buffer.insert_newline(at(1, 1));
+        buffer.delete(at(3, 0));
+        buffer.insert_text("x\ny", at(0, 1));
+        buffer.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ax\r\ny\r\nb\n\n\r\nd");
+        assert!(!buffer.is_dirty());
+        // Ctrl-T uniformise dans le style de la première ligne
+        assert_eq!(buffer.toggle_line_ending(), LineEnding::Crlf);
+        assert!(buffer.is_dirty());
+        buffer.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ax\r\ny\r\nb\r\n\r\n\r\nd");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+use std::{
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Error},
+    path::{Path, PathBuf},
+    process,
+};
+
+/// écrit un fichier sans jamais laisser de version à moitié écrite : le contenu part dans un
+/// fichier temporaire du même dossier, synchronisé sur le disque, qui reprend les permissions
+/// de l'original puis le remplace d'un seul coup. Avec `backup`, l'ancienne version est
+/// d'abord copiée dans `fichier~`.
+pub fn write_atomically(
+    path: &Path,
+    backup: bool,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    // un lien symbolique reste un lien : on remplace le fichier vers lequel il pointe
+    let target = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let original = fs::metadata(&target).ok();
+    let temp_path = temp_path_for(&target);
+    let result = write_temp_file(&temp_path, original.as_ref(), write).and_then(|()| {
+        if backup && original.is_some() {
+            let backup_path = suffixed(&target, "~");
+            fs::copy(&target, &backup_path).map_err(|err| {
+                with_context(&err, &format!("could not back up to {}", backup_path.display()))
+            })?;
+        }
+        fs::rename(&temp_path, &target).map_err(|err| {
+            with_context(&err, &format!("could not replace {}", target.display()))
+        })
+    });
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+        return result;
+    }
+    sync_parent_dir(&target);
+    Ok(())
+}
+
+fn write_temp_file(
+    temp_path: &Path,
+    original: Option<&fs::Metadata>,
+    write: impl FnOnce(&mut BufWriter<File>) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(temp_path)
+        .map_err(|err| {
+            with_context(
+                &err,
+                &format!("could not create temporary file {}", temp_path.display()),
+            )
+        })?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer).map_err(|err| with_context(&err, "could not write file"))?;
+    let file = writer.into_inner().map_err(|err| with_context(err.error(), "could not write file"))?;
+    if let Some(metadata) = original {
+        file.set_permissions(metadata.permissions())
+            .map_err(|err| with_context(&err, "could not keep file permissions"))?;
+        preserve_owner(temp_path, metadata);
+    }
+    file.sync_all()
+        .map_err(|err| with_context(&err, "could not flush file to disk"))
+}
+
+/// garde le propriétaire et le groupe de l'original quand on en a le droit
+#[cfg(unix)]
+fn preserve_owner(temp_path: &Path, metadata: &fs::Metadata) {
+    use std::os::unix::fs::{chown, MetadataExt};
+    let _ = chown(temp_path, Some(metadata.uid()), Some(metadata.gid()));
+}
+
+#[cfg(not(unix))]
+fn preserve_owner(_temp_path: &Path, _metadata: &fs::Metadata) {}
+
+/// synchronise le dossier pour que le renommage survive à un crash
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if let Ok(dir) = File::open(dir) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) {}
+
+/// `.nom.hecto-<pid>.tmp` dans le dossier du fichier
+fn temp_path_for(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".hecto-{}.tmp", process::id()));
+    path.with_file_name(name)
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// ajoute ce qu'on essayait de faire au message de l'erreur
+fn with_context(err: &Error, context: &str) -> Error {
+    Error::new(err.kind(), format!("{context}: {err}"))
+}
@@ -0,0 +1,139 @@
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Error, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use ropey::Rope;
+
+/// première ligne d'un fichier d'échange, pour ne jamais lire ou écraser celui d'un autre outil
+const HEADER: &str = "HECTO SWAP\n";
+
+/// dernier état connu de chaque buffer modifié, par chemin de fichier d'échange.
+/// Cloner une rope ne coûte rien, le hook de panique peut donc toujours écrire l'état le plus récent.
+static PENDING: Mutex<BTreeMap<PathBuf, Rope>> = Mutex::new(BTreeMap::new());
+
+/// `.nom.hecto.swp` dans le dossier du fichier
+pub fn swap_path_for(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(".hecto.swp");
+    path.with_file_name(name)
+}
+
+/// fichier dont `swap_path` est le fichier d'échange
+fn source_path_for(swap_path: &Path) -> Option<PathBuf> {
+    let name = swap_path.file_name()?.to_str()?;
+    let source_name = name.strip_prefix('.')?.strip_suffix(".hecto.swp")?;
+    Some(swap_path.with_file_name(source_name))
+}
+
+/// écrit le texte du buffer dans le fichier d'échange, qui prend les permissions du fichier
+/// avant de recevoir le texte : il ne doit pas être lisible par plus de monde que lui
+pub fn write(swap_path: &Path, text: &Rope) -> Result<(), Error> {
+    let source = source_path_for(swap_path).and_then(|source| fs::metadata(source).ok());
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // jamais plus ouvert que 0600 avant d'avoir reçu ses permissions
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options.open(swap_path)?;
+    restrict_permissions(&file, source.as_ref())?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(HEADER.as_bytes())?;
+    text.write_to(&mut writer)?;
+    writer.into_inner().map_err(|err| Error::new(err.error().kind(), err.to_string()))?.sync_all()
+}
+
+/// donne au fichier d'échange les permissions du fichier, ou 0600 sans fichier sur le disque
+#[cfg(unix)]
+fn restrict_permissions(file: &File, source: Option<&fs::Metadata>) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+    let permissions = source.map_or_else(
+        || fs::Permissions::from_mode(0o600),
+        fs::Metadata::permissions,
+    );
+    file.set_permissions(permissions)
+}
+
+#[cfg(not(unix))]
+fn restrict_permissions(file: &File, source: Option<&fs::Metadata>) -> Result<(), Error> {
+    match source {
+        Some(metadata) => file.set_permissions(metadata.permissions()),
+        None => Ok(()),
+    }
+}
+
+/// lit le texte sauvegardé dans un fichier d'échange laissé par une session précédente
+pub fn read(swap_path: &Path) -> Result<String, Error> {
+    let contents = fs::read_to_string(swap_path)?;
+    contents
+        .strip_prefix(HEADER)
+        .map(str::to_string)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "not a hecto swap file"))
+}
+
+/// vrai si un fichier d'échange de hecto existe à cet endroit
+pub fn exists(swap_path: &Path) -> bool {
+    read(swap_path).is_ok()
+}
+
+pub fn remove(swap_path: &Path) {
+    let _ = fs::remove_file(swap_path);
+    set_pending(swap_path, None);
+}
+
+/// mémorise (ou oublie) l'état à écrire si l'éditeur plante
+pub fn set_pending(swap_path: &Path, text: Option<&Rope>) {
+    if let Ok(mut pending) = PENDING.lock() {
+        match text {
+            Some(text) => {
+                pending.insert(swap_path.to_path_buf(), text.clone());
+            }
+            None => {
+                pending.remove(swap_path);
+            }
+        }
+    }
+}
+
+/// écrit tous les états en attente, appelé depuis le hook de panique
+pub fn flush_pending() {
+    // try_lock : la panique a pu survenir pendant que le verrou était pris
+    if let Ok(pending) = PENDING.try_lock() {
+        for (swap_path, text) in pending.iter() {
+            let _ = write(swap_path, text);
+        }
+    }
+}
+
+/// résume les différences entre le buffer et le fichier d'échange
+pub fn diff_summary(current: &Rope, swap_text: &str) -> String {
+    let current_text = current.to_string();
+    let current_lines: Vec<&str> = current_text.split_inclusive('\n').collect();
+    let swap_lines: Vec<&str> = swap_text.split_inclusive('\n').collect();
+    let prefix = current_lines
+        .iter()
+        .zip(&swap_lines)
+        .take_while(|(current, swapped)| current == swapped)
+        .count();
+    if prefix == current_lines.len() && prefix == swap_lines.len() {
+        return String::from("Swap file matches the file.");
+    }
+    let suffix = current_lines
+        .iter()
+        .rev()
+        .zip(swap_lines.iter().rev())
+        .take(current_lines.len().min(swap_lines.len()).saturating_sub(prefix))
+        .take_while(|(current, swapped)| current == swapped)
+        .count();
+    let changed_in_file = current_lines.len().saturating_sub(prefix).saturating_sub(suffix);
+    let changed_in_swap = swap_lines.len().saturating_sub(prefix).saturating_sub(suffix);
+    format!(
+        "Swap differs from line {}: {changed_in_file} line(s) in file, {changed_in_swap} in swap.",
+        prefix.saturating_add(1)
+    )
+}
@@ -1,22 +1,242 @@
 use std::{
     fmt::{self, Display},
+    fs,
+    hash::{DefaultHasher, Hash, Hasher},
+    io::{Error, ErrorKind},
     path::{Path,PathBuf},
+    time::SystemTime,
 };
 
-/// structure pour avoir des informations par rapport à un fichier
-#[derive(Default, Debug)]
+const BOM: char = '\u{feff}';
+
+/// style de fin de ligne d'un fichier
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::Crlf => "\r\n",
+        }
+    }
+
+    /// retourne l'autre style de fin de ligne
+    pub const fn toggled(self) -> Self {
+        match self {
+            Self::Lf => Self::Crlf,
+            Self::Crlf => Self::Lf,
+        }
+    }
+}
+
+impl Display for LineEnding {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lf => write!(formatter, "LF"),
+            Self::Crlf => write!(formatter, "CRLF"),
+        }
+    }
+}
+
+/// état du fichier sur le disque lors du dernier chargement ou de la dernière sauvegarde
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DiskState {
+    modified: Option<SystemTime>,
+    len: u64,
+    hash: u64,
+}
+
+impl DiskState {
+    /// lit l'état actuel du fichier
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let contents = fs::read(path)?;
+        let metadata = fs::metadata(path)?;
+        Ok(Self::from(&metadata, &contents))
+    }
+
+    pub fn from(metadata: &fs::Metadata, contents: &[u8]) -> Self {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        Self {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hasher.finish(),
+        }
+    }
+}
+
+/// modification du fichier faite par un autre programme
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExternalChange {
+    None,
+    Modified,
+    Deleted,
+}
+
+/// structure pour avoir des informations par rapport à un fichier,
+/// y compris son format pour pouvoir le réécrire à l'identique
+#[derive(Debug)]
 pub struct FileInfo {
     path: Option<PathBuf>,
+    pub line_ending: LineEnding,
+    /// fin de chaque ligne d'un fichier qui mélange LF et CRLF, gardée à la sauvegarde ;
+    /// `None` quand toutes les lignes finissent par `line_ending`
+    pub line_endings: Option<Vec<LineEnding>>,
+    /// le fichier se termine par une fin de ligne
+    pub final_newline: bool,
+    /// le fichier commence par un BOM UTF-8
+    pub bom: bool,
+    /// état sur le disque au dernier chargement ou à la dernière sauvegarde
+    pub disk_state: Option<DiskState>,
+}
+
+impl Default for FileInfo {
+    fn default() -> Self {
+        Self {
+            path: None,
+            line_ending: LineEnding::default(),
+            line_endings: None,
+            final_newline: true,
+            bom: false,
+            disk_state: None,
+        }
+    }
 }
 
 impl FileInfo {
-    /// permet de créer une instance de FileInfo à partir d'un nom de fichier
+    /// permet de créer une instance de `FileInfo` à partir d'un nom de fichier
     pub fn from(file_name: &str) -> Self {
         Self {
             path: Some(PathBuf::from(file_name)),
+            ..Self::default()
+        }
+    }
+
+    /// même fichier sous un autre nom, en gardant son format
+    pub fn renamed(&self, file_name: &str) -> Self {
+        Self {
+            path: Some(PathBuf::from(file_name)),
+            line_ending: self.line_ending,
+            line_endings: self.line_endings.clone(),
+            final_newline: self.final_newline,
+            bom: self.bom,
+            disk_state: None,
         }
     }
 
+    /// compare le fichier sur le disque à l'état enregistré. Si seule la date a changé,
+    /// le contenu étant identique, l'état enregistré est simplement mis à jour.
+    pub fn check_external_change(&mut self) -> ExternalChange {
+        let (Some(path), Some(recorded)) = (self.get_path(), self.disk_state) else {
+            return ExternalChange::None;
+        };
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == ErrorKind::NotFound => return ExternalChange::Deleted,
+            Err(_) => return ExternalChange::None,
+        };
+        if metadata.modified().ok() == recorded.modified && metadata.len() == recorded.len {
+            return ExternalChange::None;
+        }
+        match DiskState::read(path) {
+            Ok(current) if current.hash == recorded.hash && current.len == recorded.len => {
+                self.disk_state = Some(current);
+                ExternalChange::None
+            }
+            Ok(_) => ExternalChange::Modified,
+            Err(_) => ExternalChange::None,
+        }
+    }
+
+    /// prend l'état actuel du disque comme référence
+    pub fn record_disk_state(&mut self) {
+        self.disk_state = self.get_path().and_then(|path| DiskState::read(path).ok());
+    }
+
+    /// détecte le format du contenu lu (BOM, fins de ligne, fin de ligne finale)
+    /// et retourne le contenu sans son BOM
+    pub fn detect_format<'a>(&mut self, contents: &'a str) -> &'a str {
+        let text = contents.strip_prefix(BOM);
+        self.bom = text.is_some();
+        let text = text.unwrap_or(contents);
+        self.line_ending = match text.find('\n') {
+            Some(idx) if text.get(..idx).is_some_and(|line| line.ends_with('\r')) => {
+                LineEnding::Crlf
+            }
+            _ => LineEnding::Lf,
+        };
+        let line_endings: Vec<LineEnding> = text
+            .split_inclusive('\n')
+            .map(|line| {
+                if line.ends_with("\r\n") {
+                    LineEnding::Crlf
+                } else if line.ends_with('\n') {
+                    LineEnding::Lf
+                } else {
+                    self.line_ending
+                }
+            })
+            .collect();
+        self.line_endings = line_endings
+            .iter()
+            .any(|line_ending| *line_ending != self.line_ending)
+            .then_some(line_endings);
+        self.final_newline = text.is_empty() || text.ends_with('\n');
+        text
+    }
+
+    /// fin de ligne à écrire après la ligne donnée
+    pub fn line_ending_of(&self, line_index: usize) -> LineEnding {
+        self.line_endings
+            .as_ref()
+            .and_then(|line_endings| line_endings.get(line_index))
+            .copied()
+            .unwrap_or(self.line_ending)
+    }
+
+    /// suit les fins de ligne d'un fichier mixte : la fin de ligne de `line_index` et des
+    /// `removed` lignes suivantes disparaît avec les retours à la ligne supprimés, et les
+    /// `inserted` retours à la ligne ajoutés prennent le style de la ligne coupée
+    pub fn lines_changed(&mut self, line_index: usize, removed: usize, inserted: usize) {
+        let style = self.line_ending_of(line_index);
+        let Some(line_endings) = &mut self.line_endings else {
+            return;
+        };
+        let end = line_index.saturating_add(removed).min(line_endings.len());
+        let start = line_index.min(end);
+        line_endings.splice(start..end, std::iter::repeat_n(style, inserted));
+    }
+
+    /// BOM à écrire au début du fichier
+    pub fn bom_to_string(&self) -> String {
+        if self.bom {
+            BOM.to_string()
+        } else {
+            String::new()
+        }
+    }
+
+    /// résumé du format pour la barre de statut, par exemple `CRLF BOM noeol`
+    pub fn format_to_string(&self) -> String {
+        let mut format = if self.line_endings.is_some() {
+            String::from("Mixed")
+        } else {
+            self.line_ending.to_string()
+        };
+        if self.bom {
+            format.push_str(" BOM");
+        }
+        if !self.final_newline {
+            format.push_str(" noeol");
+        }
+        format
+    }
+
     pub fn get_path(&self) -> Option<&Path> {
         self.path.as_deref()
     }
@@ -24,6 +244,18 @@ impl FileInfo {
     pub const fn has_path(&self) -> bool {
         self.path.is_some()
     }
+
+    /// vrai si le fichier n'existe pas encore sur le disque
+    pub const fn is_new(&self) -> bool {
+        self.path.is_some() && self.disk_state.is_none()
+    }
+
+    /// retourne l'extension du fichier, si il y en a une
+    pub fn get_extension(&self) -> Option<&str> {
+        self.get_path()
+            .and_then(|path| path.extension())
+            .and_then(|extension| extension.to_str())
+    }
 }
 
 /// permet d'afficher le nom du fichier
@@ -0,0 +1,149 @@
+use std::fmt::{self, Display};
+
+use super::{AnnotatedString, AnnotationType};
+
+/// ce que les colonnes de la marge ont besoin de savoir sur la vue
+#[derive(Copy, Clone)]
+pub struct GutterContext {
+    /// nombre de lignes du buffer
+    pub line_count: usize,
+    /// ligne du curseur
+    pub caret_line: usize,
+}
+
+/// colonne de la marge. Les colonnes sont dessinées côte à côte, de gauche à droite ;
+/// d'autres marques (diagnostics, modifications, signets) peuvent s'y ajouter.
+pub trait GutterColumn {
+    /// largeur de la colonne, 0 si elle n'affiche rien
+    fn width(&self, context: GutterContext) -> usize;
+
+    /// contenu de la colonne pour une ligne du buffer, `None` après la fin du buffer.
+    /// Le texte retourné ne dépasse pas `width`, il est complété par des espaces.
+    fn cell(&self, line_index: Option<usize>, context: GutterContext) -> (String, Option<AnnotationType>);
+}
+
+/// numéros de ligne affichés dans la marge
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    /// numéro de chaque ligne
+    Absolute,
+    /// distance à la ligne du curseur
+    Relative,
+    /// distance à la ligne du curseur, et numéro de la ligne du curseur
+    Hybrid,
+}
+
+impl LineNumbers {
+    /// retrouve un mode à partir de son nom (`off`, `absolute`, `relative`, `hybrid`)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "off" => Some(Self::Off),
+            "absolute" => Some(Self::Absolute),
+            "relative" => Some(Self::Relative),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+
+    /// mode suivant, pour passer d'un mode à l'autre avec une seule touche
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Off => Self::Absolute,
+            Self::Absolute => Self::Relative,
+            Self::Relative => Self::Hybrid,
+            Self::Hybrid => Self::Off,
+        }
+    }
+
+    /// vrai si les numéros dépendent de la ligne du curseur
+    pub const fn follows_caret(self) -> bool {
+        matches!(self, Self::Relative | Self::Hybrid)
+    }
+}
+
+impl Display for LineNumbers {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Self::Off => "off",
+            Self::Absolute => "absolute",
+            Self::Relative => "relative",
+            Self::Hybrid => "hybrid",
+        };
+        write!(formatter, "{name}")
+    }
+}
+
+impl GutterColumn for LineNumbers {
+    /// assez de chiffres pour le numéro de la dernière ligne
+    fn width(&self, context: GutterContext) -> usize {
+        if *self == Self::Off {
+            return 0;
+        }
+        context.line_count.max(1).to_string().len()
+    }
+
+    fn cell(&self, line_index: Option<usize>, context: GutterContext) -> (String, Option<AnnotationType>) {
+        let width = self.width(context);
+        let Some(line_index) = line_index else {
+            return (" ".repeat(width), None);
+        };
+        let is_caret_line = line_index == context.caret_line;
+        let number = match self {
+            Self::Absolute => line_index.saturating_add(1),
+            Self::Hybrid if is_caret_line => line_index.saturating_add(1),
+            Self::Relative | Self::Hybrid => line_index.abs_diff(context.caret_line),
+            Self::Off => return (String::new(), None),
+        };
+        let annotation_type = if is_caret_line {
+            AnnotationType::CurrentLineNumber
+        } else {
+            AnnotationType::LineNumber
+        };
+        (format!("{number:>width$}"), Some(annotation_type))
+    }
+}
+
+/// marge à gauche du texte
+#[derive(Default, Clone)]
+pub struct Gutter {
+    pub line_numbers: LineNumbers,
+}
+
+impl Gutter {
+    /// colonnes affichées, de gauche à droite
+    fn columns(&self) -> [&dyn GutterColumn; 1] {
+        [&self.line_numbers]
+    }
+
+    /// largeur totale de la marge, avec l'espace qui la sépare du texte
+    pub fn width(&self, context: GutterContext) -> usize {
+        let width = self
+            .columns()
+            .iter()
+            .map(|column| column.width(context))
+            .fold(0, usize::saturating_add);
+        if width == 0 {
+            0
+        } else {
+            width.saturating_add(1)
+        }
+    }
+
+    /// contenu de la marge pour une ligne du buffer, `None` après la fin du buffer
+    pub fn render(&self, line_index: Option<usize>, context: GutterContext) -> AnnotatedString {
+        let mut result = AnnotatedString::default();
+        for column in self.columns() {
+            if column.width(context) == 0 {
+                continue;
+            }
+            let (text, annotation_type) = column.cell(line_index, context);
+            result.push_str(&text, annotation_type);
+        }
+        if !result.is_empty() {
+            result.push_str(" ", None);
+        }
+        result
+    }
+}
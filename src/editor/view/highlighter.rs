@@ -0,0 +1,371 @@
+use std::{borrow::Cow, iter::repeat_with};
+
+use super::{Annotation, AnnotationType};
+mod syntax;
+pub use syntax::Syntax;
+use syntax::Scanner;
+
+/// état de l'analyse à la fin d'une ligne, transmis à la ligne suivante
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+enum ScanState {
+    #[default]
+    Normal,
+    BlockComment,
+    String(&'static str),
+    CodeFence,
+}
+
+/// coloration d'une ligne, valable tant que la ligne et l'état de départ ne changent pas
+#[derive(Default)]
+struct HighlightedLine {
+    valid: bool,
+    start_state: ScanState,
+    end_state: ScanState,
+    annotations: Vec<Annotation>,
+}
+
+/// coloration syntaxique du buffer, mise à jour ligne par ligne au fil des éditions
+#[derive(Default)]
+pub struct Highlighter {
+    syntax: Option<&'static Syntax>,
+    lines: Vec<HighlightedLine>,
+}
+
+impl Highlighter {
+    pub fn new(syntax: Option<&'static Syntax>) -> Self {
+        Self {
+            syntax,
+            lines: Vec::new(),
+        }
+    }
+
+    pub const fn syntax(&self) -> Option<&'static Syntax> {
+        self.syntax
+    }
+
+    /// nom du langage utilisé
+    pub fn syntax_name(&self) -> &'static str {
+        self.syntax.map_or("Text", |syntax| syntax.name)
+    }
+
+    /// prend en compte une édition : la ligne `line_index` a changé, `removed` lignes qui la
+    /// suivaient ont disparu et `inserted` nouvelles lignes ont été ajoutées après elle
+    pub fn lines_changed(&mut self, line_index: usize, removed: usize, inserted: usize) {
+        let start = line_index.saturating_add(1).min(self.lines.len());
+        let end = start.saturating_add(removed).min(self.lines.len());
+        self.lines
+            .splice(start..end, repeat_with(HighlightedLine::default).take(inserted));
+        if let Some(line) = self.lines.get_mut(line_index) {
+            line.valid = false;
+        }
+    }
+
+    /// recolore les lignes invalidées jusqu'à `until_line` (incluse),
+    /// `line_text` n'est appelée que pour les lignes à recolorer
+    pub fn update<'a>(
+        &mut self,
+        line_count: usize,
+        line_text: impl Fn(usize) -> Cow<'a, str>,
+        until_line: usize,
+    ) {
+        let Some(syntax) = self.syntax else {
+            return;
+        };
+        self.lines.resize_with(line_count, HighlightedLine::default);
+        let mut state = ScanState::Normal;
+        for (line_index, highlighted) in self
+            .lines
+            .iter_mut()
+            .enumerate()
+            .take(until_line.saturating_add(1))
+        {
+            if !highlighted.valid || highlighted.start_state != state {
+                let (annotations, end_state) =
+                    highlight_line(syntax, &line_text(line_index), state);
+                *highlighted = HighlightedLine {
+                    valid: true,
+                    start_state: state,
+                    end_state,
+                    annotations,
+                };
+            }
+            state = highlighted.end_state;
+        }
+    }
+
+    /// annotations de la ligne donnée, telles que calculées lors du dernier `update`
+    pub fn annotations(&self, line_index: usize) -> &[Annotation] {
+        self.lines
+            .get(line_index)
+            .filter(|line| line.valid)
+            .map_or(&[], |line| line.annotations.as_slice())
+    }
+}
+
+fn highlight_line(syntax: &Syntax, line: &str, state: ScanState) -> (Vec<Annotation>, ScanState) {
+    let mut scanner = LineScanner {
+        line,
+        annotations: Vec::new(),
+    };
+    let end_state = match syntax.scanner {
+        Scanner::Code => scanner.scan_code(syntax, state),
+        Scanner::Markdown => scanner.scan_markdown(state),
+    };
+    (scanner.annotations, end_state)
+}
+
+/// analyse d'une ligne, produit les annotations en index d'octets
+struct LineScanner<'a> {
+    line: &'a str,
+    annotations: Vec<Annotation>,
+}
+
+impl LineScanner<'_> {
+    fn annotate(&mut self, kind: AnnotationType, start_byte_idx: usize, end_byte_idx: usize) {
+        if start_byte_idx < end_byte_idx {
+            self.annotations.push(Annotation {
+                kind,
+                start_byte_idx,
+                end_byte_idx,
+            });
+        }
+    }
+
+    fn rest(&self, from: usize) -> &str {
+        self.line.get(from..).unwrap_or_default()
+    }
+
+    fn scan_code(&mut self, syntax: &Syntax, mut state: ScanState) -> ScanState {
+        let len = self.line.len();
+        let mut idx = 0;
+        while idx < len {
+            match state {
+                ScanState::BlockComment => {
+                    let end_delimiter = syntax.block_comment.map_or("", |(_, end)| end);
+                    let end = self.find_unescaped(idx, end_delimiter, false);
+                    self.annotate(AnnotationType::Comment, idx, end.unwrap_or(len));
+                    let Some(end) = end else {
+                        return state;
+                    };
+                    idx = end;
+                    state = ScanState::Normal;
+                }
+                ScanState::String(delimiter) => {
+                    let end = self.find_unescaped(idx, delimiter, true);
+                    self.annotate(AnnotationType::String, idx, end.unwrap_or(len));
+                    let Some(end) = end else {
+                        return state;
+                    };
+                    idx = end;
+                    state = ScanState::Normal;
+                }
+                ScanState::Normal | ScanState::CodeFence => {
+                    let (next_idx, next_state) = self.scan_code_token(syntax, idx);
+                    idx = next_idx;
+                    state = next_state;
+                }
+            }
+        }
+        // une chaîne simple non terminée ne déborde pas sur la ligne suivante
+        match state {
+            ScanState::String(delimiter)
+                if !syntax
+                    .strings
+                    .iter()
+                    .any(|rule| rule.delimiter == delimiter && rule.multiline) =>
+            {
+                ScanState::Normal
+            }
+            _ => state,
+        }
+    }
+
+    /// analyse l'élément qui commence à `idx`, retourne l'index suivant et le nouvel état
+    fn scan_code_token(&mut self, syntax: &Syntax, idx: usize) -> (usize, ScanState) {
+        let len = self.line.len();
+        let rest = self.rest(idx);
+        if let Some(line_comment) = syntax.line_comment {
+            if rest.starts_with(line_comment) {
+                self.annotate(AnnotationType::Comment, idx, len);
+                return (len, ScanState::Normal);
+            }
+        }
+        if let Some((start, _)) = syntax.block_comment {
+            if rest.starts_with(start) {
+                let after = idx.saturating_add(start.len());
+                self.annotate(AnnotationType::Comment, idx, after);
+                return (after, ScanState::BlockComment);
+            }
+        }
+        if let Some(rule) = syntax
+            .strings
+            .iter()
+            .find(|rule| rest.starts_with(rule.delimiter))
+        {
+            let after = idx.saturating_add(rule.delimiter.len());
+            self.annotate(AnnotationType::String, idx, after);
+            return (after, ScanState::String(rule.delimiter));
+        }
+        let Some(character) = rest.chars().next() else {
+            return (len, ScanState::Normal);
+        };
+        let next = idx.saturating_add(character.len_utf8());
+        if syntax.char_literals && character == '\'' {
+            if let Some(end) = self.char_literal_end(idx) {
+                self.annotate(AnnotationType::String, idx, end);
+                return (end, ScanState::Normal);
+            }
+        } else if character.is_ascii_digit() && !self.follows_identifier(idx) {
+            let end = self.number_end(idx);
+            self.annotate(AnnotationType::Number, idx, end);
+            return (end, ScanState::Normal);
+        } else if is_identifier_start(character) || character == '#' {
+            let end = self.identifier_end(next);
+            let word = self.line.get(idx..end).unwrap_or_default();
+            if syntax.keywords.contains(&word) {
+                self.annotate(AnnotationType::Keyword, idx, end);
+            } else if syntax.types.contains(&word)
+                || (syntax.capitalized_types
+                    && word.starts_with(|first: char| first.is_uppercase()))
+            {
+                self.annotate(AnnotationType::Type, idx, end);
+            }
+            return (end, ScanState::Normal);
+        }
+        (next, ScanState::Normal)
+    }
+
+    /// cherche la fin d'un délimiteur à partir de `from`, retourne l'index juste après
+    fn find_unescaped(&self, from: usize, delimiter: &str, escapes: bool) -> Option<usize> {
+        if delimiter.is_empty() {
+            return None;
+        }
+        let mut chars = self.rest(from).char_indices();
+        while let Some((offset, character)) = chars.next() {
+            let idx = from.saturating_add(offset);
+            if escapes && character == '\\' {
+                chars.next();
+            } else if self.rest(idx).starts_with(delimiter) {
+                return Some(idx.saturating_add(delimiter.len()));
+            }
+        }
+        None
+    }
+
+    /// reconnaît `'a'` ou `'\n'`, mais pas une durée de vie comme `'a`
+    fn char_literal_end(&self, idx: usize) -> Option<usize> {
+        let mut chars = self.rest(idx).char_indices().skip(1);
+        let (_, first) = chars.next()?;
+        if first == '\\' {
+            chars.next()?;
+            chars
+                .find(|(_, character)| *character == '\'')
+                .map(|(offset, _)| idx.saturating_add(offset).saturating_add(1))
+        } else {
+            let (offset, closing) = chars.next()?;
+            (closing == '\'').then(|| idx.saturating_add(offset).saturating_add(1))
+        }
+    }
+
+    fn follows_identifier(&self, idx: usize) -> bool {
+        self.line
+            .get(..idx)
+            .and_then(|before| before.chars().next_back())
+            .is_some_and(is_identifier_char)
+    }
+
+    fn identifier_end(&self, from: usize) -> usize {
+        self.rest(from)
+            .char_indices()
+            .find(|(_, character)| !is_identifier_char(*character))
+            .map_or(self.line.len(), |(offset, _)| from.saturating_add(offset))
+    }
+
+    /// fin d'un nombre : chiffres, suffixes (`0xff`, `1u8`, `1e5`) et partie décimale
+    fn number_end(&self, from: usize) -> usize {
+        let rest = self.rest(from);
+        let mut end = rest.len();
+        let mut chars = rest.char_indices().peekable();
+        while let Some((offset, character)) = chars.next() {
+            let is_decimal_point = character == '.'
+                && chars
+                    .peek()
+                    .is_some_and(|(_, next)| next.is_ascii_digit());
+            if !(is_identifier_char(character) || is_decimal_point) {
+                end = offset;
+                break;
+            }
+        }
+        from.saturating_add(end)
+    }
+
+    fn scan_markdown(&mut self, state: ScanState) -> ScanState {
+        let len = self.line.len();
+        let trimmed = self.line.trim_start();
+        let indent = len.saturating_sub(trimmed.len());
+        if trimmed.starts_with("```") {
+            self.annotate(AnnotationType::String, 0, len);
+            return if state == ScanState::CodeFence {
+                ScanState::Normal
+            } else {
+                ScanState::CodeFence
+            };
+        }
+        if state == ScanState::CodeFence {
+            self.annotate(AnnotationType::String, 0, len);
+            return state;
+        }
+        if trimmed.starts_with('#') {
+            self.annotate(AnnotationType::Keyword, 0, len);
+            return ScanState::Normal;
+        }
+        if trimmed.starts_with('>') {
+            self.annotate(AnnotationType::Comment, 0, len);
+            return ScanState::Normal;
+        }
+        let marker_len = list_marker_len(trimmed);
+        self.annotate(
+            AnnotationType::Number,
+            indent,
+            indent.saturating_add(marker_len),
+        );
+        // code en ligne entre backticks
+        let mut from = indent.saturating_add(marker_len);
+        while let Some(start) = self.rest(from).find('`').map(|offset| from.saturating_add(offset)) {
+            let content_start = start.saturating_add(1);
+            let Some(end) = self.find_unescaped(content_start, "`", false) else {
+                break;
+            };
+            self.annotate(AnnotationType::String, start, end);
+            from = end;
+        }
+        ScanState::Normal
+    }
+}
+
+/// longueur d'un marqueur de liste (`- `, `* `, `1. `), 0 s'il n'y en a pas
+fn list_marker_len(trimmed: &str) -> usize {
+    if ["- ", "* ", "+ "]
+        .iter()
+        .any(|marker| trimmed.starts_with(marker))
+    {
+        return 1;
+    }
+    let digits = trimmed
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .count();
+    if digits > 0 && trimmed.get(digits..).is_some_and(|rest| rest.starts_with(". ")) {
+        digits.saturating_add(1)
+    } else {
+        0
+    }
+}
+
+fn is_identifier_start(character: char) -> bool {
+    character.is_alphabetic() || character == '_'
+}
+
+fn is_identifier_char(character: char) -> bool {
+    character.is_alphanumeric() || character == '_'
+}
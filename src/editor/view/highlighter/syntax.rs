@@ -0,0 +1,190 @@
+/// délimiteur de chaîne de caractères, `multiline` si la chaîne peut continuer sur la ligne suivante
+pub struct StringRule {
+    pub delimiter: &'static str,
+    pub multiline: bool,
+}
+
+/// manière d'analyser les lignes
+#[derive(PartialEq, Eq)]
+pub enum Scanner {
+    Code,
+    Markdown,
+}
+
+/// définition d'un langage pour la coloration syntaxique
+pub struct Syntax {
+    pub name: &'static str,
+    pub extensions: &'static [&'static str],
+    pub scanner: Scanner,
+    pub keywords: &'static [&'static str],
+    pub types: &'static [&'static str],
+    pub line_comment: Option<&'static str>,
+    pub block_comment: Option<(&'static str, &'static str)>,
+    pub strings: &'static [StringRule],
+    pub char_literals: bool,
+    pub capitalized_types: bool,
+    /// caractères qui, en fin de ligne, augmentent l'indentation de la ligne suivante
+    pub indent_after: &'static [char],
+}
+
+impl Syntax {
+    /// trouve le langage correspondant à une extension de fichier
+    pub fn for_extension(extension: &str) -> Option<&'static Self> {
+        SYNTAXES
+            .iter()
+            .find(|syntax| syntax.extensions.contains(&extension))
+    }
+}
+
+static SYNTAXES: &[Syntax] = &[RUST, C, PYTHON, MARKDOWN, TOML, JSON];
+
+const RUST: Syntax = Syntax {
+    name: "Rust",
+    extensions: &["rs"],
+    scanner: Scanner::Code,
+    keywords: &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ],
+    types: &[
+        "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize",
+        "f32", "f64", "bool", "char", "str",
+    ],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    strings: &[StringRule {
+        delimiter: "\"",
+        multiline: true,
+    }],
+    char_literals: true,
+    capitalized_types: true,
+    indent_after: &['{', '(', '['],
+};
+
+const C: Syntax = Syntax {
+    name: "C",
+    extensions: &["c", "h"],
+    scanner: Scanner::Code,
+    keywords: &[
+        "auto", "break", "case", "const", "continue", "default", "do", "else", "enum", "extern",
+        "for", "goto", "if", "inline", "register", "restrict", "return", "sizeof", "static",
+        "struct", "switch", "typedef", "union", "volatile", "while", "NULL", "true", "false",
+        "#include", "#define", "#ifdef", "#ifndef", "#endif", "#if", "#else", "#pragma",
+    ],
+    types: &[
+        "char", "double", "float", "int", "long", "short", "signed", "unsigned", "void", "bool",
+        "size_t", "int8_t", "int16_t", "int32_t", "int64_t", "uint8_t", "uint16_t", "uint32_t",
+        "uint64_t", "FILE",
+    ],
+    line_comment: Some("//"),
+    block_comment: Some(("/*", "*/")),
+    strings: &[StringRule {
+        delimiter: "\"",
+        multiline: false,
+    }],
+    char_literals: true,
+    capitalized_types: false,
+    indent_after: &['{', '(', '['],
+};
+
+const PYTHON: Syntax = Syntax {
+    name: "Python",
+    extensions: &["py", "pyw"],
+    scanner: Scanner::Code,
+    keywords: &[
+        "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class",
+        "continue", "def", "del", "elif", "else", "except", "finally", "for", "from", "global",
+        "if", "import", "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return",
+        "self", "try", "while", "with", "yield",
+    ],
+    types: &[
+        "int", "float", "str", "bytes", "bool", "list", "dict", "set", "tuple", "object",
+    ],
+    line_comment: Some("#"),
+    block_comment: None,
+    strings: &[
+        StringRule {
+            delimiter: "\"\"\"",
+            multiline: true,
+        },
+        StringRule {
+            delimiter: "'''",
+            multiline: true,
+        },
+        StringRule {
+            delimiter: "\"",
+            multiline: false,
+        },
+        StringRule {
+            delimiter: "'",
+            multiline: false,
+        },
+    ],
+    char_literals: false,
+    capitalized_types: true,
+    indent_after: &[':', '(', '[', '{'],
+};
+
+const MARKDOWN: Syntax = Syntax {
+    name: "Markdown",
+    extensions: &["md", "markdown"],
+    scanner: Scanner::Markdown,
+    keywords: &[],
+    types: &[],
+    line_comment: None,
+    block_comment: None,
+    strings: &[],
+    char_literals: false,
+    capitalized_types: false,
+    indent_after: &[],
+};
+
+const TOML: Syntax = Syntax {
+    name: "TOML",
+    extensions: &["toml"],
+    scanner: Scanner::Code,
+    keywords: &["true", "false"],
+    types: &[],
+    line_comment: Some("#"),
+    block_comment: None,
+    strings: &[
+        StringRule {
+            delimiter: "\"\"\"",
+            multiline: true,
+        },
+        StringRule {
+            delimiter: "'''",
+            multiline: true,
+        },
+        StringRule {
+            delimiter: "\"",
+            multiline: false,
+        },
+        StringRule {
+            delimiter: "'",
+            multiline: false,
+        },
+    ],
+    char_literals: false,
+    capitalized_types: false,
+    indent_after: &['[', '{'],
+};
+
+const JSON: Syntax = Syntax {
+    name: "JSON",
+    extensions: &["json"],
+    scanner: Scanner::Code,
+    keywords: &["true", "false", "null"],
+    types: &[],
+    line_comment: None,
+    block_comment: None,
+    strings: &[StringRule {
+        delimiter: "\"",
+        multiline: false,
+    }],
+    char_literals: false,
+    capitalized_types: false,
+    indent_after: &['{', '['],
+};
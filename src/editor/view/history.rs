@@ -0,0 +1,264 @@
+use super::Location;
+
+/// opération élémentaire appliquée sur le texte du buffer
+#[derive(Clone)]
+pub enum Operation {
+    Insert { at: Location, text: String },
+    Delete { at: Location, text: String },
+}
+
+impl Operation {
+    /// retourne l'opération qui annule celle-ci
+    pub fn inverse(&self) -> Self {
+        match self {
+            Self::Insert { at, text } => Self::Delete {
+                at: *at,
+                text: text.clone(),
+            },
+            Self::Delete { at, text } => Self::Insert {
+                at: *at,
+                text: text.clone(),
+            },
+        }
+    }
+}
+
+/// type d'édition, sert à regrouper les frappes consécutives en une seule étape
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum EditKind {
+    Typing,
+    Erasing,
+    Other,
+}
+
+/// groupe d'opérations annulé ou rétabli d'un seul coup
+struct Transaction {
+    id: usize,
+    kind: EditKind,
+    operations: Vec<Operation>,
+    caret_before: Location,
+    caret_after: Location,
+}
+
+/// transaction en cours de construction
+struct Pending {
+    transaction: Transaction,
+    merged_len: usize, // nb d'opérations déjà présentes si on a repris la transaction précédente
+}
+
+/// historique des éditions du buffer (undo / redo)
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Transaction>,
+    redo_stack: Vec<Transaction>,
+    pending: Option<Pending>,
+    can_merge: bool,
+    last_id: usize,
+    saved_id: usize, // id de l'état sauvegardé, 0 = état initial
+}
+
+impl History {
+    /// ouvre une transaction, en reprenant la précédente si c'est la suite d'une frappe
+    pub fn begin(&mut self, kind: EditKind, caret: Location) {
+        self.commit(caret);
+        let can_merge = self.can_merge
+            && kind != EditKind::Other
+            && self
+                .undo_stack
+                .last()
+                .is_some_and(|top| top.kind == kind && top.caret_after == caret);
+        let pending = if can_merge {
+            self.undo_stack.pop().map(|transaction| Pending {
+                merged_len: transaction.operations.len(),
+                transaction,
+            })
+        } else {
+            None
+        };
+        self.pending = Some(pending.unwrap_or(Pending {
+            transaction: Transaction {
+                id: 0,
+                kind,
+                operations: Vec::new(),
+                caret_before: caret,
+                caret_after: caret,
+            },
+            merged_len: 0,
+        }));
+    }
+
+    /// enregistre une opération dans la transaction en cours
+    pub fn record(&mut self, operation: Operation) {
+        if let Some(pending) = &mut self.pending {
+            pending.transaction.operations.push(operation);
+        } else {
+            let caret = match &operation {
+                Operation::Insert { at, .. } | Operation::Delete { at, .. } => *at,
+            };
+            self.begin(EditKind::Other, caret);
+            self.record(operation);
+        }
+    }
+
+    /// ferme la transaction en cours
+    pub fn commit(&mut self, caret: Location) {
+        let Some(Pending {
+            mut transaction,
+            merged_len,
+        }) = self.pending.take()
+        else {
+            return;
+        };
+        if transaction.operations.len() == merged_len {
+            // rien de nouveau : on remet la transaction reprise telle quelle
+            if merged_len > 0 {
+                self.undo_stack.push(transaction);
+            }
+            return;
+        }
+        self.last_id = self.last_id.saturating_add(1);
+        transaction.id = self.last_id;
+        transaction.caret_after = caret;
+        self.can_merge = transaction.kind != EditKind::Other;
+        self.undo_stack.push(transaction);
+        self.redo_stack.clear();
+    }
+
+    /// retire la dernière transaction, renvoie les opérations à annuler et le curseur à restaurer
+    pub fn undo(&mut self) -> Option<(Vec<Operation>, Location)> {
+        let transaction = self.undo_stack.pop()?;
+        self.can_merge = false;
+        let result = (
+            transaction.operations.iter().rev().map(Operation::inverse).collect(),
+            transaction.caret_before,
+        );
+        self.redo_stack.push(transaction);
+        Some(result)
+    }
+
+    /// rejoue la dernière transaction annulée
+    pub fn redo(&mut self) -> Option<(Vec<Operation>, Location)> {
+        let transaction = self.redo_stack.pop()?;
+        self.can_merge = false;
+        let result = (transaction.operations.clone(), transaction.caret_after);
+        self.undo_stack.push(transaction);
+        Some(result)
+    }
+
+    fn current_id(&self) -> usize {
+        self.undo_stack.last().map_or(0, |transaction| transaction.id)
+    }
+
+    /// retient l'état actuel comme étant celui du fichier sur le disque
+    pub fn mark_saved(&mut self) {
+        self.saved_id = self.current_id();
+        self.can_merge = false;
+    }
+
+    /// indique si le texte diffère de la dernière sauvegarde
+    pub fn is_dirty(&self) -> bool {
+        self.current_id() != self.saved_id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EditKind, History, Location, Operation};
+
+    const fn at(grapheme_index: usize) -> Location {
+        Location {
+            line_index: 0,
+            grapheme_index,
+        }
+    }
+
+    /// une frappe ou un effacement d'un caractère, comme le fait la vue
+    fn edit(history: &mut History, kind: EditKind, from: usize, to: usize, text: &str) {
+        history.begin(kind, at(from));
+        let operation = if kind == EditKind::Erasing {
+            Operation::Delete {
+                at: at(to),
+                text: text.to_string(),
+            }
+        } else {
+            Operation::Insert {
+                at: at(from),
+                text: text.to_string(),
+            }
+        };
+        history.record(operation);
+        history.commit(at(to));
+    }
+
+    fn texts(operations: &[Operation]) -> Vec<String> {
+        operations
+            .iter()
+            .map(|operation| match operation {
+                Operation::Insert { text, .. } => format!("+{text}"),
+                Operation::Delete { text, .. } => format!("-{text}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn consecutive_typing_is_one_step() {
+        let mut history = History::default();
+        edit(&mut history, EditKind::Typing, 0, 1, "a");
+        edit(&mut history, EditKind::Typing, 1, 2, "b");
+        edit(&mut history, EditKind::Typing, 2, 3, "c");
+        let (operations, caret) = history.undo().unwrap();
+        assert_eq!(texts(&operations), ["-c", "-b", "-a"]);
+        assert_eq!(caret, at(0));
+        assert!(history.undo().is_none());
+    }
+
+    #[test]
+    fn erasing_after_typing_is_a_new_step() {
+        let mut history = History::default();
+        edit(&mut history, EditKind::Typing, 0, 1, "a");
+        edit(&mut history, EditKind::Typing, 1, 2, "b");
+        edit(&mut history, EditKind::Erasing, 2, 1, "b");
+        edit(&mut history, EditKind::Erasing, 1, 0, "a");
+        let (operations, caret) = history.undo().unwrap();
+        assert_eq!(texts(&operations), ["+a", "+b"]);
+        assert_eq!(caret, at(2));
+        let (operations, caret) = history.undo().unwrap();
+        assert_eq!(texts(&operations), ["-b", "-a"]);
+        assert_eq!(caret, at(0));
+    }
+
+    #[test]
+    fn typing_elsewhere_or_other_edits_are_not_merged() {
+        let mut history = History::default();
+        edit(&mut history, EditKind::Typing, 0, 1, "a");
+        // le curseur a bougé entre les deux frappes
+        edit(&mut history, EditKind::Typing, 5, 6, "b");
+        edit(&mut history, EditKind::Other, 6, 7, "c");
+        edit(&mut history, EditKind::Other, 7, 8, "d");
+        for expected in ["-d", "-c", "-b", "-a"] {
+            assert_eq!(texts(&history.undo().unwrap().0), [expected]);
+        }
+    }
+
+    #[test]
+    fn undo_and_redo_round_trip() {
+        let mut history = History::default();
+        edit(&mut history, EditKind::Typing, 0, 1, "a");
+        history.mark_saved();
+        edit(&mut history, EditKind::Typing, 1, 2, "b");
+        assert!(history.is_dirty());
+        let (undone, caret) = history.undo().unwrap();
+        assert_eq!((texts(&undone), caret), (vec!["-b".to_string()], at(1)));
+        assert!(!history.is_dirty());
+        let (redone, caret) = history.redo().unwrap();
+        assert_eq!((texts(&redone), caret), (vec!["+b".to_string()], at(2)));
+        assert!(history.is_dirty());
+        assert!(history.redo().is_none());
+        // une frappe après un undo abandonne ce qui pouvait être rétabli et commence une étape
+        history.undo();
+        edit(&mut history, EditKind::Typing, 1, 2, "x");
+        assert!(history.redo().is_none());
+        assert_eq!(texts(&history.undo().unwrap().0), ["-x"]);
+        assert_eq!(texts(&history.undo().unwrap().0), ["-a"]);
+    }
+}
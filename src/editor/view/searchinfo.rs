@@ -0,0 +1,26 @@
+use super::{Location, Pattern, Position};
+
+/// état d'une recherche en cours, pour pouvoir revenir en arrière si on l'annule
+pub struct SearchInfo {
+    pub prev_location: Location,
+    pub prev_scroll_offset: Position,
+    pub pattern: Option<Pattern>,
+}
+
+/// sens de parcours de la recherche
+#[derive(Copy, Clone, Default, Eq, PartialEq)]
+pub enum SearchDirection {
+    #[default]
+    Forward,
+    Backward,
+}
+
+/// état d'un remplacement en cours, la correspondance courante est à la position du curseur
+pub struct ReplaceInfo {
+    pub pattern: Pattern,
+    pub replacement: String,
+    pub current_match_end: Location,
+    pub stop_at: Location,
+    pub wrapped: bool,
+    pub count: usize,
+}
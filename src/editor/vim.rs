@@ -0,0 +1,588 @@
+use crossterm::{
+    cursor::SetCursorStyle,
+    event::{
+        KeyCode::{Backspace, Char, Down, End, Enter, Esc, Home, Left, Right, Up},
+        KeyEvent, KeyModifiers,
+    },
+};
+
+use super::{
+    command::{Command, System},
+    keymap::key_chord,
+};
+
+/// plus grand nombre accepté devant une commande : au-delà, un déplacement ou `.`
+/// répété ferait attendre l'éditeur
+const MAX_COUNT: usize = 10_000;
+
+/// modes de l'édition à la Vim
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    /// saisie d'une commande `:` dans la barre de commande
+    CommandLine,
+}
+
+impl Mode {
+    /// nom affiché dans la ligne de status
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Insert => "INSERT",
+            Self::Visual => "VISUAL",
+            Self::CommandLine => "COMMAND",
+        }
+    }
+
+    /// forme du curseur : un bloc sur un caractère, une barre entre deux caractères
+    pub const fn caret_style(self) -> SetCursorStyle {
+        match self {
+            Self::Normal | Self::Visual => SetCursorStyle::SteadyBlock,
+            Self::Insert | Self::CommandLine => SetCursorStyle::SteadyBar,
+        }
+    }
+}
+
+/// déplacements des modes normal et visuel
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Motion {
+    Left,
+    Right,
+    Up,
+    Down,
+    /// `w` : début du mot suivant
+    WordForward,
+    /// `b` : début du mot précédent
+    WordBackward,
+    /// `e` : fin du mot
+    WordEnd,
+    /// `0`
+    LineStart,
+    /// `^` : premier caractère qui n'est pas un blanc
+    FirstNonBlank,
+    /// `$`
+    LineEnd,
+    /// `gg` ou `G` précédés d'un numéro de ligne (à partir de 1)
+    GotoLine(usize),
+    /// `G`
+    LastLine,
+}
+
+impl Motion {
+    /// un opérateur appliqué avec ce déplacement porte sur des lignes entières
+    pub const fn is_linewise(self) -> bool {
+        matches!(self, Self::Up | Self::Down | Self::GotoLine(_) | Self::LastLine)
+    }
+
+    /// un opérateur appliqué avec ce déplacement inclut le caractère d'arrivée
+    pub const fn is_inclusive(self) -> bool {
+        matches!(self, Self::WordEnd)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+}
+
+/// où le mode insertion commence
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum InsertPosition {
+    /// `i`
+    Before,
+    /// `a`
+    After,
+    /// `I`
+    LineStart,
+    /// `A`
+    LineEnd,
+    /// `o`
+    LineBelow,
+    /// `O`
+    LineAbove,
+}
+
+/// texte supprimé ou copié, collé par `p`
+#[derive(Default)]
+pub struct Register {
+    pub text: String,
+    /// le texte est fait de lignes entières, collées sous ou au-dessus de la ligne du curseur
+    pub linewise: bool,
+}
+
+/// ce que l'éditeur doit faire pour une touche
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// touche traitée comme sans le mode Vim, par le `Keymap`
+    Key(KeyEvent),
+    /// raccourci de l'éditeur tapé en mode normal ou visuel (Ctrl-S, F1...) : seules les
+    /// commandes système et de fenêtre sont exécutées, le texte ne change que par Vim
+    Shortcut(KeyEvent),
+    Command(Command),
+    /// `u` répété
+    Undo(usize),
+    /// `Ctrl-R` répété
+    Redo(usize),
+    Move(Motion, usize),
+    Operate(Operator, Motion, usize),
+    /// `dd`, `cc`, `yy` : l'opérateur porte sur des lignes entières
+    OperateLines(Operator, usize),
+    OperateSelection(Operator),
+    Insert(InsertPosition),
+    /// sortie du mode insertion : le curseur recule sur le dernier caractère
+    LeaveInsert,
+    Put { before: bool, count: usize },
+    StartVisual,
+    EndVisual,
+    /// ouvre la barre de commande `:`
+    CommandLine,
+}
+
+/// état de l'édition à la Vim : mode actif et commande en cours de saisie
+#[derive(Default)]
+pub struct Vim {
+    mode: Mode,
+    /// nombre tapé avant la commande ou le déplacement
+    count: Option<usize>,
+    /// opérateur qui attend son déplacement, avec le nombre tapé avant lui
+    operator: Option<(Operator, usize)>,
+    /// `g` tapé, en attente du second `g`
+    pending_g: bool,
+    /// touches de la commande en cours ; celles d'une modification sont rejouées par `.`
+    keys: Vec<KeyEvent>,
+    /// la commande en cours modifie le texte
+    is_change: bool,
+    last_change: Vec<KeyEvent>,
+    pub register: Register,
+}
+
+impl Vim {
+    pub const fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// quitte le mode visuel en gardant la sélection, que l'éditeur remplace lui-même (collage)
+    pub fn leave_visual(&mut self) {
+        if self.mode == Mode::Visual {
+            self.cancel();
+            self.keys.clear();
+            self.mode = Mode::Normal;
+        }
+    }
+
+    /// traite une touche pressée, retourne les actions à exécuter dans l'ordre
+    pub fn handle_key(&mut self, key: &KeyEvent) -> Vec<Action> {
+        let key = key_chord(key);
+        self.keys.push(key);
+        let actions = match self.mode {
+            Mode::Insert if key.code == Esc => {
+                self.mode = Mode::Normal;
+                vec![Action::LeaveInsert]
+            }
+            Mode::Insert | Mode::CommandLine => vec![Action::Key(key)],
+            Mode::Normal | Mode::Visual => self.handle_command_key(key),
+        };
+        // une modification n'est complète qu'au retour en mode normal ; en mode visuel,
+        // la sélection fait partie de la modification rejouée par `.`
+        if self.mode == Mode::Normal && !self.is_pending() {
+            if self.is_change {
+                self.last_change = std::mem::take(&mut self.keys);
+                self.is_change = false;
+            } else {
+                self.keys.clear();
+            }
+        }
+        actions
+    }
+
+    const fn is_pending(&self) -> bool {
+        self.count.is_some() || self.operator.is_some() || self.pending_g
+    }
+
+    /// abandonne la commande en cours de saisie
+    fn cancel(&mut self) -> Vec<Action> {
+        self.count = None;
+        self.operator = None;
+        self.pending_g = false;
+        self.is_change = false;
+        Vec::new()
+    }
+
+    fn handle_command_key(&mut self, key: KeyEvent) -> Vec<Action> {
+        let visual = self.mode == Mode::Visual;
+        if std::mem::take(&mut self.pending_g) {
+            return if key.code == Char('g') {
+                let line = self.count.take().unwrap_or(1);
+                self.apply_motion(Motion::GotoLine(line))
+            } else {
+                self.cancel()
+            };
+        }
+        let character = match key.code {
+            Char(character) if key.modifiers.is_empty() => character,
+            Char('r') if key.modifiers == KeyModifiers::CONTROL => {
+                return vec![Action::Redo(self.count.take().unwrap_or(1))];
+            }
+            Esc if visual => {
+                self.cancel();
+                self.mode = Mode::Normal;
+                return vec![Action::EndVisual];
+            }
+            Esc => return self.cancel(),
+            _ => {
+                if let Some(motion) = Self::motion(key) {
+                    return self.apply_motion(motion);
+                }
+                if self.is_pending() {
+                    return self.cancel();
+                }
+                // raccourci de l'éditeur (Ctrl-S, F1...), qui termine la sélection
+                if visual {
+                    self.mode = Mode::Normal;
+                    return vec![Action::EndVisual, Action::Shortcut(key)];
+                }
+                return vec![Action::Shortcut(key)];
+            }
+        };
+        if let Some(digit) = character.to_digit(10).filter(|digit| *digit > 0 || self.count.is_some()) {
+            self.count = Some(
+                self.count
+                    .unwrap_or_default()
+                    .saturating_mul(10)
+                    .saturating_add(usize::try_from(digit).unwrap_or_default())
+                    .min(MAX_COUNT),
+            );
+            return Vec::new();
+        }
+        // `gg` attend la seconde touche, le nombre tapé est le numéro de ligne
+        if character == 'g' {
+            self.pending_g = true;
+            return Vec::new();
+        }
+        if character == 'G' {
+            return match self.count.take() {
+                Some(line) => self.apply_motion(Motion::GotoLine(line)),
+                None => self.apply_motion(Motion::LastLine),
+            };
+        }
+        if let Some(motion) = Self::motion(key) {
+            return self.apply_motion(motion);
+        }
+        let operator = match character {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            _ => None,
+        };
+        if let Some(operator) = operator {
+            return self.apply_operator(operator);
+        }
+        if self.operator.is_some() {
+            return self.cancel();
+        }
+        if character == '.' && !visual {
+            let count = self.count.take();
+            return self.repeat_last_change(count);
+        }
+        let count = self.count.take().unwrap_or(1);
+        if visual {
+            return self.handle_visual_command(character);
+        }
+        self.handle_normal_command(character, count)
+    }
+
+    /// déplacement associé à une touche, sauf `G` qui dépend du nombre tapé
+    fn motion(key: KeyEvent) -> Option<Motion> {
+        let motion = match key.code {
+            Char('h') | Left | Backspace => Motion::Left,
+            Char('l' | ' ') | Right => Motion::Right,
+            Char('k') | Up => Motion::Up,
+            Char('j') | Down | Enter => Motion::Down,
+            Char('w') => Motion::WordForward,
+            Char('b') => Motion::WordBackward,
+            Char('e') => Motion::WordEnd,
+            Char('0') | Home => Motion::LineStart,
+            Char('^') => Motion::FirstNonBlank,
+            Char('$') | End => Motion::LineEnd,
+            _ => return None,
+        };
+        (key.modifiers.is_empty()).then_some(motion)
+    }
+
+    /// déplace le curseur, ou applique l'opérateur en attente jusqu'à la destination
+    fn apply_motion(&mut self, motion: Motion) -> Vec<Action> {
+        let count = self.count.take().unwrap_or(1);
+        let Some((operator, operator_count)) = self.operator.take() else {
+            return vec![Action::Move(motion, count)];
+        };
+        self.begin_operator(operator);
+        vec![Action::Operate(
+            operator,
+            motion,
+            operator_count.saturating_mul(count).min(MAX_COUNT),
+        )]
+    }
+
+    /// `d`, `c` ou `y` : en mode visuel, s'applique à la sélection ; doublé, à des lignes entières
+    fn apply_operator(&mut self, operator: Operator) -> Vec<Action> {
+        if self.mode == Mode::Visual {
+            self.count = None;
+            self.is_change = operator != Operator::Yank;
+            self.mode = Mode::Normal;
+            if operator == Operator::Change {
+                self.mode = Mode::Insert;
+            }
+            return vec![Action::OperateSelection(operator)];
+        }
+        match self.operator.take() {
+            Some((pending, count)) if pending == operator => {
+                let count = count
+                    .saturating_mul(self.count.take().unwrap_or(1))
+                    .min(MAX_COUNT);
+                self.begin_operator(operator);
+                vec![Action::OperateLines(operator, count)]
+            }
+            Some(_) => self.cancel(),
+            None => {
+                self.operator = Some((operator, self.count.take().unwrap_or(1)));
+                Vec::new()
+            }
+        }
+    }
+
+    fn begin_operator(&mut self, operator: Operator) {
+        self.is_change = operator != Operator::Yank;
+        if operator == Operator::Change {
+            self.mode = Mode::Insert;
+        }
+    }
+
+    /// commandes du mode visuel qui ne sont ni des déplacements ni des opérateurs
+    fn handle_visual_command(&mut self, character: char) -> Vec<Action> {
+        let operator = match character {
+            'x' => Operator::Delete,
+            's' => Operator::Change,
+            'v' => {
+                self.mode = Mode::Normal;
+                return vec![Action::EndVisual];
+            }
+            ':' => {
+                self.mode = Mode::Normal;
+                return vec![Action::EndVisual, Action::CommandLine];
+            }
+            _ => return Vec::new(),
+        };
+        self.apply_operator(operator)
+    }
+
+    /// commandes du mode normal qui ne sont ni des déplacements ni des opérateurs
+    fn handle_normal_command(&mut self, character: char, count: usize) -> Vec<Action> {
+        let insert_position = match character {
+            'i' => Some(InsertPosition::Before),
+            'a' => Some(InsertPosition::After),
+            'I' => Some(InsertPosition::LineStart),
+            'A' => Some(InsertPosition::LineEnd),
+            'o' => Some(InsertPosition::LineBelow),
+            'O' => Some(InsertPosition::LineAbove),
+            _ => None,
+        };
+        if let Some(position) = insert_position {
+            self.is_change = true;
+            self.mode = Mode::Insert;
+            return vec![Action::Insert(position)];
+        }
+        match character {
+            'x' => self.change(Action::Operate(Operator::Delete, Motion::Right, count)),
+            'X' => self.change(Action::Operate(Operator::Delete, Motion::Left, count)),
+            'D' => self.change(Action::Operate(Operator::Delete, Motion::LineEnd, 1)),
+            'C' => {
+                self.mode = Mode::Insert;
+                self.change(Action::Operate(Operator::Change, Motion::LineEnd, 1))
+            }
+            'Y' => vec![Action::OperateLines(Operator::Yank, count)],
+            'p' | 'P' => self.change(Action::Put {
+                before: character == 'P',
+                count,
+            }),
+            'u' => vec![Action::Undo(count)],
+            'v' => {
+                self.mode = Mode::Visual;
+                vec![Action::StartVisual]
+            }
+            ':' => vec![Action::CommandLine],
+            '/' => vec![Action::Command(Command::System(System::Search))],
+            _ => Vec::new(),
+        }
+    }
+
+    fn change(&mut self, action: Action) -> Vec<Action> {
+        self.is_change = true;
+        vec![action]
+    }
+
+    /// `.` : rejoue les touches de la dernière modification. Un nombre tapé avant `.`
+    /// remplace celui de la modification (`3.` après `2dd` supprime trois lignes) ;
+    /// une modification qui n'accepte pas de nombre, comme une insertion, est répétée
+    fn repeat_last_change(&mut self, count: Option<usize>) -> Vec<Action> {
+        self.keys.clear();
+        let mut keys = self.last_change.clone();
+        let mut repeat = 1;
+        if let Some(count) = count {
+            let uncounted = Self::without_count(&keys);
+            let takes_count = uncounted.first().is_some_and(|key| {
+                key.modifiers.is_empty() && matches!(key.code, Char('d' | 'c' | 'x' | 'X' | 'p' | 'P'))
+            });
+            if takes_count {
+                keys = count
+                    .to_string()
+                    .chars()
+                    .map(|digit| KeyEvent::new(Char(digit), KeyModifiers::NONE))
+                    .chain(uncounted)
+                    .collect();
+            } else {
+                repeat = count;
+            }
+        }
+        let mut actions = Vec::new();
+        for _ in 0..repeat {
+            for key in &keys {
+                actions.extend(self.handle_key(key));
+            }
+        }
+        actions
+    }
+
+    /// touches d'une commande sans ses nombres : celui tapé avant la commande,
+    /// et celui tapé entre l'opérateur et son déplacement (`d2w`)
+    fn without_count(keys: &[KeyEvent]) -> Vec<KeyEvent> {
+        let is_digit = |key: &KeyEvent| {
+            key.modifiers.is_empty() && matches!(key.code, Char(character) if character.is_ascii_digit())
+        };
+        let is_count_start = |key: &KeyEvent| is_digit(key) && key.code != Char('0');
+        let mut rest = keys;
+        if rest.first().is_some_and(is_count_start) {
+            let digits = rest.iter().take_while(|key| is_digit(key)).count();
+            rest = rest.get(digits..).unwrap_or_default();
+        }
+        let mut result = Vec::with_capacity(rest.len());
+        if let Some((first, after)) = rest.split_first() {
+            result.push(*first);
+            rest = after;
+            let is_operator =
+                first.modifiers.is_empty() && matches!(first.code, Char('d' | 'c' | 'y'));
+            if is_operator && rest.first().is_some_and(is_count_start) {
+                let digits = rest.iter().take_while(|key| is_digit(key)).count();
+                rest = rest.get(digits..).unwrap_or_default();
+            }
+        }
+        result.extend_from_slice(rest);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyCode::{self, Tab};
+
+    /// tape une suite de touches sans modificateur, retourne toutes les actions produites
+    fn type_keys(vim: &mut Vim, keys: &str) -> Vec<Action> {
+        keys.chars()
+            .flat_map(|character| vim.handle_key(&KeyEvent::new(Char(character), KeyModifiers::NONE)))
+            .collect()
+    }
+
+    fn press(vim: &mut Vim, code: KeyCode, modifiers: KeyModifiers) -> Vec<Action> {
+        vim.handle_key(&KeyEvent::new(code, modifiers))
+    }
+
+    #[test]
+    fn counts_multiply_motions_and_are_capped() {
+        let mut vim = Vim::default();
+        assert!(type_keys(&mut vim, "3j") == [Action::Move(Motion::Down, 3)]);
+        assert!(type_keys(&mut vim, "10l") == [Action::Move(Motion::Right, 10)]);
+        assert!(type_keys(&mut vim, "99999999k") == [Action::Move(Motion::Up, MAX_COUNT)]);
+        assert!(type_keys(&mut vim, "0") == [Action::Move(Motion::LineStart, 1)]);
+        assert!(type_keys(&mut vim, "2d3w") == [Action::Operate(Operator::Delete, Motion::WordForward, 6)]);
+    }
+
+    #[test]
+    fn doubled_operator_works_on_lines() {
+        let mut vim = Vim::default();
+        assert!(type_keys(&mut vim, "dd") == [Action::OperateLines(Operator::Delete, 1)]);
+        assert!(type_keys(&mut vim, "2dd") == [Action::OperateLines(Operator::Delete, 2)]);
+        assert!(type_keys(&mut vim, "dy").is_empty());
+        assert!(vim.mode() == Mode::Normal);
+    }
+
+    #[test]
+    fn change_word_enters_insert_until_escape() {
+        let mut vim = Vim::default();
+        assert!(type_keys(&mut vim, "cw") == [Action::Operate(Operator::Change, Motion::WordForward, 1)]);
+        assert!(vim.mode() == Mode::Insert);
+        let key = KeyEvent::new(Char('a'), KeyModifiers::NONE);
+        assert!(type_keys(&mut vim, "a") == [Action::Key(key)]);
+        assert!(press(&mut vim, Esc, KeyModifiers::NONE) == [Action::LeaveInsert]);
+        assert!(vim.mode() == Mode::Normal);
+    }
+
+    #[test]
+    fn goto_line_motions() {
+        let mut vim = Vim::default();
+        assert!(type_keys(&mut vim, "gg") == [Action::Move(Motion::GotoLine(1), 1)]);
+        assert!(type_keys(&mut vim, "5gg") == [Action::Move(Motion::GotoLine(5), 1)]);
+        assert!(type_keys(&mut vim, "G") == [Action::Move(Motion::LastLine, 1)]);
+        assert!(type_keys(&mut vim, "7G") == [Action::Move(Motion::GotoLine(7), 1)]);
+        assert!(type_keys(&mut vim, "dG") == [Action::Operate(Operator::Delete, Motion::LastLine, 1)]);
+    }
+
+    #[test]
+    fn dot_repeats_last_change() {
+        let mut vim = Vim::default();
+        type_keys(&mut vim, "2dd");
+        type_keys(&mut vim, "j");
+        assert!(type_keys(&mut vim, ".") == [Action::OperateLines(Operator::Delete, 2)]);
+        // le nombre tapé avant `.` remplace celui de la modification
+        assert!(type_keys(&mut vim, "3.") == [Action::OperateLines(Operator::Delete, 3)]);
+        assert!(type_keys(&mut vim, ".") == [Action::OperateLines(Operator::Delete, 3)]);
+        type_keys(&mut vim, "d2w");
+        assert!(type_keys(&mut vim, "4.") == [Action::Operate(Operator::Delete, Motion::WordForward, 4)]);
+    }
+
+    #[test]
+    fn dot_repeats_insertions_and_visual_changes() {
+        let mut vim = Vim::default();
+        type_keys(&mut vim, "ix");
+        press(&mut vim, Esc, KeyModifiers::NONE);
+        let insertions = type_keys(&mut vim, "2.");
+        let inserted = insertions.iter().filter(|action| matches!(action, Action::Key(_))).count();
+        assert_eq!(inserted, 2);
+        assert!(vim.mode() == Mode::Normal);
+
+        type_keys(&mut vim, "vjd");
+        let repeated = type_keys(&mut vim, ".");
+        assert!(repeated.contains(&Action::OperateSelection(Operator::Delete)));
+        assert!(vim.mode() == Mode::Normal);
+    }
+
+    #[test]
+    fn normal_mode_forwards_shortcuts_only() {
+        let mut vim = Vim::default();
+        let tab = KeyEvent::new(Tab, KeyModifiers::NONE);
+        assert!(press(&mut vim, Tab, KeyModifiers::NONE) == [Action::Shortcut(tab)]);
+        let undo = KeyEvent::new(Char('z'), KeyModifiers::CONTROL);
+        assert!(press(&mut vim, Char('z'), KeyModifiers::CONTROL) == [Action::Shortcut(undo)]);
+        type_keys(&mut vim, "v");
+        assert!(
+            press(&mut vim, Tab, KeyModifiers::NONE) == [Action::EndVisual, Action::Shortcut(tab)]
+        );
+        assert!(vim.mode() == Mode::Normal);
+        // un raccourci n'est pas une modification rejouée par `.`
+        assert!(type_keys(&mut vim, ".").is_empty());
+    }
+}
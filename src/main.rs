@@ -7,22 +7,25 @@
     clippy::integer_division
 )]
 mod editor;
-use editor::Editor;
-use std::path::Path;
+use editor::{parse_arguments, Editor};
+use std::{fs::File, path::Path};
 
 fn main() {
 
-    let args: Vec<String> = std::env::args().collect();
-    if args.len()>2 {
-        println!("wrong args\n");
-        return;
-    }
-    if args.len()==2 {
-        let filename = args.get(1).unwrap();
-        let file = Path::new(filename);
-        if !file.exists() || !file.is_file() {
-            println!("the path given is not a valid file.\n");
-        return;
+    // un chemin qui n'existe pas encore ouvre un nouveau fichier
+    for argument in parse_arguments(std::env::args().skip(1)) {
+        let file = Path::new(&argument.path);
+        if file.is_dir() {
+            eprintln!("the path given is a directory: {}\n", argument.path);
+            return;
+        }
+        if file.exists() && !file.is_file() {
+            eprintln!("the path given is not a valid file: {}\n", argument.path);
+            return;
+        }
+        if let Err(err) = file.exists().then(|| File::open(file)).transpose() {
+            eprintln!("the file given cannot be read: {} ({err})\n", argument.path);
+            return;
         }
     }
     